@@ -0,0 +1,24 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::env;
+
+fn main() {
+    // libpcre-sys reports every `pcre_8_NN` cfg flag that it knows, and those enabled by the
+    // version of the linked libpcre, so that items requiring a later libpcre can be left out.
+    println!("cargo:rerun-if-env-changed=DEP_PCRE_CHECK_CFGS");
+    if let Ok(cfgs) = env::var("DEP_PCRE_CHECK_CFGS") {
+        println!("cargo:rustc-check-cfg=cfg({})", cfgs.replace(',', ", "));
+    }
+    println!("cargo:rerun-if-env-changed=DEP_PCRE_CFGS");
+    if let Ok(cfgs) = env::var("DEP_PCRE_CFGS") {
+        for cfg in cfgs.split(',').filter(|cfg| !cfg.is_empty()) {
+            println!("cargo:rustc-cfg={}", cfg);
+        }
+    }
+}
@@ -8,16 +8,13 @@
 
 // This is a port of the pcre project's `pcredemo` sample using rust-pcre bindings.
 
-extern crate enum_set;
 extern crate getopts;
 extern crate pcre;
 
-use enum_set::{EnumSet};
 use getopts::{Options};
-use pcre::{CompileOption, Match, Pcre, pcre_version};
+use pcre::{CompileOptions, Match, Pcre, pcre_version};
 use std::collections::{BTreeMap};
 use std::env;
-use std::io::{stderr, Write};
 use std::string::{String};
 use std::vec::{Vec};
 
@@ -34,20 +31,18 @@ fn print_match(m: &Match, name_table: &BTreeMap<String, Vec<usize>>) {
     println!("Match succeeded at offset {}", m.group_start(0));
 
     // Show captured substrings by number.
-    let mut i = 0;
-    while i < m.string_count() {
-        println!("{}: {}", i, m.group(i));
-        i += 1;
+    for (i, group) in m.iter().enumerate().take(m.string_count()) {
+        println!("{}: {}", i, group.unwrap_or("<unset>"));
     }
 
     let name_count = name_table.len();
-    if name_count <= 0 {
+    if name_count == 0 {
         println!("No named substrings");
     } else {
         println!("Named substrings:");
         for (name, n_vec) in name_table.iter() {
             for n in n_vec.iter() {
-                println!("({}) {}: {}", *n, *name, m.group(*n));
+                println!("({}) {}: {}", *n, *name, m.get(*n).unwrap_or("<unset>"));
             }
         }
     }
@@ -64,7 +59,7 @@ fn main() {
     let opt_matches = match opts.parse(&args[1..]) {
         Ok(m)  => m,
         Err(f) => {
-            writeln!(stderr(), "Error: {}", f).unwrap();
+            eprintln!("Error: {}", f);
             //env::set_exit_status(1);
             return;
         }
@@ -81,16 +76,16 @@ fn main() {
     }
 
     let find_all = opt_matches.opt_present("g");
-    if opt_matches.free.len() == 0 {
-        writeln!(stderr(), "Error: No pattern").unwrap();
+    if opt_matches.free.is_empty() {
+        eprintln!("Error: No pattern");
         //env::set_exit_status(1);
         return;
     } else if opt_matches.free.len() == 1 {
-        writeln!(stderr(), "Error: No subject").unwrap();
+        eprintln!("Error: No subject");
         //env::set_exit_status(1);
         return;
     } else if opt_matches.free.len() > 2 {
-        writeln!(stderr(), "Error: Too many command line arguments").unwrap();
+        eprintln!("Error: Too many command line arguments");
         //env::set_exit_status(1);
         return;
     }
@@ -98,11 +93,10 @@ fn main() {
     let pattern = opt_matches.free[0].clone();
     let subject = opt_matches.free[1].clone();
 
-    let mut compile_options: EnumSet<CompileOption> = EnumSet::new();
-    compile_options.insert(CompileOption::DupNames);
-    let mut re = match Pcre::compile_with_options(&pattern, &compile_options) {
+    let compile_options = CompileOptions::DUPNAMES;
+    let re = match Pcre::compile_with_options(&pattern, &compile_options) {
         Err(err) => {
-            writeln!(stderr(), "Error: The pattern could not be compiled: {}", err).unwrap();
+            eprintln!("Error: The pattern could not be compiled: {}", err);
             //env::set_exit_status(1);
             return;
         },
@@ -133,7 +127,7 @@ fn main() {
                 Some(m) => m
             };
 
-            println!("");
+            println!();
             print_match(&m, &name_table);
 
             start_offset = m.group_end(0);
@@ -7,134 +7,344 @@
 // except according to those terms.
 
 extern crate bzip2;
+extern crate cc;
 extern crate pkg_config;
 extern crate tar;
 
 use bzip2::read::{BzDecoder};
 use std::env;
-use std::fs::{OpenOptions};
-use std::io::{ErrorKind};
-use std::path::{Path};
-use std::process::{Command};
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
 use tar::{Archive};
 
-const BUNDLED_PCRE_VERSION: &'static str = "8.39";
+const BUNDLED_PCRE_VERSION: &str = "8.39";
+
+/// The libraries to build and link, with the first libpcre version that provides each.
+fn libraries() -> Vec<(&'static str, &'static str)> {
+    let mut libraries = vec![("pcre", "8.20")];
+    if env::var_os("CARGO_FEATURE_PCRE16").is_some() {
+        libraries.push(("pcre16", "8.30"));
+    }
+    if env::var_os("CARGO_FEATURE_PCRE32").is_some() {
+        libraries.push(("pcre32", "8.32"));
+    }
+    libraries
+}
+
+/// How libpcre is found and linked.
+enum Mode {
+    /// Build the bundled libpcre and link it statically.
+    Vendored,
+
+    /// Link the system libpcre found by pkg-config.
+    PkgConfig,
+
+    /// Link the libpcre installed under a prefix given by `PCRE_SYS_PREFIX`.
+    Prefix(PathBuf)
+}
+
+impl Mode {
+    fn name(&self) -> &'static str {
+        match *self {
+            Mode::Vendored => "vendored",
+            Mode::PkgConfig => "pkg-config",
+            Mode::Prefix(_) => "prefix"
+        }
+    }
+}
+
+/// Reads the boolean environment variable `name`.
+fn env_flag(name: &str) -> Option<bool> {
+    println!("cargo:rerun-if-env-changed={}", name);
+    env::var(name).ok().map(|value| match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => true,
+        "0" | "false" | "no" | "off" => false,
+        _ => panic!("{} must be 0 or 1, not {:?}", name, value)
+    })
+}
+
+/// Chooses how to find and link libpcre from the `vendored` feature and the
+/// `PCRE_SYS_USE_PKG_CONFIG`, `PCRE_SYS_PREFIX` and `PCRE_SYS_STATIC` environment variables.
+fn mode(libraries: &[(&str, &str)], statik: Option<bool>) -> Mode {
+    let vendored = env::var_os("CARGO_FEATURE_VENDORED").is_some();
+    let use_pkg_config = env_flag("PCRE_SYS_USE_PKG_CONFIG");
+    println!("cargo:rerun-if-env-changed=PCRE_SYS_PREFIX");
+    let prefix = env::var_os("PCRE_SYS_PREFIX").map(PathBuf::from);
+
+    match (vendored, use_pkg_config, prefix) {
+        (true, Some(true), _) | (true, _, Some(_)) => {
+            panic!("the `vendored` feature cannot be combined with PCRE_SYS_USE_PKG_CONFIG=1 or PCRE_SYS_PREFIX");
+        },
+        (false, Some(true), Some(_)) => {
+            panic!("PCRE_SYS_USE_PKG_CONFIG=1 cannot be combined with PCRE_SYS_PREFIX");
+        },
+        (true, _, None) | (false, Some(false), None) => Mode::Vendored,
+        (false, _, Some(prefix)) => Mode::Prefix(prefix),
+        (false, Some(true), None) => Mode::PkgConfig,
+        // Prefer a system libpcre, and fall back to building the bundled one.
+        (false, None, None) => {
+            if libraries.iter().all(|&(name, version)| pkg_config_for(version, statik).cargo_metadata(false).probe(&format!("lib{}", name)).is_ok()) {
+                Mode::PkgConfig
+            } else {
+                Mode::Vendored
+            }
+        }
+    }
+}
+
+fn pkg_config_for(version: &str, statik: Option<bool>) -> pkg_config::Config {
+    let mut config = pkg_config::Config::new();
+    config.atleast_version(version);
+    config.statik(statik.unwrap_or(false));
+    config
+}
+
+/// Links the libraries found by pkg-config, and returns the libpcre version and include
+/// directory.
+fn link_pkg_config(libraries: &[(&str, &str)], statik: Option<bool>) -> (Option<String>, Option<PathBuf>) {
+    let mut found = (None, None);
+    for &(name, version) in libraries.iter() {
+        match pkg_config_for(version, statik).probe(&format!("lib{}", name)) {
+            Err(e) => panic!("failed to find lib{} {} or later using pkg-config: {}", name, version, e),
+            Ok(library) => {
+                if name == "pcre" {
+                    // pkg-config leaves out -I flags for the system include directory.
+                    let include = library.include_paths.into_iter().next()
+                        .or_else(|| pkg_config::get_variable("libpcre", "includedir").ok().map(PathBuf::from));
+                    found = (Some(library.version), include);
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Links the libraries installed under `prefix`, and returns the libpcre version and
+/// include directory.
+fn link_prefix(prefix: &Path, libraries: &[(&str, &str)], statik: bool) -> (Option<String>, Option<PathBuf>) {
+    println!("cargo:rustc-link-search=native={}", prefix.join("lib").display());
+    for &(name, _) in libraries.iter() {
+        println!("cargo:rustc-link-lib={}={}", if statik { "static" } else { "dylib" }, name);
+    }
+    let include = prefix.join("include");
+    (header_version(&include), Some(include))
+}
+
+/// Reads the libpcre version from the `pcre.h` header in `include`.
+fn header_version(include: &Path) -> Option<String> {
+    let header = BufReader::new(File::open(include.join("pcre.h")).ok()?);
+    let (mut major, mut minor) = (None, None);
+    for line in header.lines() {
+        let line = line.ok()?;
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (Some("#define"), Some("PCRE_MAJOR"), Some(value)) => major = Some(value.to_string()),
+            (Some("#define"), Some("PCRE_MINOR"), Some(value)) => minor = Some(value.to_string()),
+            _ => ()
+        }
+    }
+    Some(format!("{}.{}", major?, minor?))
+}
+
+// The oldest libpcre that can be linked, and the last libpcre 8.x release.
+const MIN_MINOR_VERSION: u32 = 20;
+const MAX_MINOR_VERSION: u32 = 45;
+
+/// Returns the `pcre_8_NN` cfg flags enabled by the libpcre `version`: one for each release
+/// from 8.20 up to and including `version`.
+///
+/// When the version is unknown, only libpcre 8.20 is assumed.
+fn version_cfgs(version: Option<&str>) -> Vec<String> {
+    let minor = version
+        .and_then(|version| version.strip_prefix("8."))
+        .map(|minor| minor.chars().take_while(char::is_ascii_digit).collect::<String>())
+        .and_then(|minor| minor.parse::<u32>().ok())
+        .unwrap_or(MIN_MINOR_VERSION);
+    (MIN_MINOR_VERSION..=minor.min(MAX_MINOR_VERSION)).map(|minor| format!("pcre_8_{}", minor)).collect()
+}
 
 fn main() {
-    if pkg_config::Config::new().atleast_version("8.20").find("libpcre").is_ok() {
-        return;
+    let libraries = libraries();
+    let statik = env_flag("PCRE_SYS_STATIC");
+    let mode = mode(&libraries, statik);
+
+    let (linked_statically, (version, include)) = match mode {
+        Mode::Vendored => {
+            if statik == Some(false) {
+                panic!("the bundled libpcre can only be linked statically; unset PCRE_SYS_STATIC or link a system libpcre");
+            }
+            let (root, include) = build_vendored(&libraries);
+            println!("cargo:root={}", root.display());
+            (true, (Some(BUNDLED_PCRE_VERSION.to_string()), Some(include)))
+        },
+        Mode::PkgConfig => (statik.unwrap_or(false), link_pkg_config(&libraries, statik)),
+        Mode::Prefix(ref prefix) => {
+            println!("cargo:root={}", prefix.display());
+            (statik.unwrap_or(false), link_prefix(prefix, &libraries, statik.unwrap_or(false)))
+        }
+    };
+
+    // Downstream crates see these as DEP_PCRE_MODE, DEP_PCRE_STATIC, DEP_PCRE_VERSION,
+    // DEP_PCRE_INCLUDE and DEP_PCRE_CFGS.
+    println!("cargo:mode={}", mode.name());
+    // Lets the tests require the pcre.h header where its location is always known.
+    println!("cargo:rustc-env=PCRE_SYS_MODE={}", mode.name());
+    println!("cargo:static={}", if linked_statically { 1 } else { 0 });
+    if let Some(ref version) = version {
+        println!("cargo:version={}", version);
+    }
+    if let Some(include) = include {
+        println!("cargo:include={}", include.display());
+        // Lets the tests check the bindings against the pcre.h header.
+        println!("cargo:rustc-env=PCRE_SYS_INCLUDE={}", include.display());
     }
 
-    let cargo_manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-    let out_dir = env::var("OUT_DIR").unwrap();
+    // `#[cfg(pcre_8_NN)]` marks items that require libpcre 8.NN or later. The pcre crate
+    // declares every flag from DEP_PCRE_CHECK_CFGS and enables the same flags from
+    // DEP_PCRE_CFGS.
+    let all_cfgs: Vec<String> = (MIN_MINOR_VERSION..=MAX_MINOR_VERSION).map(|minor| format!("pcre_8_{}", minor)).collect();
+    println!("cargo:rustc-check-cfg=cfg({})", all_cfgs.join(", "));
+    println!("cargo:check_cfgs={}", all_cfgs.join(","));
+    let cfgs = version_cfgs(version.as_deref());
+    for cfg in cfgs.iter() {
+        println!("cargo:rustc-cfg={}", cfg);
+    }
+    println!("cargo:cfgs={}", cfgs.join(","));
+}
+
+// The sources that every libpcre library is built from, without the `pcre_`, `pcre16_` or
+// `pcre32_` prefix.
+const COMMON_SOURCES: &[&str] = &[
+    "byte_order", "compile", "config", "dfa_exec", "exec", "fullinfo", "get", "globals",
+    "jit_compile", "maketables", "newline", "refcount", "string_utils", "study", "tables",
+    "ucd", "version", "xclass"
+];
+
+/// Returns the sources of the library `name` that are specific to its code unit width.
+fn width_sources(name: &str) -> &'static [&'static str] {
+    match name {
+        "pcre" => &["ord2utf8", "valid_utf8"],
+        "pcre16" => &["chartables", "ord2utf16", "utf16_utils", "valid_utf16"],
+        "pcre32" => &["chartables", "ord2utf32", "utf32_utils", "valid_utf32"],
+        _ => unreachable!()
+    }
+}
+
+/// Reads the value of `m4_define(name, [value])` from the bundled `configure.ac`.
+fn configure_define(configure_ac: &str, name: &str) -> String {
+    let prefix = format!("m4_define({}, [", name);
+    configure_ac.lines()
+        .filter_map(|line| line.trim().strip_prefix(prefix.as_str()))
+        .filter_map(|rest| rest.split(']').next())
+        .next()
+        .unwrap_or_else(|| panic!("`{}` is not defined in configure.ac", name))
+        .to_string()
+}
 
-    let ext_pathbuf = Path::new(&cargo_manifest_dir).join("ext");
+/// Returns the contents of `config.h` for the bundled build.
+fn config_h(libraries: &[(&str, &str)]) -> String {
+    let mut defines = vec![
+        ("HAVE_INTTYPES_H", "1"),
+        ("HAVE_LIMITS_H", "1"),
+        ("HAVE_MEMMOVE", "1"),
+        ("HAVE_STDINT_H", "1"),
+        ("HAVE_STDLIB_H", "1"),
+        ("HAVE_STRERROR", "1"),
+        ("HAVE_STRING_H", "1"),
+        ("LINK_SIZE", "2"),
+        ("MATCH_LIMIT", "10000000"),
+        ("MATCH_LIMIT_RECURSION", "MATCH_LIMIT"),
+        ("MAX_NAME_COUNT", "10000"),
+        ("MAX_NAME_SIZE", "32"),
+        ("NEWLINE", "10"),
+        ("PARENS_NEST_LIMIT", "250"),
+        ("PCRE_STATIC", "1"),
+        ("POSIX_MALLOC_THRESHOLD", "10")
+    ];
+    // The build script runs on the host, so the target is checked through Cargo.
+    if env::var_os("CARGO_CFG_UNIX").is_some() {
+        defines.push(("HAVE_UNISTD_H", "1"));
+    }
+    for &(name, _) in libraries.iter() {
+        defines.push(match name {
+            "pcre" => ("SUPPORT_PCRE8", "1"),
+            "pcre16" => ("SUPPORT_PCRE16", "1"),
+            _ => ("SUPPORT_PCRE32", "1")
+        });
+    }
+    if env::var_os("CARGO_FEATURE_JIT").is_some() {
+        defines.push(("SUPPORT_JIT", "1"));
+    }
+    if env::var_os("CARGO_FEATURE_UTF").is_some() {
+        defines.push(("SUPPORT_UTF", "1"));
+    }
+    if env::var_os("CARGO_FEATURE_UCP").is_some() {
+        defines.push(("SUPPORT_UCP", "1"));
+    }
+
+    let mut config_h = String::from("/* Generated by the libpcre-sys build script. */\n\n");
+    for &(name, value) in defines.iter() {
+        config_h.push_str(&format!("#define {} {}\n", name, value));
+    }
+    config_h
+}
 
-    let pcre_tbz2_pathbuf = ext_pathbuf.join(format!("pcre-{}.tar.bz2", BUNDLED_PCRE_VERSION));
+/// Builds the bundled libpcre with the `cc` crate and links it statically.
+///
+/// # Return value
+/// The installation root and the include directory.
+fn build_vendored(libraries: &[(&str, &str)]) -> (PathBuf, PathBuf) {
+    let cargo_manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+    let pcre_tbz2_pathbuf = Path::new(&cargo_manifest_dir).join("ext").join(format!("pcre-{}.tar.bz2", BUNDLED_PCRE_VERSION));
+    println!("cargo:rerun-if-changed={}", pcre_tbz2_pathbuf.display());
     let pcre_tbz2_f = OpenOptions::new().read(true).open(pcre_tbz2_pathbuf).unwrap();
     let decompressor = BzDecoder::new(pcre_tbz2_f);
 
     let mut archive = Archive::new(decompressor);
-    if !archive.unpack(&out_dir).is_ok() {
+    if archive.unpack(&out_dir).is_err() {
         panic!("failed to extract the tarball");
     }
 
-    let pcre_pathbuf = Path::new(&out_dir).join(format!("pcre-{}", BUNDLED_PCRE_VERSION));
-
-    if cfg!(unix) {
-        let mut cmd = Command::new("autoreconf");
-        cmd.current_dir(&pcre_pathbuf);
-        let status = match cmd.status() {
-            Err(ref e) if e.kind() == ErrorKind::NotFound => {
-                panic!("failed to execute `autoreconf`: {}. Are the Autotools installed?", e);
-            },
-            Err(e) => {
-                panic!("failed to execute `autoreconf`: {}", e);
-            },
-            Ok(status) => status
-        };
-        if !status.success() {
-            panic!("`autoreconf` did not run successfully.");
-        }
+    let src_dir = out_dir.join(format!("pcre-{}", BUNDLED_PCRE_VERSION));
+    let build_dir = out_dir.join("build");
+    let include_dir = out_dir.join("include");
+    fs::create_dir_all(&build_dir).unwrap();
+    fs::create_dir_all(&include_dir).unwrap();
 
-        let mut cmd = Command::new("./configure");
-        cmd.arg("--with-pic");
-        cmd.arg("--disable-shared");
-        cmd.arg("--disable-cpp");
-        cmd.arg("--enable-jit");
-        cmd.arg("--enable-utf");
-        cmd.arg("--enable-unicode-properties");
-        cmd.arg(format!("--prefix={}", Path::new(&out_dir).display()));
-        cmd.current_dir(&pcre_pathbuf);
-        let status = match cmd.status() {
-            Err(e) => {
-                panic!("failed to execute `./configure`: {}", e);
-            },
-            Ok(status) => status
-        };
-        if !status.success() {
-            panic!("`./configure --with-pic ...` did not run successfully.");
-        }
+    // Generate what `./configure` and `make` would: config.h, pcre.h from pcre.h.in, and
+    // the default character tables.
+    fs::write(build_dir.join("config.h"), config_h(libraries)).unwrap();
 
-        let mut cmd = Command::new("make");
-        cmd.arg("install");
-        cmd.current_dir(&pcre_pathbuf);
-        let status = match cmd.status() {
-            Err(ref e) if e.kind() == ErrorKind::NotFound => {
-                panic!("failed to execute `make`: {}. Is GNU Make installed?", e);
-            },
-            Err(e) => {
-                panic!("failed to execute `make`: {}", e);
-            },
-            Ok(status) => status
-        };
-        if !status.success() {
-            panic!("`make install` did not run successfully.");
-        }
+    let configure_ac = fs::read_to_string(src_dir.join("configure.ac")).unwrap();
+    let pcre_h = fs::read_to_string(src_dir.join("pcre.h.in")).unwrap()
+        .replace("@PCRE_MAJOR@", &configure_define(&configure_ac, "pcre_major"))
+        .replace("@PCRE_MINOR@", &configure_define(&configure_ac, "pcre_minor"))
+        .replace("@PCRE_PRERELEASE@", &configure_define(&configure_ac, "pcre_prerelease"))
+        .replace("@PCRE_DATE@", &configure_define(&configure_ac, "pcre_date"));
+    fs::write(include_dir.join("pcre.h"), pcre_h).unwrap();
 
-        println!("cargo:rustc-link-search=native={}", Path::new(&out_dir).join("lib").as_path().display());
-    } else {
-        let mut cmd = Command::new("cmake");
-        cmd.arg(".");
-        cmd.arg("-DBUILD_SHARED_LIBS=OFF");
-        cmd.arg("-DPCRE_BUILD_PCRECPP=OFF");
-        cmd.arg("-DPCRE_BUILD_PCREGREP=OFF");
-        cmd.arg("-DPCRE_BUILD_TESTS=OFF");
-        cmd.arg("-DPCRE_BUILD_PCRE8=ON");
-        cmd.arg("-DPCRE_SUPPORT_JIT=ON");
-        cmd.arg("-DPCRE_SUPPORT_UTF=ON");
-        cmd.arg("-DPCRE_SUPPORT_UNICODE_PROPERTIES=ON");
-        cmd.current_dir(&pcre_pathbuf);
-        let status = match cmd.status() {
-            Err(ref e) if e.kind() == ErrorKind::NotFound => {
-                panic!("failed to execute `cmake`: {}. Is CMake installed?", e);
-            },
-            Err(e) => {
-                panic!("failed to execute `cmake`: {}", e);
-            },
-            Ok(status) => status
-        };
-        if !status.success() {
-            panic!("`cmake . -DBUILD_SHARED_LIBS=OFF ...` did not run successfully.");
-        }
+    fs::copy(src_dir.join("pcre_chartables.c.dist"), build_dir.join("pcre_chartables.c")).unwrap();
 
-        let mut cmd = Command::new("cmake");
-        cmd.arg("--build").arg(".").current_dir(&pcre_pathbuf);
-        let status = match cmd.status() {
-            Err(ref e) if e.kind() == ErrorKind::NotFound => {
-                panic!("failed to execute `cmake`: {}. Is CMake installed?", e);
-            },
-            Err(e) => {
-                panic!("failed to execute `cmake`: {}", e);
-            },
-            Ok(status) => status
-        };
-        if !status.success() {
-            panic!("`cmake --build .` did not run successfully.");
+    for &(name, _) in libraries.iter() {
+        let mut build = cc::Build::new();
+        build.include(&build_dir)
+            .include(&include_dir)
+            .include(&src_dir)
+            .define("HAVE_CONFIG_H", None)
+            .warnings(false);
+        for source in COMMON_SOURCES.iter().chain(width_sources(name).iter()) {
+            build.file(src_dir.join(format!("{}_{}.c", name, source)));
         }
-
-        println!("cargo:rustc-link-search=native={}", pcre_pathbuf.as_path().display());
+        if name == "pcre" {
+            build.file(build_dir.join("pcre_chartables.c"));
+        }
+        // Emits the cargo:rustc-link-lib=static and cargo:rustc-link-search lines.
+        build.compile(name);
     }
 
-    println!("cargo:rustc-link-lib=static=pcre");
+    (out_dir, include_dir)
 }
@@ -8,44 +8,233 @@
 
 extern crate libc;
 
-use libc::{c_char, c_int, c_uchar, c_ulong, c_void};
+use libc::{c_char, c_int, c_uchar, c_ulong, c_void, size_t};
 use std::option::{Option};
 use std::ptr;
 
 #[allow(non_camel_case_types)]
 pub type compile_options = c_int;
 #[allow(non_camel_case_types)]
+pub type config_what = c_int;
+#[allow(non_camel_case_types)]
 pub type exec_options = c_int;
 #[allow(non_camel_case_types)]
 pub type fullinfo_field = c_int;
 #[allow(non_camel_case_types)]
 pub type study_options = c_int;
 
+// Compile options
+pub const PCRE_CASELESS: compile_options = 0x00000001;
+pub const PCRE_MULTILINE: compile_options = 0x00000002;
+pub const PCRE_DOTALL: compile_options = 0x00000004;
+pub const PCRE_EXTENDED: compile_options = 0x00000008;
+pub const PCRE_DOLLAR_ENDONLY: compile_options = 0x00000020;
+pub const PCRE_EXTRA: compile_options = 0x00000040;
+pub const PCRE_UNGREEDY: compile_options = 0x00000200;
 pub const PCRE_UTF8: compile_options = 0x00000800;
+pub const PCRE_UTF16: compile_options = 0x00000800;
+pub const PCRE_UTF32: compile_options = 0x00000800;
+pub const PCRE_NO_AUTO_CAPTURE: compile_options = 0x00001000;
+pub const PCRE_AUTO_CALLOUT: compile_options = 0x00004000;
+pub const PCRE_NEVER_UTF: compile_options = 0x00010000;
+pub const PCRE_NO_AUTO_POSSESS: compile_options = 0x00020000;
+pub const PCRE_FIRSTLINE: compile_options = 0x00040000;
+pub const PCRE_DUPNAMES: compile_options = 0x00080000;
+pub const PCRE_JAVASCRIPT_COMPAT: compile_options = 0x02000000;
+pub const PCRE_UCP: compile_options = 0x20000000;
 
-// PCRE_NO_UTF8_CHECK is both a compile and exec option
+// Options that are both compile and exec options
+pub const PCRE_ANCHORED: c_int = 0x00000010;
 pub const PCRE_NO_UTF8_CHECK: c_int = 0x00002000;
+pub const PCRE_NO_UTF16_CHECK: c_int = 0x00002000;
+pub const PCRE_NO_UTF32_CHECK: c_int = 0x00002000;
+pub const PCRE_NEWLINE_CR: c_int = 0x00100000;
+pub const PCRE_NEWLINE_LF: c_int = 0x00200000;
+pub const PCRE_NEWLINE_CRLF: c_int = 0x00300000;
+pub const PCRE_NEWLINE_ANY: c_int = 0x00400000;
+pub const PCRE_NEWLINE_ANYCRLF: c_int = 0x00500000;
+pub const PCRE_BSR_ANYCRLF: c_int = 0x00800000;
+pub const PCRE_BSR_UNICODE: c_int = 0x01000000;
+pub const PCRE_NO_START_OPTIMIZE: c_int = 0x04000000;
+pub const PCRE_NO_START_OPTIMISE: c_int = 0x04000000;
+
+// Exec options, also used by pcre_dfa_exec() and pcre_jit_exec()
+pub const PCRE_NOTBOL: exec_options = 0x00000080;
+pub const PCRE_NOTEOL: exec_options = 0x00000100;
+pub const PCRE_NOTEMPTY: exec_options = 0x00000400;
+pub const PCRE_PARTIAL_SOFT: exec_options = 0x00008000;
+pub const PCRE_PARTIAL: exec_options = 0x00008000;
+pub const PCRE_DFA_SHORTEST: exec_options = 0x00010000;
+pub const PCRE_DFA_RESTART: exec_options = 0x00020000;
+pub const PCRE_PARTIAL_HARD: exec_options = 0x08000000;
+pub const PCRE_NOTEMPTY_ATSTART: exec_options = 0x10000000;
 
+// Error codes returned by the exec and substring functions
 pub const PCRE_ERROR_NOMATCH: c_int = -1;
 pub const PCRE_ERROR_NULL: c_int = -2;
+pub const PCRE_ERROR_BADOPTION: c_int = -3;
+pub const PCRE_ERROR_BADMAGIC: c_int = -4;
+pub const PCRE_ERROR_UNKNOWN_OPCODE: c_int = -5;
+pub const PCRE_ERROR_UNKNOWN_NODE: c_int = -5;
+pub const PCRE_ERROR_NOMEMORY: c_int = -6;
+pub const PCRE_ERROR_NOSUBSTRING: c_int = -7;
+pub const PCRE_ERROR_MATCHLIMIT: c_int = -8;
+pub const PCRE_ERROR_CALLOUT: c_int = -9;
+pub const PCRE_ERROR_BADUTF8: c_int = -10;
+pub const PCRE_ERROR_BADUTF16: c_int = -10;
+pub const PCRE_ERROR_BADUTF32: c_int = -10;
+pub const PCRE_ERROR_BADUTF8_OFFSET: c_int = -11;
+pub const PCRE_ERROR_BADUTF16_OFFSET: c_int = -11;
+pub const PCRE_ERROR_PARTIAL: c_int = -12;
+pub const PCRE_ERROR_BADPARTIAL: c_int = -13;
+pub const PCRE_ERROR_INTERNAL: c_int = -14;
+pub const PCRE_ERROR_BADCOUNT: c_int = -15;
+pub const PCRE_ERROR_DFA_UITEM: c_int = -16;
+pub const PCRE_ERROR_DFA_UCOND: c_int = -17;
+pub const PCRE_ERROR_DFA_UMLIMIT: c_int = -18;
+pub const PCRE_ERROR_DFA_WSSIZE: c_int = -19;
+pub const PCRE_ERROR_DFA_RECURSE: c_int = -20;
+pub const PCRE_ERROR_RECURSIONLIMIT: c_int = -21;
+pub const PCRE_ERROR_NULLWSLIMIT: c_int = -22;
+pub const PCRE_ERROR_BADNEWLINE: c_int = -23;
+pub const PCRE_ERROR_BADOFFSET: c_int = -24;
+pub const PCRE_ERROR_SHORTUTF8: c_int = -25;
+pub const PCRE_ERROR_SHORTUTF16: c_int = -25;
+pub const PCRE_ERROR_RECURSELOOP: c_int = -26;
+pub const PCRE_ERROR_JIT_STACKLIMIT: c_int = -27;
+pub const PCRE_ERROR_BADMODE: c_int = -28;
+pub const PCRE_ERROR_BADENDIANNESS: c_int = -29;
+pub const PCRE_ERROR_DFA_BADRESTART: c_int = -30;
+pub const PCRE_ERROR_JIT_BADOPTION: c_int = -31;
+pub const PCRE_ERROR_BADLENGTH: c_int = -32;
+pub const PCRE_ERROR_UNSET: c_int = -33;
+
+// Reasons for PCRE_ERROR_BADUTF8, PCRE_ERROR_BADUTF16 and PCRE_ERROR_BADUTF32, as returned in ovector[1]
+pub const PCRE_UTF8_ERR0: c_int = 0;
+pub const PCRE_UTF8_ERR1: c_int = 1;
+pub const PCRE_UTF8_ERR2: c_int = 2;
+pub const PCRE_UTF8_ERR3: c_int = 3;
+pub const PCRE_UTF8_ERR4: c_int = 4;
+pub const PCRE_UTF8_ERR5: c_int = 5;
+pub const PCRE_UTF8_ERR6: c_int = 6;
+pub const PCRE_UTF8_ERR7: c_int = 7;
+pub const PCRE_UTF8_ERR8: c_int = 8;
+pub const PCRE_UTF8_ERR9: c_int = 9;
+pub const PCRE_UTF8_ERR10: c_int = 10;
+pub const PCRE_UTF8_ERR11: c_int = 11;
+pub const PCRE_UTF8_ERR12: c_int = 12;
+pub const PCRE_UTF8_ERR13: c_int = 13;
+pub const PCRE_UTF8_ERR14: c_int = 14;
+pub const PCRE_UTF8_ERR15: c_int = 15;
+pub const PCRE_UTF8_ERR16: c_int = 16;
+pub const PCRE_UTF8_ERR17: c_int = 17;
+pub const PCRE_UTF8_ERR18: c_int = 18;
+pub const PCRE_UTF8_ERR19: c_int = 19;
+pub const PCRE_UTF8_ERR20: c_int = 20;
+pub const PCRE_UTF8_ERR21: c_int = 21;
+pub const PCRE_UTF8_ERR22: c_int = 22;
+pub const PCRE_UTF16_ERR0: c_int = 0;
+pub const PCRE_UTF16_ERR1: c_int = 1;
+pub const PCRE_UTF16_ERR2: c_int = 2;
+pub const PCRE_UTF16_ERR3: c_int = 3;
+pub const PCRE_UTF16_ERR4: c_int = 4;
+pub const PCRE_UTF32_ERR0: c_int = 0;
+pub const PCRE_UTF32_ERR1: c_int = 1;
+pub const PCRE_UTF32_ERR2: c_int = 2;
+pub const PCRE_UTF32_ERR3: c_int = 3;
 
+pub const PCRE_INFO_OPTIONS: fullinfo_field = 0;
+pub const PCRE_INFO_SIZE: fullinfo_field = 1;
 pub const PCRE_INFO_CAPTURECOUNT: fullinfo_field = 2;
+pub const PCRE_INFO_BACKREFMAX: fullinfo_field = 3;
+pub const PCRE_INFO_FIRSTBYTE: fullinfo_field = 4;
+pub const PCRE_INFO_FIRSTCHAR: fullinfo_field = 4;
+pub const PCRE_INFO_FIRSTTABLE: fullinfo_field = 5;
+pub const PCRE_INFO_LASTLITERAL: fullinfo_field = 6;
 pub const PCRE_INFO_NAMEENTRYSIZE: fullinfo_field = 7;
 pub const PCRE_INFO_NAMECOUNT: fullinfo_field = 8;
 pub const PCRE_INFO_NAMETABLE: fullinfo_field = 9;
+pub const PCRE_INFO_STUDYSIZE: fullinfo_field = 10;
+pub const PCRE_INFO_DEFAULT_TABLES: fullinfo_field = 11;
+pub const PCRE_INFO_OKPARTIAL: fullinfo_field = 12;
+pub const PCRE_INFO_JCHANGED: fullinfo_field = 13;
+pub const PCRE_INFO_HASCRORLF: fullinfo_field = 14;
+pub const PCRE_INFO_MINLENGTH: fullinfo_field = 15;
+pub const PCRE_INFO_JIT: fullinfo_field = 16;
+pub const PCRE_INFO_JITSIZE: fullinfo_field = 17;
+pub const PCRE_INFO_MAXLOOKBEHIND: fullinfo_field = 18;
+pub const PCRE_INFO_FIRSTCHARACTER: fullinfo_field = 19;
+pub const PCRE_INFO_FIRSTCHARACTERFLAGS: fullinfo_field = 20;
+pub const PCRE_INFO_REQUIREDCHAR: fullinfo_field = 21;
+pub const PCRE_INFO_REQUIREDCHARFLAGS: fullinfo_field = 22;
+pub const PCRE_INFO_MATCHLIMIT: fullinfo_field = 23;
+pub const PCRE_INFO_RECURSIONLIMIT: fullinfo_field = 24;
+pub const PCRE_INFO_MATCH_EMPTY: fullinfo_field = 25;
+
+pub const PCRE_CONFIG_UTF8: config_what = 0;
+pub const PCRE_CONFIG_NEWLINE: config_what = 1;
+pub const PCRE_CONFIG_LINK_SIZE: config_what = 2;
+pub const PCRE_CONFIG_POSIX_MALLOC_THRESHOLD: config_what = 3;
+pub const PCRE_CONFIG_MATCH_LIMIT: config_what = 4;
+pub const PCRE_CONFIG_STACKRECURSE: config_what = 5;
+pub const PCRE_CONFIG_UNICODE_PROPERTIES: config_what = 6;
+pub const PCRE_CONFIG_MATCH_LIMIT_RECURSION: config_what = 7;
+pub const PCRE_CONFIG_BSR: config_what = 8;
+pub const PCRE_CONFIG_JIT: config_what = 9;
+pub const PCRE_CONFIG_UTF16: config_what = 10;
+pub const PCRE_CONFIG_JITTARGET: config_what = 11;
+pub const PCRE_CONFIG_UTF32: config_what = 12;
+pub const PCRE_CONFIG_PARENS_LIMIT: config_what = 13;
+
+pub const PCRE_STUDY_JIT_COMPILE: study_options = 0x0001;
+pub const PCRE_STUDY_JIT_PARTIAL_SOFT_COMPILE: study_options = 0x0002;
+pub const PCRE_STUDY_JIT_PARTIAL_HARD_COMPILE: study_options = 0x0004;
+pub const PCRE_STUDY_EXTRA_NEEDED: study_options = 0x0008;
 
-//const PCRE_EXTRA_STUDY_DATA: c_ulong = 0x0001;
-const PCRE_EXTRA_MATCH_LIMIT: c_ulong = 0x0002;
-//const PCRE_EXTRA_CALLOUT_DATA: c_ulong = 0x0004;
-//const PCRE_EXTRA_TABLES: c_ulong = 0x0008;
-const PCRE_EXTRA_MATCH_LIMIT_RECURSION: c_ulong = 0x0010;
-const PCRE_EXTRA_MARK: c_ulong = 0x0020;
-//const PCRE_EXTRA_EXECUTABLE_JIT: c_ulong = 0x0040;
+// The flags of pcre_extra that tell which fields are set
+pub const PCRE_EXTRA_STUDY_DATA: c_ulong = 0x0001;
+pub const PCRE_EXTRA_MATCH_LIMIT: c_ulong = 0x0002;
+pub const PCRE_EXTRA_CALLOUT_DATA: c_ulong = 0x0004;
+pub const PCRE_EXTRA_TABLES: c_ulong = 0x0008;
+pub const PCRE_EXTRA_MATCH_LIMIT_RECURSION: c_ulong = 0x0010;
+pub const PCRE_EXTRA_MARK: c_ulong = 0x0020;
+pub const PCRE_EXTRA_EXECUTABLE_JIT: c_ulong = 0x0040;
 
 #[allow(non_camel_case_types)]
 pub enum pcre {}
 
 #[allow(non_camel_case_types)]
+pub enum pcre_jit_stack {}
+
+/// Returns the JIT stack to use for a match, given the data passed to
+/// `pcre_assign_jit_stack()`.
+#[allow(non_camel_case_types)]
+pub type pcre_jit_callback = Option<extern "C" fn(data: *mut c_void) -> *mut pcre_jit_stack>;
+
+/// The data passed to the `pcre_callout` function at each callout point.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct pcre_callout_block {
+    pub version: c_int,
+    pub callout_number: c_int,
+    pub offset_vector: *mut c_int,
+    pub subject: *const c_char,
+    pub subject_length: c_int,
+    pub start_match: c_int,
+    pub current_position: c_int,
+    pub capture_top: c_int,
+    pub capture_last: c_int,
+    pub callout_data: *mut c_void,
+    // Version 1
+    pub pattern_position: c_int,
+    pub next_item_length: c_int,
+    // Version 2
+    pub mark: *const c_uchar
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy)]
 #[repr(C)]
 pub struct pcre_extra {
     flags: c_ulong,
@@ -71,6 +260,9 @@ impl pcre_extra {
     }
 
     /// Sets the match limit to `limit` instead of using PCRE's default.
+    ///
+    /// `pcre_exec()` returns `PCRE_ERROR_MATCHLIMIT` or `PCRE_ERROR_RECURSIONLIMIT` for matches
+    /// that exceed the limit.
     pub fn set_match_limit(&mut self, limit: u32) {
         self.flags |= PCRE_EXTRA_MATCH_LIMIT;
         self.match_limit_ = limit as c_ulong;
@@ -88,17 +280,29 @@ impl pcre_extra {
     }
 
     /// Sets the recursion depth limit to `limit` instead of using PCRE's default.
+    ///
+    /// `pcre_exec()` returns `PCRE_ERROR_MATCHLIMIT` or `PCRE_ERROR_RECURSIONLIMIT` for matches
+    /// that exceed the limit.
     pub fn set_match_limit_recursion(&mut self, limit: u32) {
         self.flags |= PCRE_EXTRA_MATCH_LIMIT_RECURSION;
-        self.match_limit_ = limit as c_ulong;
+        self.match_limit_recursion_ = limit as c_ulong;
     }
 
     /// Sets the mark field.
+    ///
+    /// # Safety
+    /// `mark` must stay valid for as long as the extra block is passed to `pcre_exec()`
+    /// with the mark field set.
     pub unsafe fn set_mark(&mut self, mark: &mut *mut c_uchar) {
         self.flags |= PCRE_EXTRA_MARK;
         self.mark = mark as *mut *mut c_uchar;
     }
 
+    /// Returns `true` if the mark field is set.
+    pub fn has_mark(&self) -> bool {
+        (self.flags & PCRE_EXTRA_MARK) != 0
+    }
+
     /// Unsets the mark field. PCRE will not save mark names when matching the compiled regular expression.
     pub fn unset_mark(&mut self) {
         self.flags &= !PCRE_EXTRA_MARK;
@@ -106,16 +310,137 @@ impl pcre_extra {
     }
 }
 
+// The 16-bit and 32-bit libraries use the same extra block, except that the mark field
+// points to a name made of 16-bit or 32-bit code units.
+#[allow(non_camel_case_types)]
+#[cfg(feature = "pcre16")]
+pub type pcre16_extra = pcre_extra;
+#[allow(non_camel_case_types)]
+#[cfg(feature = "pcre32")]
+pub type pcre32_extra = pcre_extra;
+
+#[allow(non_camel_case_types)]
+#[cfg(feature = "pcre16")]
+pub enum pcre16 {}
+#[allow(non_camel_case_types)]
+#[cfg(feature = "pcre32")]
+pub enum pcre32 {}
+
 #[link(name = "pcre")]
-extern {
-    pub static pcre_free: extern "C" fn(ptr: *mut c_void);
+extern "C" {
+    /// The function that libpcre allocates compiled patterns, study data and other memory
+    /// with. Defaults to `malloc()`.
+    pub static mut pcre_malloc: extern "C" fn(size: size_t) -> *mut c_void;
+    /// The function that frees memory allocated with `pcre_malloc`. Defaults to `free()`.
+    pub static mut pcre_free: extern "C" fn(ptr: *mut c_void);
+    /// The function that allocates the frames of `pcre_exec()` when libpcre was built not to
+    /// recurse on the machine stack. Defaults to `malloc()`.
+    pub static mut pcre_stack_malloc: extern "C" fn(size: size_t) -> *mut c_void;
+    /// The function that frees memory allocated with `pcre_stack_malloc`. Defaults to `free()`.
+    pub static mut pcre_stack_free: extern "C" fn(ptr: *mut c_void);
+    /// The function called at callout points, `(?C)`, while matching. A return value of zero
+    /// continues matching, greater than zero fails at the current point, and less than zero
+    /// aborts the match with that error code.
+    pub static mut pcre_callout: Option<extern "C" fn(block: *mut pcre_callout_block) -> c_int>;
+    /// The function called at the start of each parenthesized group while compiling. A
+    /// non-zero return value aborts the compilation with error 85 ("parentheses are too
+    /// deeply nested (stack check)").
+    #[cfg(pcre_8_35)]
+    pub static mut pcre_stack_guard: Option<extern "C" fn() -> c_int>;
 
+    /// Compiles a regular expression.
     pub fn pcre_compile(pattern: *const c_char, options: compile_options, errptr: *mut *const c_char, erroffset: *mut c_int, tableptr: *const c_uchar) -> *mut pcre;
+    /// Compiles a regular expression, also returning the numeric code of the error.
+    pub fn pcre_compile2(pattern: *const c_char, options: compile_options, errorcodeptr: *mut c_int, errptr: *mut *const c_char, erroffset: *mut c_int, tableptr: *const c_uchar) -> *mut pcre;
+    /// Reports a build-time option of libpcre.
+    pub fn pcre_config(what: config_what, where_: *mut c_void) -> c_int;
+    /// Copies the named captured substring into `buffer`.
+    pub fn pcre_copy_named_substring(code: *const pcre, subject: *const c_char, ovector: *mut c_int, stringcount: c_int, stringname: *const c_char, buffer: *mut c_char, buffersize: c_int) -> c_int;
+    /// Copies the captured substring `stringnumber` into `buffer`.
+    pub fn pcre_copy_substring(subject: *const c_char, ovector: *mut c_int, stringcount: c_int, stringnumber: c_int, buffer: *mut c_char, buffersize: c_int) -> c_int;
+    /// Matches a compiled regular expression with the alternative, DFA matching algorithm.
+    pub fn pcre_dfa_exec(code: *const pcre, extra: *const pcre_extra, subject: *const c_char, length: c_int, startoffset: c_int, options: exec_options, ovector: *mut c_int, ovecsize: c_int, workspace: *mut c_int, wscount: c_int) -> c_int;
+    /// Matches a compiled regular expression.
     pub fn pcre_exec(code: *const pcre, extra: *const pcre_extra, subject: *const c_char, length: c_int, startoffset: c_int, options: exec_options, ovector: *mut c_int, ovecsize: c_int) -> c_int;
+    /// Frees a substring returned by `pcre_get_substring()` or `pcre_get_named_substring()`.
+    pub fn pcre_free_substring(stringptr: *const c_char);
+    /// Frees a list returned by `pcre_get_substring_list()`.
+    pub fn pcre_free_substring_list(stringptr: *mut *const c_char);
+    /// Frees the study data returned by `pcre_study()`.
     pub fn pcre_free_study(extra: *mut pcre_extra);
+    /// Reports information about a compiled regular expression.
     pub fn pcre_fullinfo(code: *const pcre, extra: *const pcre_extra, what: fullinfo_field, where_: *mut c_void) -> c_int;
+    /// Returns the named captured substring in memory obtained from `pcre_malloc`.
+    pub fn pcre_get_named_substring(code: *const pcre, subject: *const c_char, ovector: *mut c_int, stringcount: c_int, stringname: *const c_char, stringptr: *mut *const c_char) -> c_int;
+    /// Returns the number of the capture group named `name`.
+    pub fn pcre_get_stringnumber(code: *const pcre, name: *const c_char) -> c_int;
+    /// Returns the first and last entries of the name table for the capture groups named
+    /// `name`, which may be several when PCRE_DUPNAMES is set.
+    pub fn pcre_get_stringtable_entries(code: *const pcre, name: *const c_char, first: *mut *mut c_char, last: *mut *mut c_char) -> c_int;
+    /// Returns the captured substring `stringnumber` in memory obtained from `pcre_malloc`.
+    pub fn pcre_get_substring(subject: *const c_char, ovector: *mut c_int, stringcount: c_int, stringnumber: c_int, stringptr: *mut *const c_char) -> c_int;
+    /// Returns all the captured substrings as a NULL-terminated list in memory obtained from
+    /// `pcre_malloc`.
+    pub fn pcre_get_substring_list(subject: *const c_char, ovector: *mut c_int, stringcount: c_int, listptr: *mut *mut *const c_char) -> c_int;
+    /// Builds character tables for the current locale in memory obtained from `pcre_malloc`.
+    pub fn pcre_maketables() -> *const c_uchar;
     // Note: libpcre's pcre_refcount() function is not thread-safe.
+    /// Adjusts the reference count of a compiled regular expression.
     pub fn pcre_refcount(code: *mut pcre, adjust: c_int) -> c_int;
+    /// Studies a compiled regular expression, and JIT compiles it if requested.
     pub fn pcre_study(code: *const pcre, options: study_options, errptr: *mut *const c_char) -> *mut pcre_extra;
+    /// Returns the version and release date of libpcre.
     pub fn pcre_version() -> *const c_char;
+
+    /// Converts a compiled regular expression saved on a host with the other byte order.
+    #[cfg(pcre_8_30)]
+    pub fn pcre_pattern_to_host_byte_order(code: *mut pcre, extra: *mut pcre_extra, tables: *const c_uchar) -> c_int;
+
+    /// Matches a JIT compiled regular expression, skipping the checks of `pcre_exec()`.
+    #[cfg(pcre_8_32)]
+    pub fn pcre_jit_exec(code: *const pcre, extra: *const pcre_extra, subject: *const c_char, length: c_int, startoffset: c_int, options: exec_options, ovector: *mut c_int, ovecsize: c_int, jstack: *mut pcre_jit_stack) -> c_int;
+    /// Allocates a JIT stack that starts at `startsize` bytes and grows up to `maxsize` bytes.
+    pub fn pcre_jit_stack_alloc(startsize: c_int, maxsize: c_int) -> *mut pcre_jit_stack;
+    /// Frees a JIT stack.
+    pub fn pcre_jit_stack_free(stack: *mut pcre_jit_stack);
+    /// Makes JIT matches of a studied regular expression use the stack returned by `callback`,
+    /// or `data` itself if `callback` is `None`.
+    pub fn pcre_assign_jit_stack(extra: *mut pcre_extra, callback: pcre_jit_callback, data: *mut c_void);
+    /// Frees the unused memory of the JIT compiler's executable memory allocator.
+    #[cfg(pcre_8_34)]
+    pub fn pcre_jit_free_unused_memory();
+}
+
+#[cfg(feature = "pcre16")]
+#[link(name = "pcre16")]
+extern "C" {
+    /// The function that the 16-bit library allocates memory with. Defaults to `malloc()`.
+    pub static mut pcre16_malloc: extern "C" fn(size: size_t) -> *mut c_void;
+    /// The function that frees memory allocated with `pcre16_malloc`. Defaults to `free()`.
+    pub static mut pcre16_free: extern "C" fn(ptr: *mut c_void);
+
+    pub fn pcre16_compile(pattern: *const u16, options: compile_options, errptr: *mut *const c_char, erroffset: *mut c_int, tableptr: *const c_uchar) -> *mut pcre16;
+    pub fn pcre16_compile2(pattern: *const u16, options: compile_options, errorcodeptr: *mut c_int, errptr: *mut *const c_char, erroffset: *mut c_int, tableptr: *const c_uchar) -> *mut pcre16;
+    pub fn pcre16_exec(code: *const pcre16, extra: *const pcre16_extra, subject: *const u16, length: c_int, startoffset: c_int, options: exec_options, ovector: *mut c_int, ovecsize: c_int) -> c_int;
+    pub fn pcre16_free_study(extra: *mut pcre16_extra);
+    pub fn pcre16_fullinfo(code: *const pcre16, extra: *const pcre16_extra, what: fullinfo_field, where_: *mut c_void) -> c_int;
+    pub fn pcre16_study(code: *const pcre16, options: study_options, errptr: *mut *const c_char) -> *mut pcre16_extra;
+    pub fn pcre16_version() -> *const c_char;
+}
+
+#[cfg(feature = "pcre32")]
+#[link(name = "pcre32")]
+extern "C" {
+    /// The function that the 32-bit library allocates memory with. Defaults to `malloc()`.
+    pub static mut pcre32_malloc: extern "C" fn(size: size_t) -> *mut c_void;
+    /// The function that frees memory allocated with `pcre32_malloc`. Defaults to `free()`.
+    pub static mut pcre32_free: extern "C" fn(ptr: *mut c_void);
+
+    pub fn pcre32_compile(pattern: *const u32, options: compile_options, errptr: *mut *const c_char, erroffset: *mut c_int, tableptr: *const c_uchar) -> *mut pcre32;
+    pub fn pcre32_compile2(pattern: *const u32, options: compile_options, errorcodeptr: *mut c_int, errptr: *mut *const c_char, erroffset: *mut c_int, tableptr: *const c_uchar) -> *mut pcre32;
+    pub fn pcre32_exec(code: *const pcre32, extra: *const pcre32_extra, subject: *const u32, length: c_int, startoffset: c_int, options: exec_options, ovector: *mut c_int, ovecsize: c_int) -> c_int;
+    pub fn pcre32_free_study(extra: *mut pcre32_extra);
+    pub fn pcre32_fullinfo(code: *const pcre32, extra: *const pcre32_extra, what: fullinfo_field, where_: *mut c_void) -> c_int;
+    pub fn pcre32_study(code: *const pcre32, options: study_options, errptr: *mut *const c_char) -> *mut pcre32_extra;
+    pub fn pcre32_version() -> *const c_char;
 }
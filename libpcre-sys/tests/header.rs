@@ -0,0 +1,236 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Checks the bindings against the pcre.h header of the linked libpcre.
+
+extern crate libc;
+extern crate libpcre_sys;
+
+use libpcre_sys::*;
+use std::collections::{BTreeMap};
+use std::fs;
+use std::path::{Path};
+
+/// Lists constants with their values.
+macro_rules! constants {
+    ($($name:ident),*) => {
+        vec![$((stringify!($name), $name as i64)),*]
+    }
+}
+
+/// Lists a function with its number of parameters. The cast checks that the binding takes
+/// as many parameters as there are `_`s.
+macro_rules! function {
+    ($name:ident $(, $param:tt)*) => {{
+        let _ = $name as unsafe extern "C" fn($($param),*) -> _;
+        (stringify!($name), <[&str]>::len(&[$(stringify!($param)),*]))
+    }}
+}
+
+/// Lists a function pointer variable with the number of parameters of the function.
+macro_rules! global {
+    ($name:ident, $ty:ty $(, $param:tt)*) => {{
+        let _: $ty = unsafe { $name };
+        (stringify!($name), <[&str]>::len(&[$(stringify!($param)),*]))
+    }}
+}
+
+/// Returns the contents of pcre.h, or `None` if the build script did not find the header.
+///
+/// # Panics
+/// If the bundled libpcre is used, whose header the build script always knows.
+fn header() -> Option<String> {
+    match option_env!("PCRE_SYS_INCLUDE") {
+        None => {
+            assert_ne!(option_env!("PCRE_SYS_MODE"), Some("vendored"), "the directory of the bundled pcre.h is unknown");
+            eprintln!("skipped: the directory of pcre.h is unknown");
+            None
+        },
+        Some(include) => Some(fs::read_to_string(Path::new(include).join("pcre.h")).unwrap())
+    }
+}
+
+/// Returns the integer constants defined by `header`.
+fn header_constants(header: &str) -> BTreeMap<String, i64> {
+    let mut constants = BTreeMap::new();
+    for line in header.lines() {
+        let mut words = line.split_whitespace();
+        if words.next() != Some("#define") {
+            continue;
+        }
+        let (name, value) = match (words.next(), words.next()) {
+            (Some(name), Some(value)) if name.starts_with("PCRE_") => (name, value.trim_matches(|c| c == '(' || c == ')')),
+            _ => continue
+        };
+        if name == "PCRE_MAJOR" || name == "PCRE_MINOR" {
+            continue;
+        }
+        let value = if let Some(hex) = value.strip_prefix("0x") {
+            i64::from_str_radix(hex, 16).ok()
+        } else {
+            value.parse().ok()
+        };
+        if let Some(value) = value {
+            constants.insert(name.to_string(), value);
+        }
+    }
+    constants
+}
+
+/// Returns the functions and function pointer variables declared by `header`, with their
+/// numbers of parameters.
+fn header_functions(header: &str) -> BTreeMap<String, usize> {
+    let mut functions = BTreeMap::new();
+    for declaration in header.split("PCRE_EXP_DECL").skip(1) {
+        let declaration = match declaration.split(';').next() {
+            Some(declaration) if declaration.contains('(') => declaration,
+            _ => continue
+        };
+        // Either `type name(params)` or `type (*name)(params)`.
+        let (name, params) = match declaration.find("(*") {
+            Some(start) => {
+                let rest = &declaration[(start + 2)..];
+                let end = rest.find(')').unwrap();
+                (rest[..end].trim(), &rest[(end + 1)..])
+            },
+            None => {
+                let start = declaration.find('(').unwrap();
+                let name = declaration[..start].rsplit(|c: char| c.is_whitespace() || c == '*').next().unwrap();
+                (name, &declaration[start..])
+            }
+        };
+        if !name.starts_with("pcre") {
+            continue;
+        }
+        let params = params.trim().trim_start_matches('(').trim_end_matches(')').trim();
+        let count = if params.is_empty() || params == "void" { 0 } else { params.split(',').count() };
+        functions.entry(name.to_string()).or_insert(count);
+    }
+    functions
+}
+
+#[test]
+fn test_constants() {
+    let header = match header() {
+        None => return,
+        Some(header) => header
+    };
+    let header_constants = header_constants(&header);
+    let constants: BTreeMap<&str, i64> = constants![
+        PCRE_CASELESS, PCRE_MULTILINE, PCRE_DOTALL, PCRE_EXTENDED, PCRE_DOLLAR_ENDONLY, PCRE_EXTRA,
+        PCRE_UNGREEDY, PCRE_UTF8, PCRE_UTF16, PCRE_UTF32, PCRE_NO_AUTO_CAPTURE, PCRE_AUTO_CALLOUT,
+        PCRE_NEVER_UTF, PCRE_NO_AUTO_POSSESS, PCRE_FIRSTLINE, PCRE_DUPNAMES, PCRE_JAVASCRIPT_COMPAT,
+        PCRE_UCP, PCRE_ANCHORED, PCRE_NO_UTF8_CHECK, PCRE_NO_UTF16_CHECK, PCRE_NO_UTF32_CHECK,
+        PCRE_NEWLINE_CR, PCRE_NEWLINE_LF, PCRE_NEWLINE_CRLF, PCRE_NEWLINE_ANY, PCRE_NEWLINE_ANYCRLF,
+        PCRE_BSR_ANYCRLF, PCRE_BSR_UNICODE, PCRE_NO_START_OPTIMIZE, PCRE_NO_START_OPTIMISE,
+        PCRE_NOTBOL, PCRE_NOTEOL, PCRE_NOTEMPTY, PCRE_PARTIAL_SOFT, PCRE_PARTIAL, PCRE_DFA_SHORTEST,
+        PCRE_DFA_RESTART, PCRE_PARTIAL_HARD, PCRE_NOTEMPTY_ATSTART, PCRE_ERROR_NOMATCH,
+        PCRE_ERROR_NULL, PCRE_ERROR_BADOPTION, PCRE_ERROR_BADMAGIC, PCRE_ERROR_UNKNOWN_OPCODE,
+        PCRE_ERROR_UNKNOWN_NODE, PCRE_ERROR_NOMEMORY, PCRE_ERROR_NOSUBSTRING, PCRE_ERROR_MATCHLIMIT,
+        PCRE_ERROR_CALLOUT, PCRE_ERROR_BADUTF8, PCRE_ERROR_BADUTF16, PCRE_ERROR_BADUTF32,
+        PCRE_ERROR_BADUTF8_OFFSET, PCRE_ERROR_BADUTF16_OFFSET, PCRE_ERROR_PARTIAL,
+        PCRE_ERROR_BADPARTIAL, PCRE_ERROR_INTERNAL, PCRE_ERROR_BADCOUNT, PCRE_ERROR_DFA_UITEM,
+        PCRE_ERROR_DFA_UCOND, PCRE_ERROR_DFA_UMLIMIT, PCRE_ERROR_DFA_WSSIZE, PCRE_ERROR_DFA_RECURSE,
+        PCRE_ERROR_RECURSIONLIMIT, PCRE_ERROR_NULLWSLIMIT, PCRE_ERROR_BADNEWLINE,
+        PCRE_ERROR_BADOFFSET, PCRE_ERROR_SHORTUTF8, PCRE_ERROR_SHORTUTF16, PCRE_ERROR_RECURSELOOP,
+        PCRE_ERROR_JIT_STACKLIMIT, PCRE_ERROR_BADMODE, PCRE_ERROR_BADENDIANNESS,
+        PCRE_ERROR_DFA_BADRESTART, PCRE_ERROR_JIT_BADOPTION, PCRE_ERROR_BADLENGTH, PCRE_ERROR_UNSET,
+        PCRE_UTF8_ERR0, PCRE_UTF8_ERR1, PCRE_UTF8_ERR2, PCRE_UTF8_ERR3, PCRE_UTF8_ERR4,
+        PCRE_UTF8_ERR5, PCRE_UTF8_ERR6, PCRE_UTF8_ERR7, PCRE_UTF8_ERR8, PCRE_UTF8_ERR9,
+        PCRE_UTF8_ERR10, PCRE_UTF8_ERR11, PCRE_UTF8_ERR12, PCRE_UTF8_ERR13, PCRE_UTF8_ERR14,
+        PCRE_UTF8_ERR15, PCRE_UTF8_ERR16, PCRE_UTF8_ERR17, PCRE_UTF8_ERR18, PCRE_UTF8_ERR19,
+        PCRE_UTF8_ERR20, PCRE_UTF8_ERR21, PCRE_UTF8_ERR22, PCRE_UTF16_ERR0, PCRE_UTF16_ERR1,
+        PCRE_UTF16_ERR2, PCRE_UTF16_ERR3, PCRE_UTF16_ERR4, PCRE_UTF32_ERR0, PCRE_UTF32_ERR1,
+        PCRE_UTF32_ERR2, PCRE_UTF32_ERR3, PCRE_INFO_OPTIONS, PCRE_INFO_SIZE, PCRE_INFO_CAPTURECOUNT,
+        PCRE_INFO_BACKREFMAX, PCRE_INFO_FIRSTBYTE, PCRE_INFO_FIRSTCHAR, PCRE_INFO_FIRSTTABLE,
+        PCRE_INFO_LASTLITERAL, PCRE_INFO_NAMEENTRYSIZE, PCRE_INFO_NAMECOUNT, PCRE_INFO_NAMETABLE,
+        PCRE_INFO_STUDYSIZE, PCRE_INFO_DEFAULT_TABLES, PCRE_INFO_OKPARTIAL, PCRE_INFO_JCHANGED,
+        PCRE_INFO_HASCRORLF, PCRE_INFO_MINLENGTH, PCRE_INFO_JIT, PCRE_INFO_JITSIZE,
+        PCRE_INFO_MAXLOOKBEHIND, PCRE_INFO_FIRSTCHARACTER, PCRE_INFO_FIRSTCHARACTERFLAGS,
+        PCRE_INFO_REQUIREDCHAR, PCRE_INFO_REQUIREDCHARFLAGS, PCRE_INFO_MATCHLIMIT,
+        PCRE_INFO_RECURSIONLIMIT, PCRE_INFO_MATCH_EMPTY, PCRE_CONFIG_UTF8, PCRE_CONFIG_NEWLINE,
+        PCRE_CONFIG_LINK_SIZE, PCRE_CONFIG_POSIX_MALLOC_THRESHOLD, PCRE_CONFIG_MATCH_LIMIT,
+        PCRE_CONFIG_STACKRECURSE, PCRE_CONFIG_UNICODE_PROPERTIES, PCRE_CONFIG_MATCH_LIMIT_RECURSION,
+        PCRE_CONFIG_BSR, PCRE_CONFIG_JIT, PCRE_CONFIG_UTF16, PCRE_CONFIG_JITTARGET,
+        PCRE_CONFIG_UTF32, PCRE_CONFIG_PARENS_LIMIT, PCRE_STUDY_JIT_COMPILE,
+        PCRE_STUDY_JIT_PARTIAL_SOFT_COMPILE, PCRE_STUDY_JIT_PARTIAL_HARD_COMPILE,
+        PCRE_STUDY_EXTRA_NEEDED, PCRE_EXTRA_STUDY_DATA, PCRE_EXTRA_MATCH_LIMIT,
+        PCRE_EXTRA_CALLOUT_DATA, PCRE_EXTRA_TABLES, PCRE_EXTRA_MATCH_LIMIT_RECURSION,
+        PCRE_EXTRA_MARK, PCRE_EXTRA_EXECUTABLE_JIT
+    ].into_iter().collect();
+
+    for (name, value) in header_constants.iter() {
+        match constants.get(name.as_str()) {
+            None => panic!("{} is not bound", name),
+            Some(bound) => assert_eq!(bound, value, "{} is {} in pcre.h", name, value)
+        }
+    }
+}
+
+#[test]
+fn test_functions() {
+    let header = match header() {
+        None => return,
+        Some(header) => header
+    };
+    let header_functions = header_functions(&header);
+
+    let mut functions = vec![
+        global!(pcre_malloc, extern "C" fn(_) -> _, _),
+        global!(pcre_free, extern "C" fn(_), _),
+        global!(pcre_stack_malloc, extern "C" fn(_) -> _, _),
+        global!(pcre_stack_free, extern "C" fn(_), _),
+        global!(pcre_callout, Option<extern "C" fn(_) -> _>, _),
+        function!(pcre_compile, _, _, _, _, _),
+        function!(pcre_compile2, _, _, _, _, _, _),
+        function!(pcre_config, _, _),
+        function!(pcre_copy_named_substring, _, _, _, _, _, _, _),
+        function!(pcre_copy_substring, _, _, _, _, _, _),
+        function!(pcre_dfa_exec, _, _, _, _, _, _, _, _, _, _),
+        function!(pcre_exec, _, _, _, _, _, _, _, _),
+        function!(pcre_free_substring, _),
+        function!(pcre_free_substring_list, _),
+        function!(pcre_free_study, _),
+        function!(pcre_fullinfo, _, _, _, _),
+        function!(pcre_get_named_substring, _, _, _, _, _, _),
+        function!(pcre_get_stringnumber, _, _),
+        function!(pcre_get_stringtable_entries, _, _, _, _),
+        function!(pcre_get_substring, _, _, _, _, _),
+        function!(pcre_get_substring_list, _, _, _, _),
+        function!(pcre_maketables),
+        function!(pcre_refcount, _, _),
+        function!(pcre_study, _, _, _),
+        function!(pcre_version),
+        function!(pcre_jit_stack_alloc, _, _),
+        function!(pcre_jit_stack_free, _),
+        function!(pcre_assign_jit_stack, _, _, _)
+    ];
+    #[cfg(pcre_8_30)]
+    functions.push(function!(pcre_pattern_to_host_byte_order, _, _, _));
+    #[cfg(pcre_8_32)]
+    functions.push(function!(pcre_jit_exec, _, _, _, _, _, _, _, _, _));
+    #[cfg(pcre_8_34)]
+    functions.push(function!(pcre_jit_free_unused_memory));
+    #[cfg(pcre_8_35)]
+    functions.push(global!(pcre_stack_guard, Option<extern "C" fn() -> _>));
+    #[cfg(feature = "pcre16")]
+    functions.extend(vec![global!(pcre16_malloc, extern "C" fn(_) -> _, _), global!(pcre16_free, extern "C" fn(_), _)]);
+    #[cfg(feature = "pcre32")]
+    functions.extend(vec![global!(pcre32_malloc, extern "C" fn(_) -> _, _), global!(pcre32_free, extern "C" fn(_), _)]);
+    let functions: BTreeMap<&str, usize> = functions.into_iter().collect();
+
+    for (name, count) in header_functions.iter().filter(|&(name, _)| name.starts_with("pcre_")) {
+        match functions.get(name.as_str()) {
+            None => panic!("{} is not bound", name),
+            Some(bound) => assert_eq!(bound, count, "{} takes {} parameters in pcre.h", name, count)
+        }
+    }
+    for name in functions.keys() {
+        assert!(header_functions.contains_key(*name), "{} is not declared in pcre.h", name);
+    }
+}
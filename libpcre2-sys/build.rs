@@ -0,0 +1,18 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate pkg_config;
+
+// pcre2_set_depth_limit() was added in 10.30.
+const MINIMUM_PCRE2_VERSION: &str = "10.30";
+
+fn main() {
+    if let Err(e) = pkg_config::Config::new().atleast_version(MINIMUM_PCRE2_VERSION).find("libpcre2-8") {
+        panic!("failed to find libpcre2-8 {} or later using pkg-config: {}. Is libpcre2 installed?", MINIMUM_PCRE2_VERSION, e);
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate libc;
+
+use libc::{c_int, c_void, size_t};
+
+#[allow(non_camel_case_types)]
+pub type PCRE2_SIZE = size_t;
+#[allow(non_camel_case_types)]
+pub type PCRE2_SPTR = *const u8;
+#[allow(non_camel_case_types)]
+pub type PCRE2_UCHAR = u8;
+
+pub const PCRE2_UNSET: PCRE2_SIZE = !0;
+pub const PCRE2_ZERO_TERMINATED: PCRE2_SIZE = !0;
+
+// Compile options
+pub const PCRE2_ALLOW_EMPTY_CLASS: u32 = 0x00000001;
+pub const PCRE2_ALT_BSUX: u32 = 0x00000002;
+pub const PCRE2_AUTO_CALLOUT: u32 = 0x00000004;
+pub const PCRE2_CASELESS: u32 = 0x00000008;
+pub const PCRE2_DOLLAR_ENDONLY: u32 = 0x00000010;
+pub const PCRE2_DOTALL: u32 = 0x00000020;
+pub const PCRE2_DUPNAMES: u32 = 0x00000040;
+pub const PCRE2_EXTENDED: u32 = 0x00000080;
+pub const PCRE2_FIRSTLINE: u32 = 0x00000100;
+pub const PCRE2_MATCH_UNSET_BACKREF: u32 = 0x00000200;
+pub const PCRE2_MULTILINE: u32 = 0x00000400;
+pub const PCRE2_NEVER_UCP: u32 = 0x00000800;
+pub const PCRE2_NEVER_UTF: u32 = 0x00001000;
+pub const PCRE2_NO_AUTO_CAPTURE: u32 = 0x00002000;
+pub const PCRE2_NO_AUTO_POSSESS: u32 = 0x00004000;
+pub const PCRE2_NO_DOTSTAR_ANCHOR: u32 = 0x00008000;
+pub const PCRE2_NO_START_OPTIMIZE: u32 = 0x00010000;
+pub const PCRE2_UCP: u32 = 0x00020000;
+pub const PCRE2_UNGREEDY: u32 = 0x00040000;
+pub const PCRE2_UTF: u32 = 0x00080000;
+
+// PCRE2_ANCHORED and PCRE2_NO_UTF_CHECK are both compile and match options
+pub const PCRE2_ANCHORED: u32 = 0x80000000;
+pub const PCRE2_NO_UTF_CHECK: u32 = 0x40000000;
+
+// Match options
+pub const PCRE2_NOTBOL: u32 = 0x00000001;
+pub const PCRE2_NOTEOL: u32 = 0x00000002;
+pub const PCRE2_NOTEMPTY: u32 = 0x00000004;
+pub const PCRE2_NOTEMPTY_ATSTART: u32 = 0x00000008;
+pub const PCRE2_PARTIAL_SOFT: u32 = 0x00000010;
+pub const PCRE2_PARTIAL_HARD: u32 = 0x00000020;
+
+// JIT compile options
+pub const PCRE2_JIT_COMPLETE: u32 = 0x00000001;
+pub const PCRE2_JIT_PARTIAL_SOFT: u32 = 0x00000002;
+pub const PCRE2_JIT_PARTIAL_HARD: u32 = 0x00000004;
+
+pub const PCRE2_NEWLINE_CR: u32 = 1;
+pub const PCRE2_NEWLINE_LF: u32 = 2;
+pub const PCRE2_NEWLINE_CRLF: u32 = 3;
+pub const PCRE2_NEWLINE_ANY: u32 = 4;
+pub const PCRE2_NEWLINE_ANYCRLF: u32 = 5;
+pub const PCRE2_NEWLINE_NUL: u32 = 6;
+
+pub const PCRE2_BSR_UNICODE: u32 = 1;
+pub const PCRE2_BSR_ANYCRLF: u32 = 2;
+
+pub const PCRE2_ERROR_NOMATCH: c_int = -1;
+pub const PCRE2_ERROR_PARTIAL: c_int = -2;
+pub const PCRE2_ERROR_MATCHLIMIT: c_int = -47;
+pub const PCRE2_ERROR_NOMEMORY: c_int = -48;
+pub const PCRE2_ERROR_DEPTHLIMIT: c_int = -53;
+pub const PCRE2_ERROR_HEAPLIMIT: c_int = -63;
+
+pub const PCRE2_ERROR_PARENTHESES_NEST_TOO_DEEP: c_int = 119;
+pub const PCRE2_ERROR_HEAP_FAILED: c_int = 121;
+pub const PCRE2_ERROR_PARENTHESES_STACK_CHECK: c_int = 133;
+
+pub const PCRE2_INFO_BACKREFMAX: u32 = 2;
+pub const PCRE2_INFO_CAPTURECOUNT: u32 = 4;
+pub const PCRE2_INFO_FIRSTCODEUNIT: u32 = 5;
+pub const PCRE2_INFO_FIRSTCODETYPE: u32 = 6;
+pub const PCRE2_INFO_LASTCODEUNIT: u32 = 11;
+pub const PCRE2_INFO_LASTCODETYPE: u32 = 12;
+pub const PCRE2_INFO_NAMECOUNT: u32 = 17;
+pub const PCRE2_INFO_NAMEENTRYSIZE: u32 = 18;
+pub const PCRE2_INFO_NAMETABLE: u32 = 19;
+pub const PCRE2_INFO_SIZE: u32 = 22;
+
+pub const PCRE2_CONFIG_BSR: u32 = 0;
+pub const PCRE2_CONFIG_JIT: u32 = 1;
+pub const PCRE2_CONFIG_JITTARGET: u32 = 2;
+pub const PCRE2_CONFIG_LINKSIZE: u32 = 3;
+pub const PCRE2_CONFIG_MATCHLIMIT: u32 = 4;
+pub const PCRE2_CONFIG_NEWLINE: u32 = 5;
+pub const PCRE2_CONFIG_PARENSLIMIT: u32 = 6;
+pub const PCRE2_CONFIG_DEPTHLIMIT: u32 = 7;
+pub const PCRE2_CONFIG_STACKRECURSE: u32 = 8;
+pub const PCRE2_CONFIG_UNICODE: u32 = 9;
+pub const PCRE2_CONFIG_VERSION: u32 = 11;
+
+#[allow(non_camel_case_types)]
+pub enum pcre2_code {}
+#[allow(non_camel_case_types)]
+pub enum pcre2_compile_context {}
+#[allow(non_camel_case_types)]
+pub enum pcre2_general_context {}
+#[allow(non_camel_case_types)]
+pub enum pcre2_match_context {}
+#[allow(non_camel_case_types)]
+pub enum pcre2_match_data {}
+
+#[link(name = "pcre2-8")]
+extern "C" {
+    pub fn pcre2_code_free_8(code: *mut pcre2_code);
+    pub fn pcre2_compile_8(pattern: PCRE2_SPTR, length: PCRE2_SIZE, options: u32, errorcode: *mut c_int, erroroffset: *mut PCRE2_SIZE, ccontext: *mut pcre2_compile_context) -> *mut pcre2_code;
+    pub fn pcre2_compile_context_create_8(gcontext: *mut pcre2_general_context) -> *mut pcre2_compile_context;
+    pub fn pcre2_compile_context_free_8(ccontext: *mut pcre2_compile_context);
+    pub fn pcre2_config_8(what: u32, where_: *mut c_void) -> c_int;
+    pub fn pcre2_general_context_create_8(private_malloc: Option<extern "C" fn(size: PCRE2_SIZE, memory_data: *mut c_void) -> *mut c_void>, private_free: Option<extern "C" fn(ptr: *mut c_void, memory_data: *mut c_void)>, memory_data: *mut c_void) -> *mut pcre2_general_context;
+    pub fn pcre2_get_error_message_8(errorcode: c_int, buffer: *mut PCRE2_UCHAR, bufflen: PCRE2_SIZE) -> c_int;
+    pub fn pcre2_get_mark_8(match_data: *mut pcre2_match_data) -> PCRE2_SPTR;
+    pub fn pcre2_get_ovector_count_8(match_data: *mut pcre2_match_data) -> u32;
+    pub fn pcre2_get_ovector_pointer_8(match_data: *mut pcre2_match_data) -> *mut PCRE2_SIZE;
+    pub fn pcre2_jit_compile_8(code: *mut pcre2_code, options: u32) -> c_int;
+    pub fn pcre2_match_8(code: *const pcre2_code, subject: PCRE2_SPTR, length: PCRE2_SIZE, startoffset: PCRE2_SIZE, options: u32, match_data: *mut pcre2_match_data, mcontext: *mut pcre2_match_context) -> c_int;
+    pub fn pcre2_match_context_create_8(gcontext: *mut pcre2_general_context) -> *mut pcre2_match_context;
+    pub fn pcre2_match_context_free_8(mcontext: *mut pcre2_match_context);
+    pub fn pcre2_match_data_create_8(ovecsize: u32, gcontext: *mut pcre2_general_context) -> *mut pcre2_match_data;
+    pub fn pcre2_match_data_free_8(match_data: *mut pcre2_match_data);
+    pub fn pcre2_pattern_info_8(code: *const pcre2_code, what: u32, where_: *mut c_void) -> c_int;
+    pub fn pcre2_set_bsr_8(ccontext: *mut pcre2_compile_context, value: u32) -> c_int;
+    pub fn pcre2_set_compile_recursion_guard_8(ccontext: *mut pcre2_compile_context, guard_function: Option<extern "C" fn(depth: u32, user_data: *mut c_void) -> c_int>, user_data: *mut c_void) -> c_int;
+    pub fn pcre2_set_depth_limit_8(mcontext: *mut pcre2_match_context, value: u32) -> c_int;
+    pub fn pcre2_set_match_limit_8(mcontext: *mut pcre2_match_context, value: u32) -> c_int;
+    pub fn pcre2_set_newline_8(ccontext: *mut pcre2_compile_context, value: u32) -> c_int;
+    pub fn pcre2_substitute_8(code: *const pcre2_code, subject: PCRE2_SPTR, length: PCRE2_SIZE, startoffset: PCRE2_SIZE, options: u32, match_data: *mut pcre2_match_data, mcontext: *mut pcre2_match_context, replacement: PCRE2_SPTR, rlength: PCRE2_SIZE, outputbuffer: *mut PCRE2_UCHAR, outlengthptr: *mut PCRE2_SIZE) -> c_int;
+}
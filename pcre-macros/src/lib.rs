@@ -0,0 +1,359 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Procedural macros for the [pcre](https://crates.io/crates/pcre) crate.
+//!
+//! The code generated by these macros refers to the `pcre` crate, which must be a
+//! dependency of the crate using them.
+
+extern crate pcre;
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use pcre::{CompileOptions, Pcre};
+use proc_macro::{TokenStream};
+use proc_macro2::{Span};
+use syn::parse::{Parse, ParseStream};
+use syn::{Data, DeriveInput, Fields, GenericArgument, Ident, LitStr, PathArguments, Token, Type};
+
+/// The arguments of `checked_pcre!`: the pattern and optional Perl modifiers.
+struct PcreInput {
+
+    pattern: LitStr,
+
+    modifiers: Option<LitStr>
+
+}
+
+impl Parse for PcreInput {
+    fn parse(input: ParseStream) -> syn::Result<PcreInput> {
+        let pattern = input.parse()?;
+        let mut modifiers = None;
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            modifiers = Some(input.parse()?);
+            input.parse::<Option<Token![,]>>()?;
+        }
+        Ok(PcreInput {
+            pattern,
+            modifiers
+        })
+    }
+}
+
+/// Compiles a regular expression when the crate is built, reporting errors in the pattern
+/// as compile errors, and evaluates to a lazily compiled static regular expression with a
+/// typed accessor for each named capture group.
+///
+/// The pattern must be a string literal. An optional second string literal gives Perl
+/// modifiers such as `"i"` or `"mx"`, as accepted by `CompileOptions::from_str()`.
+///
+/// The macro evaluates to a copyable wrapper that dereferences to `&'static pcre::Pcre`.
+/// Its `exec()`, `exec_from()` and `matches()` methods return matches that dereference to
+/// `pcre::Match` and have a method for each named capture group, returning `None` if the
+/// group did not participate in the match. A named group whose name is a method of
+/// `pcre::Match` hides that method.
+///
+/// Unlike the `pcre!` macro of the `pcre` crate, which checks the pattern when it is first
+/// evaluated, this macro checks it when the crate is built. The different names let both be
+/// imported with `#[macro_use]`.
+///
+/// ```
+/// extern crate pcre;
+/// #[macro_use] extern crate pcre_macros;
+///
+/// # fn main() {
+/// let date = checked_pcre!(r"(?<year>\d{4})-(?<month>\d\d)(?:-(?<day>\d\d))?");
+/// let m = date.exec("due 2015-07").unwrap();
+/// assert_eq!(m.year(), Some("2015"));
+/// assert_eq!(m.day(), None);
+/// assert_eq!(m.group(0), "2015-07");
+/// # }
+/// ```
+///
+/// Errors in the pattern fail the build:
+///
+/// ```compile_fail
+/// extern crate pcre;
+/// #[macro_use] extern crate pcre_macros;
+///
+/// # fn main() {
+/// let re = checked_pcre!("a(b");
+/// # }
+/// ```
+#[proc_macro]
+pub fn checked_pcre(input: TokenStream) -> TokenStream {
+    let result = syn::parse::<PcreInput>(input)
+        .map_err(|err| (err.span(), err.to_string()))
+        .and_then(|input| expand(&input));
+    match result {
+        Err((span, message)) => compile_error(span, &message).into(),
+        Ok(tokens) => tokens.into()
+    }
+}
+
+/// Returns a `compile_error!` invocation reporting `message` at `span`.
+///
+/// `syn::Error::to_compile_error()` refers to `::core`, which crates using the 2015 edition
+/// cannot name.
+fn compile_error(span: Span, message: &str) -> proc_macro2::TokenStream {
+    quote_spanned! {span=>
+        compile_error!(#message)
+    }
+}
+
+fn expand(input: &PcreInput) -> Result<proc_macro2::TokenStream, (Span, String)> {
+    let options = match input.modifiers {
+        None => CompileOptions::empty(),
+        Some(ref modifiers) => match modifiers.value().parse::<CompileOptions>() {
+            Err(err) => return Err((modifiers.span(), err.to_string())),
+            Ok(options) => options
+        }
+    };
+
+    let pattern = input.pattern.value();
+    let re = match Pcre::compile_with_options(&pattern, &options) {
+        Err(err) => return Err((offset_span(&input.pattern, err.offset()), err.to_string())),
+        Ok(re) => re
+    };
+
+    let mut accessors = Vec::new();
+    for (name, numbers) in re.name_table() {
+        let ident = match method_ident(&name) {
+            None => return Err((input.pattern.span(), format!("named group `{}` cannot be used as a method name", name))),
+            Some(ident) => ident
+        };
+        accessors.push(quote! {
+            fn #ident(&self) -> ::std::option::Option<&'a str> {
+                // With duplicate names, the first group that is set wins.
+                [#(#numbers),*].iter().filter_map(|&n| self.0.get(n)).next()
+            }
+        });
+    }
+
+    let pattern_lit = &input.pattern;
+    let bits = options.bits();
+    Ok(quote! {
+        {
+            #[derive(Clone, Copy)]
+            struct Pattern(&'static ::pcre::Pcre);
+
+            struct Captures<'a>(::pcre::Match<'a>);
+
+            #[allow(dead_code)]
+            impl Pattern {
+                fn exec<'a>(&self, subject: &'a str) -> ::std::option::Option<Captures<'a>> {
+                    self.0.exec(subject).map(Captures)
+                }
+
+                fn exec_from<'a>(&self, subject: &'a str, startoffset: usize) -> ::std::option::Option<Captures<'a>> {
+                    self.0.exec_from(subject, startoffset).map(Captures)
+                }
+
+                fn matches<'a>(&self, subject: &'a str) -> impl ::std::iter::Iterator<Item = Captures<'a>> + 'a {
+                    self.0.matches(subject).map(Captures)
+                }
+            }
+
+            impl ::std::ops::Deref for Pattern {
+                type Target = ::pcre::Pcre;
+
+                fn deref(&self) -> &::pcre::Pcre {
+                    self.0
+                }
+            }
+
+            #[allow(dead_code)]
+            impl<'a> Captures<'a> {
+                #(#accessors)*
+            }
+
+            impl<'a> ::std::ops::Deref for Captures<'a> {
+                type Target = ::pcre::Match<'a>;
+
+                fn deref(&self) -> &::pcre::Match<'a> {
+                    &self.0
+                }
+            }
+
+            static PCRE: ::std::sync::OnceLock<::pcre::Pcre> = ::std::sync::OnceLock::new();
+            Pattern(PCRE.get_or_init(|| {
+                ::pcre::Pcre::compile_with_options(#pattern_lit, &::pcre::CompileOptions::from_bits(#bits))
+                    .expect("pattern validated at build time")
+            }))
+        }
+    })
+}
+
+/// Implements `pcre::FromCaptures` for a struct with named fields, for use with
+/// `Pcre::captures_into()`.
+///
+/// Each field is read from the named capture group of the same name, or from the group
+/// named by a `#[pcre(group = "name")]` attribute, and parsed using `FromStr`. A field of
+/// type `Option<T>` is `None` if its group did not participate in the match; any other field
+/// requires its group to have participated.
+///
+/// ```
+/// extern crate pcre;
+/// #[macro_use] extern crate pcre_macros;
+///
+/// #[derive(FromCaptures)]
+/// struct Version {
+///     major: u32,
+///     minor: u32,
+///     #[pcre(group = "pre")]
+///     prerelease: Option<String>
+/// }
+///
+/// # fn main() {
+/// let re = pcre::Pcre::compile(r"(?<major>\d+)\.(?<minor>\d+)(?:-(?<pre>\w+))?").unwrap();
+/// let version: Version = re.captures_into("v1.12").unwrap().unwrap();
+/// assert_eq!((version.major, version.minor, version.prerelease), (1, 12, None));
+/// # }
+/// ```
+#[proc_macro_derive(FromCaptures, attributes(pcre))]
+pub fn derive_from_captures(input: TokenStream) -> TokenStream {
+    let result = syn::parse::<DeriveInput>(input)
+        .map_err(|err| (err.span(), err.to_string()))
+        .and_then(|input| expand_from_captures(&input));
+    match result {
+        Err((span, message)) => compile_error(span, &message).into(),
+        Ok(tokens) => tokens.into()
+    }
+}
+
+fn expand_from_captures(input: &DeriveInput) -> Result<proc_macro2::TokenStream, (Span, String)> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => return Err((input.ident.span(), "FromCaptures can only be derived for structs with named fields".to_string()))
+        },
+        _ => return Err((input.ident.span(), "FromCaptures can only be derived for structs".to_string()))
+    };
+
+    let mut group_names = Vec::new();
+    let mut field_values = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let ident = field.ident.as_ref().unwrap();
+        let group = group_name(field)?.unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+        let value = match option_inner_type(&field.ty) {
+            Some(ty) => quote! {
+                match groups[#index] {
+                    ::std::option::Option::None => ::std::option::Option::None,
+                    ::std::option::Option::Some(value) => ::std::option::Option::Some(parse_group::<#ty>(#group, value)?)
+                }
+            },
+            None => {
+                let ty = &field.ty;
+                quote! {
+                    match groups[#index] {
+                        ::std::option::Option::None => return ::std::result::Result::Err(::pcre::CapturesError::UnsetGroup(#group.to_string())),
+                        ::std::option::Option::Some(value) => parse_group::<#ty>(#group, value)?
+                    }
+                }
+            }
+        };
+        group_names.push(group);
+        field_values.push(quote! { #ident: #value });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::pcre::FromCaptures for #name #ty_generics #where_clause {
+            const GROUP_NAMES: &'static [&'static str] = &[#(#group_names),*];
+
+            fn from_groups(groups: &[::std::option::Option<&str>]) -> ::std::result::Result<Self, ::pcre::CapturesError> {
+                fn parse_group<T>(group: &str, value: &str) -> ::std::result::Result<T, ::pcre::CapturesError>
+                    where T: ::std::str::FromStr, T::Err: ::std::fmt::Display {
+                    value.parse::<T>().map_err(|err| ::pcre::CapturesError::Parse {
+                        group: group.to_string(),
+                        value: value.to_string(),
+                        message: err.to_string()
+                    })
+                }
+
+                ::std::result::Result::Ok(#name {
+                    #(#field_values),*
+                })
+            }
+        }
+    })
+}
+
+/// Returns the group name given by the `#[pcre(group = "name")]` attribute of `field`.
+fn group_name(field: &syn::Field) -> Result<Option<String>, (Span, String)> {
+    let mut group = None;
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("pcre")) {
+        let result = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("group") {
+                group = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `group = \"name\"`"))
+            }
+        });
+        if let Err(err) = result {
+            return Err((err.span(), err.to_string()));
+        }
+    }
+    Ok(group)
+}
+
+/// Returns `T` if `ty` is `Option<T>`.
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let path = match *ty {
+        Type::Path(ref ty) if ty.qself.is_none() => &ty.path,
+        _ => return None
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match segment.arguments {
+        PathArguments::AngleBracketed(ref args) if args.args.len() == 1 => match args.args[0] {
+            GenericArgument::Type(ref ty) => Some(ty),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+/// Returns the identifier of the accessor method for the named group `name`.
+fn method_ident(name: &str) -> Option<Ident> {
+    match syn::parse_str::<Ident>(name) {
+        Ok(ident) => Some(ident),
+        // Keywords other than `self`, `Self`, `super` and `crate` can be raw identifiers.
+        Err(_) if !["self", "Self", "super", "crate"].contains(&name) => Some(Ident::new_raw(name, Span::call_site())),
+        Err(_) => None
+    }
+}
+
+/// Returns the span of the character at byte offset `offset` within the value of `lit`, or
+/// the span of the whole literal if the compiler cannot point into it.
+fn offset_span(lit: &LitStr, offset: usize) -> Span {
+    let token = lit.token();
+    let source = token.to_string();
+    let value = lit.value();
+
+    // Offsets into the value only map directly onto the source of raw literals and of
+    // literals without escapes.
+    let prefix = if source.starts_with('r') {
+        source.find('"').unwrap() + 1
+    } else if source.get(1..(1 + value.len())) == Some(value.as_str()) && source.len() == value.len() + 2 {
+        1
+    } else {
+        return lit.span();
+    };
+
+    let start = prefix + offset.min(value.len());
+    let len = source[start..].chars().next().map_or(1, char::len_utf8);
+    token.subspan(start..(start + len)).unwrap_or_else(|| lit.span())
+}
@@ -0,0 +1,90 @@
+#[macro_use]
+extern crate pcre;
+#[macro_use]
+extern crate pcre_macros;
+
+#[test]
+fn test_pcre_named_groups() {
+    let re = checked_pcre!(r"(?<key>\w+)=(?<value>\d+)?");
+    let m = re.exec("a=1").unwrap();
+    assert_eq!(m.key(), Some("a"));
+    assert_eq!(m.value(), Some("1"));
+    assert_eq!(m.group(0), "a=1");
+
+    let m = re.exec("b=").unwrap();
+    assert_eq!(m.key(), Some("b"));
+    assert_eq!(m.value(), None);
+
+    let keys: Vec<_> = re.matches("x=1 y=2").map(|m| m.key().unwrap()).collect();
+    assert_eq!(keys, ["x", "y"]);
+    assert_eq!(re.capture_count(), 2);
+}
+
+#[test]
+fn test_pcre_modifiers() {
+    let re = checked_pcre!("^(?<type>abc)$", "im");
+    assert_eq!(re.exec("x\nABC").unwrap().r#type(), Some("ABC"));
+    assert!(re.exec_from("x\nABC", 3).is_none());
+}
+
+#[test]
+fn test_pcre_duplicate_names() {
+    let re = checked_pcre!("(?:(?<n>a)|(?<n>b))", "J");
+    assert_eq!(re.exec("b").unwrap().n(), Some("b"));
+    assert_eq!(re.exec("a").unwrap().n(), Some("a"));
+}
+
+#[test]
+fn test_pcre_compiled_once() {
+    let compiled: Vec<*const pcre::Pcre> = (0..2).map(|_| &*checked_pcre!("a") as *const pcre::Pcre).collect();
+    assert_eq!(compiled[0], compiled[1]);
+}
+
+#[test]
+fn test_pcre_with_runtime_macro() {
+    let checked = checked_pcre!("(?<word>\\w+)");
+    let runtime = pcre!("(?<word>\\w+)");
+    assert_eq!(checked.exec("hi there").unwrap().word(), runtime.exec("hi there").unwrap().named_group("word"));
+}
+
+#[derive(Debug, PartialEq, FromCaptures)]
+struct Entry {
+    key: String,
+    #[pcre(group = "n")]
+    count: u32,
+    unit: Option<char>
+}
+
+#[test]
+fn test_derive_from_captures() {
+    let re = pcre::Pcre::compile(r"(?<key>\w+)=(?<n>\d+)(?<unit>[a-z])?").unwrap();
+    assert_eq!(re.captures_into::<Entry>("size=12k").unwrap(),
+               Some(Entry { key: "size".to_string(), count: 12, unit: Some('k') }));
+    assert_eq!(re.captures_into::<Entry>("size=12").unwrap(),
+               Some(Entry { key: "size".to_string(), count: 12, unit: None }));
+    assert_eq!(re.captures_into::<Entry>("nothing").unwrap(), None);
+
+    match re.captures_into::<Entry>("size=99999999999") {
+        Err(pcre::CapturesError::Parse { ref group, ref value, .. }) => {
+            assert_eq!(group, "n");
+            assert_eq!(value, "99999999999");
+        },
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_derive_from_captures_errors() {
+    let re = pcre::Pcre::compile(r"(?<key>\w+)=(?<unit>[a-z])?").unwrap();
+    match re.captures_into::<Entry>("a=") {
+        Err(pcre::CapturesError::MissingGroup(ref group)) => assert_eq!(group, "n"),
+        other => panic!("unexpected result {:?}", other)
+    }
+
+    let re = pcre::Pcre::compile(r"(?<key>\w+)=(?:(?<n>\d+)|(?<unit>[a-z]))").unwrap();
+    assert_eq!(re.captures_into::<Entry>("a=4").unwrap(), Some(Entry { key: "a".to_string(), count: 4, unit: None }));
+    match re.captures_into::<Entry>("a=k") {
+        Err(pcre::CapturesError::UnsetGroup(ref group)) => assert_eq!(group, "n"),
+        other => panic!("unexpected result {:?}", other)
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::{BTreeMap, HashMap};
+use std::string::{String};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use {CompilationError, CompileOptions, Pcre, StudyOptions};
+
+/// The capacity of the cache returned by [PcreCache::global()](struct.PcreCache.html#method.global).
+const GLOBAL_CAPACITY: usize = 256;
+
+/// Hit and miss counts of a [PcreCache](struct.PcreCache.html).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The number of lookups that found a compiled regular expression in the cache.
+    pub hits: u64,
+
+    /// The number of lookups that compiled the regular expression.
+    pub misses: u64,
+
+    /// The number of compiled regular expressions evicted to make room for others.
+    pub evictions: u64
+}
+
+/// A thread-safe cache of compiled regular expressions, keyed by pattern and compile options.
+///
+/// Compiled regular expressions are shared as `Arc<Pcre>`, so that one compilation serves
+/// every thread that asks for the same pattern. When the cache is full, the least recently
+/// used regular expression is evicted; regular expressions still in use elsewhere stay alive
+/// until their last `Arc` is dropped.
+///
+/// ```
+/// let cache = pcre::PcreCache::new(16);
+/// let re = cache.get("a+").unwrap();
+/// assert!(re.exec("baaad").is_some());
+/// assert!(std::sync::Arc::ptr_eq(&re, &cache.get("a+").unwrap()));
+/// assert_eq!(cache.stats().hits, 1);
+/// ```
+#[derive(Debug)]
+pub struct PcreCache {
+
+    capacity: usize,
+
+    study_options: Option<StudyOptions>,
+
+    inner: Mutex<CacheInner>
+
+}
+
+#[derive(Debug, Default)]
+struct CacheInner {
+
+    /// Each entry with the tick of its last use.
+    entries: HashMap<(String, CompileOptions), (Arc<Pcre>, u64)>,
+
+    /// The key of each entry, by the tick of its last use.
+    recency: BTreeMap<u64, (String, CompileOptions)>,
+
+    tick: u64,
+
+    stats: CacheStats
+
+}
+
+impl PcreCache {
+    /// Creates a cache that holds at most `capacity` compiled regular expressions.
+    pub fn new(capacity: usize) -> PcreCache {
+        PcreCache {
+            capacity,
+            study_options: None,
+            inner: Mutex::new(CacheInner::default())
+        }
+    }
+
+    /// Creates a cache that holds at most `capacity` compiled regular expressions, each
+    /// studied using the given study options `options` when it is inserted. Pass
+    /// [`StudyOptions::JIT_COMPILE`](struct.StudyOptions.html#associatedconstant.JIT_COMPILE)
+    /// to JIT-compile the cached regular expressions.
+    pub fn with_study_options(capacity: usize, options: &StudyOptions) -> PcreCache {
+        PcreCache {
+            capacity,
+            study_options: Some(*options),
+            inner: Mutex::new(CacheInner::default())
+        }
+    }
+
+    /// Returns the cache shared by the whole process, which holds up to 256 compiled
+    /// regular expressions.
+    pub fn global() -> &'static PcreCache {
+        static GLOBAL: OnceLock<PcreCache> = OnceLock::new();
+        GLOBAL.get_or_init(|| PcreCache::new(GLOBAL_CAPACITY))
+    }
+
+    /// Returns the compiled regular expression for `pattern`, compiling it if it is not
+    /// in the cache.
+    pub fn get(&self, pattern: &str) -> Result<Arc<Pcre>, CompilationError> {
+        self.get_with_options(pattern, &CompileOptions::empty())
+    }
+
+    /// Returns the compiled regular expression for `pattern` with the compilation options
+    /// `options`, compiling it if it is not in the cache.
+    ///
+    /// Compilation errors are not cached.
+    pub fn get_with_options(&self, pattern: &str, options: &CompileOptions) -> Result<Arc<Pcre>, CompilationError> {
+        let key = (pattern.to_string(), *options);
+        if let Some(re) = self.lock().lookup(&key) {
+            return Ok(re);
+        }
+
+        // Compile without holding the lock so that other patterns can be looked up meanwhile.
+        let mut re = Pcre::compile_with_options(pattern, options)?;
+        if let Some(ref study_options) = self.study_options {
+            re.study_with_options(study_options);
+        }
+        Ok(self.lock().insert(key, Arc::new(re), self.capacity))
+    }
+
+    /// Returns the maximum number of compiled regular expressions in the cache.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of compiled regular expressions in the cache.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Returns `true` if the cache holds no compiled regular expressions.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes every compiled regular expression from the cache. The statistics are kept.
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.entries.clear();
+        inner.recency.clear();
+    }
+
+    /// Returns the hit and miss counts of the cache.
+    pub fn stats(&self) -> CacheStats {
+        self.lock().stats
+    }
+
+    fn lock(&self) -> ::std::sync::MutexGuard<'_, CacheInner> {
+        // The cache is consistent between statements, so a panic elsewhere cannot poison it.
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl CacheInner {
+    /// Looks up `key`, marking its entry as the most recently used.
+    fn lookup(&mut self, key: &(String, CompileOptions)) -> Option<Arc<Pcre>> {
+        self.tick += 1;
+        let tick = self.tick;
+        match self.entries.get_mut(key) {
+            None => {
+                self.stats.misses += 1;
+                None
+            },
+            Some(&mut (ref re, ref mut last_use)) => {
+                self.stats.hits += 1;
+                let key = self.recency.remove(last_use).unwrap();
+                self.recency.insert(tick, key);
+                *last_use = tick;
+                Some(re.clone())
+            }
+        }
+    }
+
+    /// Inserts `re` for `key`, unless another thread has inserted it meanwhile, evicting the
+    /// least recently used entries beyond `capacity`.
+    fn insert(&mut self, key: (String, CompileOptions), re: Arc<Pcre>, capacity: usize) -> Arc<Pcre> {
+        if let Some((existing, _)) = self.entries.get(&key) {
+            return existing.clone();
+        }
+        if capacity == 0 {
+            return re;
+        }
+        while self.entries.len() >= capacity {
+            let (_, oldest) = self.recency.pop_first().unwrap();
+            self.entries.remove(&oldest);
+            self.stats.evictions += 1;
+        }
+        self.tick += 1;
+        self.recency.insert(self.tick, key.clone());
+        self.entries.insert(key, (re.clone(), self.tick));
+        re
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::{BTreeMap};
+use std::error::{Error};
+use std::fmt;
+use std::string::{String};
+use std::vec::{Vec};
+
+use {Match};
+
+/// A type that can be built from the named capture groups of a match. See
+/// [Pcre::captures_into()](struct.Pcre.html#method.captures_into).
+///
+/// This trait is usually implemented with `#[derive(FromCaptures)]` from the `pcre-macros`
+/// crate, which reads each field of a struct from the named capture group of the same name
+/// (or the name given by a `#[pcre(group = "name")]` attribute) and parses it using
+/// `FromStr`. A field of type `Option<T>` is `None` if its group did not participate in the
+/// match.
+pub trait FromCaptures: Sized {
+    /// The names of the capture groups that the value is built from.
+    const GROUP_NAMES: &'static [&'static str];
+
+    /// Builds a value from the substrings captured by the groups named in
+    /// [GROUP_NAMES](#associatedconstant.GROUP_NAMES), in the same order. A substring is
+    /// `None` if its group did not participate in the match.
+    fn from_groups(groups: &[Option<&str>]) -> Result<Self, CapturesError>;
+}
+
+/// The error returned by [Pcre::captures_into()](struct.Pcre.html#method.captures_into).
+#[derive(Debug)]
+pub enum CapturesError {
+    /// The regular expression has no capture group with the given name.
+    MissingGroup(String),
+
+    /// The capture group with the given name did not participate in the match, and the
+    /// field read from it is not optional.
+    UnsetGroup(String),
+
+    /// The substring captured by a group could not be parsed.
+    Parse {
+        /// The name of the capture group.
+        group: String,
+
+        /// The captured substring.
+        value: String,
+
+        /// The message of the parse error.
+        message: String
+    }
+}
+
+impl fmt::Display for CapturesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CapturesError::MissingGroup(ref group) => write!(f, "no capture group named `{}`", group),
+            CapturesError::UnsetGroup(ref group) => write!(f, "capture group `{}` did not participate in the match", group),
+            CapturesError::Parse { ref group, ref value, ref message } =>
+                write!(f, "invalid value {:?} for capture group `{}`: {}", value, group, message)
+        }
+    }
+}
+
+impl Error for CapturesError {}
+
+/// Looks up the group numbers for each of `names` in `name_table`, which is `None` if there
+/// are no named groups.
+pub fn group_numbers<'t>(names: &[&str], name_table: Option<&'t BTreeMap<String, Vec<usize>>>) -> Result<Vec<&'t [usize]>, CapturesError> {
+    names.iter()
+        .map(|&name| {
+            name_table.and_then(|name_table| name_table.get(name))
+                .map(|numbers| &numbers[..])
+                .ok_or_else(|| CapturesError::MissingGroup(name.to_string()))
+        })
+        .collect()
+}
+
+/// Returns the substring captured by the first of the groups `numbers` that participated in
+/// the match `m`.
+pub fn first_set_group<'a>(m: &Match<'a>, numbers: &[usize]) -> Option<&'a str> {
+    numbers.iter().filter_map(|&n| m.get(n)).next()
+}
@@ -0,0 +1,91 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::option::{Option};
+use std::string::{String};
+
+use {detail, CompileOptions};
+
+/// The architecture that the JIT compiler of the linked libpcre generates code for, for
+/// example "x86 64bit (little endian + unaligned)".
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct JitTarget(pub(crate) String);
+
+impl JitTarget {
+    /// Returns the description of the architecture.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for JitTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The capabilities and defaults that the linked libpcre was built with.
+///
+/// Distribution packages of libpcre are not all built with the same options. In particular,
+/// JIT compilation and Unicode property support may be missing, in which case
+/// [Pcre::study()](struct.Pcre.html#method.study) does not speed up matching and `\p{..}`
+/// fails to compile.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PcreConfig {
+
+    /// Whether UTF-8 support is available.
+    pub utf: bool,
+
+    /// Whether Unicode character properties, such as `\p{Lu}`, are supported.
+    pub unicode_properties: bool,
+
+    /// Whether the JIT compiler is available.
+    pub jit: bool,
+
+    /// The architecture that the JIT compiler targets, or `None` if JIT is unavailable.
+    pub jit_target: Option<JitTarget>,
+
+    /// The default newline convention, as one of the `NEWLINE_*` compile options. Empty if
+    /// the default newline convention has no equivalent compile option.
+    pub newline: CompileOptions,
+
+    /// What `\R` matches by default, as one of the `BSR_*` compile options.
+    pub bsr: CompileOptions,
+
+    /// The number of bytes used for internal offsets within compiled patterns.
+    pub link_size: u32,
+
+    /// The default match limit.
+    pub match_limit: u64,
+
+    /// The default recursion depth limit.
+    pub match_limit_recursion: u64,
+
+    /// The maximum nesting depth of parentheses within a pattern, or `None` if the linked
+    /// libpcre does not report it.
+    pub parens_limit: Option<u64>,
+
+    /// Whether matching recurses on the machine stack rather than on the heap. Deeply
+    /// recursive matches may overflow the stack when this is `true`.
+    pub stack_recurse: bool
+
+}
+
+/// Returns the capabilities and defaults that the linked libpcre was built with.
+///
+/// ```
+/// let config = pcre::config();
+/// if !config.jit {
+///     eprintln!("warning: libpcre was built without JIT support");
+/// }
+/// assert!(config.utf);
+/// ```
+pub fn config() -> PcreConfig {
+    detail::pcre_config()
+}
@@ -0,0 +1,210 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use libc::{c_char, c_int, c_uchar, c_ulong, c_void, size_t};
+use libpcre_sys;
+pub use libpcre_sys::{pcre, pcre_extra, fullinfo_field, PCRE_UTF8, PCRE_NO_UTF8_CHECK, PCRE_ERROR_NOMATCH, PCRE_ERROR_NULL, PCRE_ERROR_BADOPTION, PCRE_ERROR_MATCHLIMIT, PCRE_ERROR_RECURSIONLIMIT};
+pub use libpcre_sys::{PCRE_INFO_BACKREFMAX, PCRE_INFO_CAPTURECOUNT, PCRE_INFO_FIRSTBYTE, PCRE_INFO_LASTLITERAL, PCRE_INFO_NAMECOUNT, PCRE_INFO_NAMEENTRYSIZE, PCRE_INFO_NAMETABLE, PCRE_INFO_SIZE};
+use std::ffi::{CStr};
+use std::ptr;
+use std::result::{Result};
+use std::string::{String};
+#[cfg(pcre_8_35)]
+use std::sync::{Once};
+
+use {CompileOptions};
+
+pub unsafe fn pcre_compile(pattern: *const c_char, options: &::CompileOptions, tableptr: *const c_uchar) -> Result<*mut pcre, (Option<String>, c_int, ::CompilationErrorKind)> {
+    assert!(!pattern.is_null());
+    install_stack_guard();
+    let converted_options = options.bits() | PCRE_UTF8 | PCRE_NO_UTF8_CHECK;
+    let mut errorcode: c_int = 0;
+    let mut err: *const c_char = ptr::null();
+    let mut erroffset: c_int = 0;
+    let code = ::stack::guard(|| libpcre_sys::pcre_compile2(pattern, converted_options, &mut errorcode, &mut err, &mut erroffset, tableptr));
+
+    if code.is_null() {
+        // "Otherwise, if  compilation  of  a  pattern fails, pcre_compile() returns
+        // NULL, and sets the variable pointed to by errptr to point to a textual
+        // error message. This is a static string that is part of the library. You
+        // must not try to free it."
+        // http://pcre.org/pcre.txt
+        let err_cstr = CStr::from_ptr(err);
+        let kind = ::CompilationErrorKind::from_libpcre(errorcode);
+        // http://illegalargumentexception.blogspot.com/2015/05/rust-utf-8-byte-array-to-string.html
+        // TODO Investigate memory allocations and check for alternative solutions.
+        match String::from_utf8(Vec::from(err_cstr.to_bytes())) {
+            Err(_) => Err((None, erroffset, kind)),
+            Ok(err_str) => Err((Some(err_str), erroffset, kind))
+        }
+    } else {
+        assert!(!code.is_null());
+        assert_eq!(erroffset, 0);
+
+        Ok(code)
+    }
+}
+
+#[cfg(pcre_8_35)]
+extern "C" fn stack_guard_hook() -> c_int {
+    ::stack::exceeded() as c_int
+}
+
+/// Makes libpcre call `stack::exceeded()` at each level of nesting while compiling.
+fn install_stack_guard() {
+    #[cfg(pcre_8_35)]
+    {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| unsafe { libpcre_sys::pcre_stack_guard = Some(stack_guard_hook) });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn pcre_exec(code: *const pcre, extra: *const ::PcreExtra, subject: *const c_char, length: c_int, startoffset: c_int, options: &::ExecOptions, ovector: *mut c_int, ovecsize: c_int) -> c_int {
+    assert!(!code.is_null());
+    assert!(ovecsize >= 0 && ovecsize % 3 == 0);
+    let converted_options = options.bits() | PCRE_NO_UTF8_CHECK;
+    let rc = libpcre_sys::pcre_exec(code, extra, subject, length, startoffset, converted_options, ovector, ovecsize);
+    if rc == PCRE_ERROR_NOMATCH || (rc == libpcre_sys::PCRE_ERROR_NOMEMORY && ::memory::refused()) {
+        return -1;
+    } else if rc < 0 && rc != PCRE_ERROR_NULL && rc != PCRE_ERROR_BADOPTION && rc != PCRE_ERROR_MATCHLIMIT && rc != PCRE_ERROR_RECURSIONLIMIT {
+        panic!("pcre_exec");
+    }
+
+    rc
+}
+
+pub unsafe fn pcre_free(ptr: *mut c_void) {
+    libpcre_sys::pcre_free(ptr);
+}
+
+pub unsafe fn pcre_free_study(extra: *mut ::PcreExtra) {
+    libpcre_sys::pcre_free_study(extra);
+}
+
+pub unsafe fn pcre_fullinfo(code: *const pcre, extra: *const ::PcreExtra, what: fullinfo_field, where_: *mut c_void) {
+    assert!(!code.is_null());
+    let rc = libpcre_sys::pcre_fullinfo(code, extra, what, where_);
+    if rc < 0 && rc != PCRE_ERROR_NULL {
+        panic!("pcre_fullinfo");
+    }
+}
+
+pub unsafe fn pcre_refcount(code: *mut ::detail::pcre, adjust: c_int) -> c_int {
+    assert!(!code.is_null());
+    let curr_refcount = libpcre_sys::pcre_refcount(code, 0);
+    if curr_refcount + adjust < 0 {
+        panic!("refcount underflow");
+    } else if curr_refcount + adjust > 65535 {
+        panic!("refcount overflow");
+    }
+    libpcre_sys::pcre_refcount(code, adjust)
+}
+
+pub unsafe fn pcre_study(code: *const ::detail::pcre, options: &::StudyOptions) -> *mut ::PcreExtra {
+    assert!(!code.is_null());
+    let converted_options = options.bits();
+    let mut err: *const c_char = ptr::null();
+    let extra = libpcre_sys::pcre_study(code, converted_options, &mut err);
+    // "The third argument for pcre_study() is a pointer for an error message. If
+    // studying succeeds (even if no data is returned), the variable it points to is
+    // set to NULL. Otherwise it is set to point to a textual error message. This is
+    // a static string that is part of the library. You must not try to free it."
+    // http://pcre.org/pcre.txt
+    if !err.is_null() {
+        let err_cstr = CStr::from_ptr(err);
+        match String::from_utf8(Vec::from(err_cstr.to_bytes())) {
+            Err(_) => panic!("pcre_study() failed"),
+            Ok(err_str) => panic!("pcre_study() failed: {}", err_str)
+        }
+    }
+    assert!(err.is_null());
+
+    extra
+}
+
+pub fn pcre_version() -> String {
+    let version_cstr = unsafe { CStr::from_ptr(libpcre_sys::pcre_version()) };
+    String::from_utf8(Vec::from(version_cstr.to_bytes())).unwrap()
+}
+
+/// Returns the value of the `pcre_config()` option `what`, or `None` if the linked libpcre does
+/// not know the option.
+unsafe fn config_value<T: Default>(what: libpcre_sys::config_what) -> Option<T> {
+    let mut value = T::default();
+    if libpcre_sys::pcre_config(what, &mut value as *mut T as *mut c_void) < 0 {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Returns the parentheses nesting limit. PCRE_CONFIG_PARENS_LIMIT was added in libpcre 8.36.
+#[cfg(pcre_8_36)]
+#[allow(clippy::unnecessary_cast)]
+fn parens_limit() -> Option<u64> {
+    unsafe { config_value::<c_ulong>(libpcre_sys::PCRE_CONFIG_PARENS_LIMIT).map(|limit| limit as u64) }
+}
+
+#[cfg(not(pcre_8_36))]
+fn parens_limit() -> Option<u64> {
+    None
+}
+
+// c_ulong is 64 bits wide on some platforms and 32 bits wide on others.
+#[allow(clippy::unnecessary_cast)]
+pub fn pcre_config() -> ::PcreConfig {
+    unsafe {
+        let int_value = |what| config_value::<c_int>(what).unwrap_or(0);
+        let ulong_value = |what| config_value::<c_ulong>(what).map(|value| value as u64);
+
+        let jit_target = config_value::<usize>(libpcre_sys::PCRE_CONFIG_JITTARGET)
+            .map(|ptr| ptr as *const c_char)
+            .filter(|ptr| !ptr.is_null())
+            .map(|ptr| ::JitTarget(CStr::from_ptr(ptr).to_string_lossy().into_owned()));
+
+        ::PcreConfig {
+            utf: int_value(libpcre_sys::PCRE_CONFIG_UTF8) != 0,
+            unicode_properties: int_value(libpcre_sys::PCRE_CONFIG_UNICODE_PROPERTIES) != 0,
+            jit: int_value(libpcre_sys::PCRE_CONFIG_JIT) != 0,
+            jit_target,
+            newline: match int_value(libpcre_sys::PCRE_CONFIG_NEWLINE) {
+                // 21 and 37 are the EBCDIC line feed characters.
+                10 | 21 | 37 => CompileOptions::NEWLINE_LF,
+                13 => CompileOptions::NEWLINE_CR,
+                3338 => CompileOptions::NEWLINE_CRLF,
+                -1 => CompileOptions::NEWLINE_ANY,
+                -2 => CompileOptions::NEWLINE_ANYCRLF,
+                _ => CompileOptions::empty()
+            },
+            bsr: if int_value(libpcre_sys::PCRE_CONFIG_BSR) == 0 { CompileOptions::BSR_UNICODE } else { CompileOptions::BSR_ANYCRLF },
+            link_size: int_value(libpcre_sys::PCRE_CONFIG_LINK_SIZE) as u32,
+            match_limit: ulong_value(libpcre_sys::PCRE_CONFIG_MATCH_LIMIT).unwrap_or(0),
+            match_limit_recursion: ulong_value(libpcre_sys::PCRE_CONFIG_MATCH_LIMIT_RECURSION).unwrap_or(0),
+            parens_limit: parens_limit(),
+            stack_recurse: int_value(libpcre_sys::PCRE_CONFIG_STACKRECURSE) != 0
+        }
+    }
+}
+
+extern "C" fn malloc_hook(size: size_t) -> *mut c_void {
+    ::memory::allocate(size)
+}
+
+extern "C" fn free_hook(ptr: *mut c_void) {
+    unsafe { ::memory::deallocate(ptr) }
+}
+
+/// Makes libpcre allocate memory with `memory::allocate()` and free it with
+/// `memory::deallocate()`.
+pub unsafe fn install_allocator() {
+    libpcre_sys::pcre_malloc = malloc_hook;
+    libpcre_sys::pcre_free = free_hook;
+    libpcre_sys::pcre_stack_malloc = malloc_hook;
+    libpcre_sys::pcre_stack_free = free_hook;
+}
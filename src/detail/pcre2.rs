@@ -0,0 +1,556 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The libpcre functions used by `Pcre`, implemented using libpcre2.
+
+use libc::{c_char, c_int, c_uchar, c_void};
+use libpcre2_sys;
+use libpcre2_sys::{PCRE2_SIZE};
+use std::cell::{RefCell};
+use std::cmp;
+use std::ops::{BitOr, Sub};
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::result::{Result};
+use std::string::{String};
+
+use {CompileOptions, ExecOptions, StudyOptions};
+
+#[allow(non_camel_case_types)]
+pub type fullinfo_field = c_int;
+
+// The libpcre pcre_exec() error codes that pcre_exec() returns for unsupported options and
+// exceeded limits.
+pub const PCRE_ERROR_BADOPTION: c_int = -3;
+pub const PCRE_ERROR_MATCHLIMIT: c_int = -8;
+pub const PCRE_ERROR_RECURSIONLIMIT: c_int = -21;
+
+// The libpcre pcre_fullinfo() fields that pcre_fullinfo() emulates, with their libpcre values.
+pub const PCRE_INFO_SIZE: fullinfo_field = 1;
+pub const PCRE_INFO_CAPTURECOUNT: fullinfo_field = 2;
+pub const PCRE_INFO_BACKREFMAX: fullinfo_field = 3;
+pub const PCRE_INFO_FIRSTBYTE: fullinfo_field = 4;
+pub const PCRE_INFO_LASTLITERAL: fullinfo_field = 6;
+pub const PCRE_INFO_NAMEENTRYSIZE: fullinfo_field = 7;
+pub const PCRE_INFO_NAMECOUNT: fullinfo_field = 8;
+pub const PCRE_INFO_NAMETABLE: fullinfo_field = 9;
+
+/// A compiled regular expression, with the reference count that libpcre2 does not keep.
+#[allow(non_camel_case_types)]
+pub struct pcre {
+
+    code: *mut libpcre2_sys::pcre2_code,
+
+    refcount: c_int
+
+}
+
+/// The equivalent of the libpcre extra block, whose settings are passed to libpcre2 in a
+/// match context.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy)]
+pub struct pcre_extra {
+
+    match_limit_: Option<u32>,
+
+    match_limit_recursion_: Option<u32>,
+
+    mark: *mut *mut c_uchar
+
+}
+
+impl pcre_extra {
+    /// Returns the match limit, if previously set by [set_match_limit()](#method.set_match_limit).
+    ///
+    /// The default value for this limit is set when PCRE2 is built. The default default is 10 million.
+    pub fn match_limit(&self) -> Option<usize> {
+        self.match_limit_.map(|limit| limit as usize)
+    }
+
+    /// Sets the match limit to `limit` instead of using PCRE2's default.
+    ///
+    /// `Pcre::try_exec()` reports matches that exceed the limit.
+    pub fn set_match_limit(&mut self, limit: u32) {
+        self.match_limit_ = Some(limit);
+    }
+
+    /// Returns the depth limit, if previously set by [set_match_limit_recursion()](#method.set_match_limit_recursion).
+    ///
+    /// The default value for this limit is set when PCRE2 is built.
+    pub fn match_limit_recursion(&self) -> Option<usize> {
+        self.match_limit_recursion_.map(|limit| limit as usize)
+    }
+
+    /// Sets the depth limit to `limit` instead of using PCRE2's default.
+    ///
+    /// `Pcre::try_exec()` reports matches that exceed the limit.
+    pub fn set_match_limit_recursion(&mut self, limit: u32) {
+        self.match_limit_recursion_ = Some(limit);
+    }
+
+    /// Sets the mark field.
+    ///
+    /// # Safety
+    /// `mark` must stay valid for as long as the extra block is passed to `pcre_exec()`
+    /// with the mark field set.
+    pub unsafe fn set_mark(&mut self, mark: &mut *mut c_uchar) {
+        self.mark = mark as *mut *mut c_uchar;
+    }
+
+    /// Returns `true` if the mark field is set.
+    pub fn has_mark(&self) -> bool {
+        !self.mark.is_null()
+    }
+
+    /// Unsets the mark field. Mark names will not be saved when matching the compiled regular expression.
+    pub fn unset_mark(&mut self) {
+        self.mark = ptr::null_mut();
+    }
+}
+
+// The libpcre compile options and their libpcre2 equivalents. The newline and \R
+// conventions are set in a compile context instead.
+const COMPILE_OPTIONS: &[(CompileOptions, u32)] = &[
+    (CompileOptions::CASELESS, libpcre2_sys::PCRE2_CASELESS),
+    (CompileOptions::MULTILINE, libpcre2_sys::PCRE2_MULTILINE),
+    (CompileOptions::DOTALL, libpcre2_sys::PCRE2_DOTALL),
+    (CompileOptions::EXTENDED, libpcre2_sys::PCRE2_EXTENDED),
+    (CompileOptions::ANCHORED, libpcre2_sys::PCRE2_ANCHORED),
+    (CompileOptions::DOLLAR_ENDONLY, libpcre2_sys::PCRE2_DOLLAR_ENDONLY),
+    (CompileOptions::UNGREEDY, libpcre2_sys::PCRE2_UNGREEDY),
+    (CompileOptions::NO_AUTO_CAPTURE, libpcre2_sys::PCRE2_NO_AUTO_CAPTURE),
+    (CompileOptions::AUTO_CALLOUT, libpcre2_sys::PCRE2_AUTO_CALLOUT),
+    (CompileOptions::NEVER_UTF, libpcre2_sys::PCRE2_NEVER_UTF),
+    (CompileOptions::NO_AUTO_POSSESS, libpcre2_sys::PCRE2_NO_AUTO_POSSESS),
+    (CompileOptions::FIRSTLINE, libpcre2_sys::PCRE2_FIRSTLINE),
+    (CompileOptions::DUPNAMES, libpcre2_sys::PCRE2_DUPNAMES),
+    (CompileOptions::JAVASCRIPT_COMPAT, libpcre2_sys::PCRE2_ALT_BSUX | libpcre2_sys::PCRE2_ALLOW_EMPTY_CLASS | libpcre2_sys::PCRE2_MATCH_UNSET_BACKREF),
+    (CompileOptions::NO_START_OPTIMIZE, libpcre2_sys::PCRE2_NO_START_OPTIMIZE),
+    (CompileOptions::UCP, libpcre2_sys::PCRE2_UCP)
+];
+
+// The libpcre matching options and their libpcre2 equivalents. libpcre2 only accepts the
+// newline and \R conventions and PCRE_NO_START_OPTIMIZE at compile time.
+const EXEC_OPTIONS: &[(ExecOptions, u32)] = &[
+    (ExecOptions::ANCHORED, libpcre2_sys::PCRE2_ANCHORED),
+    (ExecOptions::NOTBOL, libpcre2_sys::PCRE2_NOTBOL),
+    (ExecOptions::NOTEOL, libpcre2_sys::PCRE2_NOTEOL),
+    (ExecOptions::NOTEMPTY, libpcre2_sys::PCRE2_NOTEMPTY),
+    (ExecOptions::NOTEMPTY_ATSTART, libpcre2_sys::PCRE2_NOTEMPTY_ATSTART),
+    (ExecOptions::PARTIAL_SOFT, libpcre2_sys::PCRE2_PARTIAL_SOFT),
+    (ExecOptions::PARTIAL_HARD, libpcre2_sys::PCRE2_PARTIAL_HARD)
+];
+
+// The libpcre study options and their libpcre2 JIT compile option equivalents.
+const STUDY_OPTIONS: &[(StudyOptions, u32)] = &[
+    (StudyOptions::JIT_COMPILE, libpcre2_sys::PCRE2_JIT_COMPLETE),
+    (StudyOptions::JIT_PARTIAL_SOFT_COMPILE, libpcre2_sys::PCRE2_JIT_PARTIAL_SOFT),
+    (StudyOptions::JIT_PARTIAL_HARD_COMPILE, libpcre2_sys::PCRE2_JIT_PARTIAL_HARD)
+];
+
+/// Returns the libpcre2 newline convention for the libpcre compile options `options`.
+fn newline(options: &CompileOptions) -> Option<u32> {
+    let newline_bits = options.bits() & (CompileOptions::NEWLINE_CRLF | CompileOptions::NEWLINE_ANY).bits();
+    match CompileOptions::from_bits(newline_bits) {
+        CompileOptions::NEWLINE_CR => Some(libpcre2_sys::PCRE2_NEWLINE_CR),
+        CompileOptions::NEWLINE_LF => Some(libpcre2_sys::PCRE2_NEWLINE_LF),
+        CompileOptions::NEWLINE_CRLF => Some(libpcre2_sys::PCRE2_NEWLINE_CRLF),
+        CompileOptions::NEWLINE_ANY => Some(libpcre2_sys::PCRE2_NEWLINE_ANY),
+        CompileOptions::NEWLINE_ANYCRLF => Some(libpcre2_sys::PCRE2_NEWLINE_ANYCRLF),
+        _ => None
+    }
+}
+
+/// Returns the libpcre2 \R convention for the libpcre compile options `options`.
+fn bsr(options: &CompileOptions) -> Option<u32> {
+    if options.contains(CompileOptions::BSR_ANYCRLF) {
+        Some(libpcre2_sys::PCRE2_BSR_ANYCRLF)
+    } else if options.contains(CompileOptions::BSR_UNICODE) {
+        Some(libpcre2_sys::PCRE2_BSR_UNICODE)
+    } else {
+        None
+    }
+}
+
+/// Converts the libpcre options in `options` to libpcre2 options using `table`.
+///
+/// # Return value
+/// The libpcre2 options, or the options in `options` that are neither in `table` nor in
+/// `handled`, which libpcre2 does not support.
+fn convert_options<T>(options: T, table: &[(T, u32)], handled: T, contains: fn(&T, T) -> bool) -> Result<u32, T>
+    where T: Copy + BitOr<Output = T> + Sub<Output = T> + Default + PartialEq
+{
+    let unsupported = table.iter().fold(options - handled, |unsupported, &(option, _)| unsupported - option);
+    if unsupported != T::default() {
+        return Err(unsupported);
+    }
+    Ok(table.iter()
+        .filter(|&&(option, _)| contains(&options, option))
+        .fold(0, |converted, &(_, option2)| converted | option2))
+}
+
+/// Returns the message for the libpcre2 error code `errorcode`.
+fn error_message(errorcode: c_int) -> Option<String> {
+    let mut buffer = [0 as libpcre2_sys::PCRE2_UCHAR; 256];
+    let len = unsafe { libpcre2_sys::pcre2_get_error_message_8(errorcode, buffer.as_mut_ptr(), buffer.len() as PCRE2_SIZE) };
+    if len < 0 {
+        None
+    } else {
+        String::from_utf8(buffer[..(len as usize)].to_vec()).ok()
+    }
+}
+
+extern "C" fn stack_guard_hook(_depth: u32, _user_data: *mut c_void) -> c_int {
+    ::stack::exceeded() as c_int
+}
+
+/// Classifies a libpcre2 compile error code.
+fn error_kind(errorcode: c_int) -> ::CompilationErrorKind {
+    match errorcode {
+        libpcre2_sys::PCRE2_ERROR_NOMEMORY | libpcre2_sys::PCRE2_ERROR_HEAP_FAILED => ::CompilationErrorKind::OutOfMemory,
+        libpcre2_sys::PCRE2_ERROR_PARENTHESES_NEST_TOO_DEEP => ::CompilationErrorKind::NestingLimit,
+        libpcre2_sys::PCRE2_ERROR_PARENTHESES_STACK_CHECK => ::CompilationErrorKind::StackGuard,
+        _ => ::CompilationErrorKind::Invalid
+    }
+}
+
+pub unsafe fn pcre_compile(pattern: *const c_char, options: &CompileOptions, _tableptr: *const c_uchar) -> Result<*mut pcre, (Option<String>, c_int, ::CompilationErrorKind)> {
+    assert!(!pattern.is_null());
+    // Patterns are always compiled in UTF mode, and the newline and \R conventions are set in
+    // the compile context.
+    let handled = CompileOptions::UTF8 | CompileOptions::NO_UTF8_CHECK | CompileOptions::NEWLINE_CRLF | CompileOptions::NEWLINE_ANY
+        | CompileOptions::BSR_ANYCRLF | CompileOptions::BSR_UNICODE;
+    let converted_options = match convert_options(*options, COMPILE_OPTIONS, handled, CompileOptions::contains) {
+        Err(unsupported) => return Err((Some(format!("compile options {:?} are not supported by libpcre2", unsupported)), 0, ::CompilationErrorKind::Invalid)),
+        Ok(converted_options) => converted_options | libpcre2_sys::PCRE2_UTF | libpcre2_sys::PCRE2_NO_UTF_CHECK
+    };
+
+    // The compiled regular expression is allocated by the compile context's allocator, and
+    // the stack guard is set in the compile context.
+    let gcontext = GENERAL_CONTEXT.load(Ordering::Acquire);
+    let ccontext = libpcre2_sys::pcre2_compile_context_create_8(gcontext);
+    if ccontext.is_null() {
+        let errorcode = libpcre2_sys::PCRE2_ERROR_NOMEMORY;
+        return Err((error_message(errorcode), 0, error_kind(errorcode)));
+    }
+    if let Some(newline) = newline(options) {
+        libpcre2_sys::pcre2_set_newline_8(ccontext, newline);
+    }
+    if let Some(bsr) = bsr(options) {
+        libpcre2_sys::pcre2_set_bsr_8(ccontext, bsr);
+    }
+    libpcre2_sys::pcre2_set_compile_recursion_guard_8(ccontext, Some(stack_guard_hook), ptr::null_mut());
+
+    let mut errorcode: c_int = 0;
+    let mut erroroffset: PCRE2_SIZE = 0;
+    let code = ::stack::guard(|| libpcre2_sys::pcre2_compile_8(pattern as libpcre2_sys::PCRE2_SPTR, libpcre2_sys::PCRE2_ZERO_TERMINATED,
+                                                              converted_options, &mut errorcode, &mut erroroffset, ccontext));
+    libpcre2_sys::pcre2_compile_context_free_8(ccontext);
+
+    if code.is_null() {
+        Err((error_message(errorcode), erroroffset as c_int, error_kind(errorcode)))
+    } else {
+        Ok(Box::into_raw(Box::new(pcre {
+            code,
+            refcount: 0
+        })))
+    }
+}
+
+/// The match data and match context that are reused by the matches running on a thread.
+struct MatchState {
+
+    match_data: *mut libpcre2_sys::pcre2_match_data,
+
+    /// The number of offset pairs that `match_data` can record.
+    pairs: u32,
+
+    mcontext: *mut libpcre2_sys::pcre2_match_context
+
+}
+
+impl MatchState {
+    const fn new() -> MatchState {
+        MatchState {
+            match_data: ptr::null_mut(),
+            pairs: 0,
+            mcontext: ptr::null_mut()
+        }
+    }
+
+    /// Returns match data that can record at least `pairs` offset pairs, or null if libpcre2
+    /// ran out of memory.
+    unsafe fn match_data(&mut self, pairs: u32) -> *mut libpcre2_sys::pcre2_match_data {
+        if self.match_data.is_null() || self.pairs < pairs {
+            libpcre2_sys::pcre2_match_data_free_8(self.match_data);
+            let gcontext = GENERAL_CONTEXT.load(Ordering::Acquire);
+            self.match_data = libpcre2_sys::pcre2_match_data_create_8(pairs, gcontext);
+            self.pairs = pairs;
+        }
+        self.match_data
+    }
+
+    /// Returns a match context with the limits set in `extra`, or null if it sets none.
+    unsafe fn mcontext(&mut self, extra: *const ::PcreExtra) -> *mut libpcre2_sys::pcre2_match_context {
+        if extra.is_null() || ((*extra).match_limit_.is_none() && (*extra).match_limit_recursion_.is_none()) {
+            return ptr::null_mut();
+        }
+        if self.mcontext.is_null() {
+            let gcontext = GENERAL_CONTEXT.load(Ordering::Acquire);
+            self.mcontext = libpcre2_sys::pcre2_match_context_create_8(gcontext);
+            assert!(!self.mcontext.is_null());
+        }
+        // A limit that is not set keeps the default, which a previous match may have replaced.
+        let match_limit = (*extra).match_limit_.or_else(|| config_value(libpcre2_sys::PCRE2_CONFIG_MATCHLIMIT));
+        let depth_limit = (*extra).match_limit_recursion_.or_else(|| config_value(libpcre2_sys::PCRE2_CONFIG_DEPTHLIMIT));
+        libpcre2_sys::pcre2_set_match_limit_8(self.mcontext, match_limit.unwrap());
+        libpcre2_sys::pcre2_set_depth_limit_8(self.mcontext, depth_limit.unwrap());
+        self.mcontext
+    }
+}
+
+impl Drop for MatchState {
+    fn drop(&mut self) {
+        unsafe {
+            libpcre2_sys::pcre2_match_context_free_8(self.mcontext);
+            libpcre2_sys::pcre2_match_data_free_8(self.match_data);
+        }
+    }
+}
+
+thread_local! {
+    /// The match state reused by the matches running on this thread.
+    static MATCH_STATE: RefCell<MatchState> = const { RefCell::new(MatchState::new()) };
+}
+
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn pcre_exec(code: *const pcre, extra: *const ::PcreExtra, subject: *const c_char, length: c_int, startoffset: c_int, options: &ExecOptions, ovector: *mut c_int, ovecsize: c_int) -> c_int {
+    assert!(!code.is_null());
+    assert!(ovecsize >= 0 && ovecsize % 3 == 0);
+    let converted_options = match convert_options(*options, EXEC_OPTIONS, ExecOptions::NO_UTF8_CHECK, ExecOptions::contains) {
+        Err(_) => return PCRE_ERROR_BADOPTION,
+        Ok(converted_options) => converted_options | libpcre2_sys::PCRE2_NO_UTF_CHECK
+    };
+    let exec = |state: &mut MatchState| exec_with_state(state, code, extra, subject, length, startoffset, converted_options, ovector, ovecsize);
+
+    if ::memory::in_scope() {
+        // Memory charged to a budget is freed after the match instead of staying allocated
+        // for the lifetime of the thread.
+        return exec(&mut MatchState::new());
+    }
+    // The state is already borrowed if a match runs within a match, and gone if the thread
+    // is exiting.
+    MATCH_STATE.try_with(|state| match state.try_borrow_mut() {
+        Err(_) => exec(&mut MatchState::new()),
+        Ok(mut state) => exec(&mut state)
+    }).unwrap_or_else(|_| exec(&mut MatchState::new()))
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe fn exec_with_state(state: &mut MatchState, code: *const pcre, extra: *const ::PcreExtra, subject: *const c_char, length: c_int, startoffset: c_int, options: u32, ovector: *mut c_int, ovecsize: c_int) -> c_int {
+    // libpcre only uses the first two thirds of the ovector for offsets.
+    let pairs = (ovecsize / 3) as u32;
+    let match_data = state.match_data(cmp::max(pairs, 1));
+    if match_data.is_null() && ::memory::refused() {
+        return -1;
+    }
+    assert!(!match_data.is_null());
+    let mcontext = state.mcontext(extra);
+
+    let mut rc = libpcre2_sys::pcre2_match_8((*code).code, subject as libpcre2_sys::PCRE2_SPTR, length as PCRE2_SIZE,
+                                             startoffset as PCRE2_SIZE, options, match_data, mcontext);
+    if !extra.is_null() && (*extra).has_mark() {
+        // The mark name is stored within the compiled regular expression, as with libpcre.
+        *(*extra).mark = libpcre2_sys::pcre2_get_mark_8(match_data) as *mut c_uchar;
+    }
+    if rc >= 0 {
+        let offsets = libpcre2_sys::pcre2_get_ovector_pointer_8(match_data);
+        let recorded = cmp::min(pairs, libpcre2_sys::pcre2_get_ovector_count_8(match_data)) as usize;
+        for i in 0..(recorded * 2) {
+            let offset = *offsets.add(i);
+            *ovector.add(i) = if offset == libpcre2_sys::PCRE2_UNSET { -1 } else { offset as c_int };
+        }
+        // As with libpcre, 0 means that the ovector is too small to record every group that
+        // is set, which the reused match data may have recorded.
+        if rc as u32 > pairs {
+            rc = 0;
+        }
+    }
+
+    if rc == libpcre2_sys::PCRE2_ERROR_NOMATCH || (rc == libpcre2_sys::PCRE2_ERROR_NOMEMORY && ::memory::refused()) {
+        return -1;
+    } else if rc == libpcre2_sys::PCRE2_ERROR_MATCHLIMIT {
+        return PCRE_ERROR_MATCHLIMIT;
+    } else if rc == libpcre2_sys::PCRE2_ERROR_DEPTHLIMIT || rc == libpcre2_sys::PCRE2_ERROR_HEAPLIMIT {
+        // The heap limit bounds the memory used for backtracking, as the depth limit does.
+        return PCRE_ERROR_RECURSIONLIMIT;
+    } else if rc < 0 {
+        panic!("pcre2_match");
+    }
+
+    rc
+}
+
+pub unsafe fn pcre_free(ptr: *mut c_void) {
+    let code = Box::from_raw(ptr as *mut pcre);
+    libpcre2_sys::pcre2_code_free_8(code.code);
+}
+
+pub unsafe fn pcre_free_study(extra: *mut ::PcreExtra) {
+    if !extra.is_null() {
+        drop(Box::from_raw(extra));
+    }
+}
+
+pub unsafe fn pcre_fullinfo(code: *const pcre, _extra: *const ::PcreExtra, what: fullinfo_field, where_: *mut c_void) {
+    assert!(!code.is_null());
+    let pattern_info = |what2: u32, where2: *mut c_void| {
+        if libpcre2_sys::pcre2_pattern_info_8((*code).code, what2, where2) < 0 {
+            panic!("pcre_fullinfo");
+        }
+    };
+    let info = |what2: u32| -> u32 {
+        let mut value: u32 = 0;
+        pattern_info(what2, &mut value as *mut u32 as *mut c_void);
+        value
+    };
+    match what {
+        PCRE_INFO_SIZE => pattern_info(libpcre2_sys::PCRE2_INFO_SIZE, where_),
+        PCRE_INFO_CAPTURECOUNT => pattern_info(libpcre2_sys::PCRE2_INFO_CAPTURECOUNT, where_),
+        PCRE_INFO_BACKREFMAX => pattern_info(libpcre2_sys::PCRE2_INFO_BACKREFMAX, where_),
+        PCRE_INFO_NAMEENTRYSIZE => pattern_info(libpcre2_sys::PCRE2_INFO_NAMEENTRYSIZE, where_),
+        PCRE_INFO_NAMECOUNT => pattern_info(libpcre2_sys::PCRE2_INFO_NAMECOUNT, where_),
+        PCRE_INFO_NAMETABLE => pattern_info(libpcre2_sys::PCRE2_INFO_NAMETABLE, where_),
+        PCRE_INFO_FIRSTBYTE => {
+            // libpcre returns -1 for a pattern that only matches at the start of a line, and
+            // -2 if there is no first character.
+            *(where_ as *mut c_int) = match info(libpcre2_sys::PCRE2_INFO_FIRSTCODETYPE) {
+                1 => info(libpcre2_sys::PCRE2_INFO_FIRSTCODEUNIT) as c_int,
+                2 => -1,
+                _ => -2
+            };
+        },
+        PCRE_INFO_LASTLITERAL => {
+            *(where_ as *mut c_int) = match info(libpcre2_sys::PCRE2_INFO_LASTCODETYPE) {
+                1 => info(libpcre2_sys::PCRE2_INFO_LASTCODEUNIT) as c_int,
+                _ => -1
+            };
+        },
+        _ => panic!("pcre_fullinfo: unsupported field {}", what)
+    }
+}
+
+pub unsafe fn pcre_refcount(code: *mut ::detail::pcre, adjust: c_int) -> c_int {
+    assert!(!code.is_null());
+    let curr_refcount = (*code).refcount;
+    if curr_refcount + adjust < 0 {
+        panic!("refcount underflow");
+    } else if curr_refcount + adjust > 65535 {
+        panic!("refcount overflow");
+    }
+    (*code).refcount += adjust;
+    (*code).refcount
+}
+
+pub unsafe fn pcre_study(code: *const ::detail::pcre, options: &StudyOptions) -> *mut ::PcreExtra {
+    assert!(!code.is_null());
+    // libpcre2 studies every pattern when compiling it, so studying only JIT compiles it.
+    // As with libpcre, a JIT compilation failure only means that the interpreter is used.
+    // libpcre2 has no extra block, so one is always created unless the options are not
+    // supported.
+    let jit_options = match convert_options(*options, STUDY_OPTIONS, StudyOptions::EXTRA_NEEDED, StudyOptions::contains) {
+        Err(_) => return ptr::null_mut(),
+        Ok(jit_options) => jit_options
+    };
+    if jit_options != 0 {
+        libpcre2_sys::pcre2_jit_compile_8((*code).code, jit_options);
+    }
+
+    Box::into_raw(Box::new(pcre_extra {
+        match_limit_: None,
+        match_limit_recursion_: None,
+        mark: ptr::null_mut()
+    }))
+}
+
+pub fn pcre_version() -> String {
+    let mut buffer = [0u8; 64];
+    let len = unsafe { libpcre2_sys::pcre2_config_8(libpcre2_sys::PCRE2_CONFIG_VERSION, buffer.as_mut_ptr() as *mut c_void) };
+    assert!(len > 0);
+    // The length includes the terminating NUL.
+    String::from_utf8(buffer[..((len - 1) as usize)].to_vec()).unwrap()
+}
+
+/// Returns the value of the `pcre2_config()` option `what`, or `None` if the linked libpcre2
+/// does not know the option.
+unsafe fn config_value(what: u32) -> Option<u32> {
+    let mut value: u32 = 0;
+    if libpcre2_sys::pcre2_config_8(what, &mut value as *mut u32 as *mut c_void) < 0 {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+pub fn pcre_config() -> ::PcreConfig {
+    unsafe {
+        let value = |what| config_value(what).unwrap_or(0);
+
+        let mut buffer = [0u8; 128];
+        let len = libpcre2_sys::pcre2_config_8(libpcre2_sys::PCRE2_CONFIG_JITTARGET, buffer.as_mut_ptr() as *mut c_void);
+        // The length includes the terminating NUL.
+        let jit_target = if len > 0 {
+            Some(::JitTarget(String::from_utf8_lossy(&buffer[..((len - 1) as usize)]).into_owned()))
+        } else {
+            None
+        };
+
+        ::PcreConfig {
+            // libpcre2 has UTF support whenever it has Unicode support.
+            utf: value(libpcre2_sys::PCRE2_CONFIG_UNICODE) != 0,
+            unicode_properties: value(libpcre2_sys::PCRE2_CONFIG_UNICODE) != 0,
+            jit: value(libpcre2_sys::PCRE2_CONFIG_JIT) != 0,
+            jit_target,
+            newline: match value(libpcre2_sys::PCRE2_CONFIG_NEWLINE) {
+                libpcre2_sys::PCRE2_NEWLINE_CR => CompileOptions::NEWLINE_CR,
+                libpcre2_sys::PCRE2_NEWLINE_LF => CompileOptions::NEWLINE_LF,
+                libpcre2_sys::PCRE2_NEWLINE_CRLF => CompileOptions::NEWLINE_CRLF,
+                libpcre2_sys::PCRE2_NEWLINE_ANY => CompileOptions::NEWLINE_ANY,
+                libpcre2_sys::PCRE2_NEWLINE_ANYCRLF => CompileOptions::NEWLINE_ANYCRLF,
+                _ => CompileOptions::empty()
+            },
+            bsr: if value(libpcre2_sys::PCRE2_CONFIG_BSR) == libpcre2_sys::PCRE2_BSR_ANYCRLF { CompileOptions::BSR_ANYCRLF } else { CompileOptions::BSR_UNICODE },
+            link_size: value(libpcre2_sys::PCRE2_CONFIG_LINKSIZE),
+            match_limit: value(libpcre2_sys::PCRE2_CONFIG_MATCHLIMIT) as u64,
+            match_limit_recursion: value(libpcre2_sys::PCRE2_CONFIG_DEPTHLIMIT) as u64,
+            parens_limit: config_value(libpcre2_sys::PCRE2_CONFIG_PARENSLIMIT).map(|limit| limit as u64),
+            // libpcre2 10.30 and later never recurse on the machine stack when matching.
+            stack_recurse: false
+        }
+    }
+}
+
+/// The general context that makes libpcre2 allocate with `memory::allocate()`, once
+/// `install_allocator()` has been called.
+static GENERAL_CONTEXT: AtomicPtr<libpcre2_sys::pcre2_general_context> = AtomicPtr::new(ptr::null_mut());
+
+extern "C" fn malloc_hook(size: PCRE2_SIZE, _data: *mut c_void) -> *mut c_void {
+    ::memory::allocate(size)
+}
+
+extern "C" fn free_hook(ptr: *mut c_void, _data: *mut c_void) {
+    unsafe { ::memory::deallocate(ptr) }
+}
+
+/// Makes libpcre2 allocate memory with `memory::allocate()` and free it with
+/// `memory::deallocate()`.
+pub unsafe fn install_allocator() {
+    let gcontext = libpcre2_sys::pcre2_general_context_create_8(Some(malloc_hook), Some(free_hook), ptr::null_mut());
+    assert!(!gcontext.is_null());
+    GENERAL_CONTEXT.store(gcontext, Ordering::Release);
+}
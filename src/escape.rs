@@ -0,0 +1,168 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt::{Write};
+use std::string::{String};
+
+/// Escapes all regular expression metacharacters in `literal`, returning a pattern
+/// fragment that matches `literal` exactly.
+///
+/// Every ASCII punctuation character other than `_`, and the space character, is preceded
+/// by a backslash, which also keeps `#` and spaces significant when the pattern is compiled
+/// with [`CompileOptions::EXTENDED`](struct.CompileOptions.html#associatedconstant.EXTENDED).
+/// ASCII control characters, including NUL, are written as `\xhh` escapes. All other
+/// characters are copied as is.
+///
+/// ```
+/// assert_eq!(pcre::escape("1+1=2?"), "1\\+1\\=2\\?");
+/// ```
+pub fn escape(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len() * 2);
+    for c in literal.chars() {
+        push_escaped(&mut escaped, c);
+    }
+    escaped
+}
+
+/// Quotes `literal` using `\Q...\E`, returning a pattern fragment that matches `literal`
+/// exactly.
+///
+/// Quoting is often more readable than [escape()](fn.escape.html) for long literals. An
+/// `\E` within `literal` would end the quoted sequence early, so it is written outside
+/// of the quoted sequence, as are NUL characters, which cannot be passed to libpcre
+/// within a pattern.
+///
+/// ```
+/// assert_eq!(pcre::quote("a.b"), "\\Qa.b\\E");
+/// assert_eq!(pcre::quote("a\\Eb"), "\\Qa\\E\\\\E\\Qb\\E");
+/// ```
+pub fn quote(literal: &str) -> String {
+    let mut quoted = String::with_capacity(literal.len() + 4);
+    let mut in_quote = false;
+    let mut rest = literal;
+    while !rest.is_empty() {
+        let (unquoted, len) = if rest.starts_with("\\E") {
+            ("\\\\E", 2)
+        } else if rest.starts_with('\0') {
+            ("\\x00", 1)
+        } else {
+            if !in_quote {
+                quoted.push_str("\\Q");
+                in_quote = true;
+            }
+            let c = rest.chars().next().unwrap();
+            quoted.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        if in_quote {
+            quoted.push_str("\\E");
+            in_quote = false;
+        }
+        quoted.push_str(unquoted);
+        rest = &rest[len..];
+    }
+    if in_quote {
+        quoted.push_str("\\E");
+    }
+    quoted
+}
+
+/// Appends `c` to `escaped`, escaping it if it is not a literal character in a pattern.
+pub fn push_escaped(escaped: &mut String, c: char) {
+    if c.is_ascii_control() {
+        write!(escaped, "\\x{:02x}", c as u32).unwrap();
+    } else if (c.is_ascii_punctuation() && c != '_') || c == ' ' {
+        escaped.push('\\');
+        escaped.push(c);
+    } else {
+        escaped.push(c);
+    }
+}
+
+/// Records where [escape_nul()](fn.escape_nul.html) rewrote NUL characters so that offsets
+/// within the rewritten pattern can be mapped back to the original pattern.
+pub struct NulEscapes {
+
+    /// `(original offset, original length, rewritten length)` for each rewrite.
+    spans: Vec<(usize, usize, usize)>
+
+}
+
+impl NulEscapes {
+    /// Maps the byte offset `offset` within the rewritten pattern to the corresponding
+    /// byte offset within the original pattern.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        let mut delta = 0;
+        for &(start, len, rewritten_len) in self.spans.iter() {
+            let rewritten_start = start + delta;
+            if offset < rewritten_start {
+                break;
+            } else if offset < rewritten_start + rewritten_len {
+                return start;
+            }
+            delta = delta + rewritten_len - len;
+        }
+        offset - delta
+    }
+}
+
+/// Rewrites the NUL characters in `pattern`, which cannot be passed to libpcre, as `\x00`
+/// escapes.
+///
+/// A NUL preceded by a backslash is an escaped literal NUL and is rewritten as a whole.
+/// Within a `\Q...\E` quoted sequence, the quoted sequence is interrupted around the escape.
+pub fn escape_nul(pattern: &str) -> (String, NulEscapes) {
+    let bytes = pattern.as_bytes();
+    let mut rewritten: Vec<u8> = Vec::with_capacity(bytes.len() + 8);
+    let mut spans = Vec::new();
+    let mut in_quote = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let next = bytes.get(i + 1).cloned();
+        if in_quote {
+            if b == b'\\' && next == Some(b'E') {
+                rewritten.extend_from_slice(b"\\E");
+                in_quote = false;
+                i += 2;
+            } else if b == 0 {
+                rewritten.extend_from_slice(b"\\E\\x00\\Q");
+                spans.push((i, 1, 8));
+                i += 1;
+            } else {
+                rewritten.push(b);
+                i += 1;
+            }
+        } else if b == b'\\' {
+            match next {
+                Some(0) => {
+                    rewritten.extend_from_slice(b"\\x00");
+                    spans.push((i, 2, 4));
+                },
+                Some(next) => {
+                    in_quote = next == b'Q';
+                    rewritten.push(b);
+                    rewritten.push(next);
+                },
+                None => rewritten.push(b)
+            }
+            i += 2;
+        } else if b == 0 {
+            rewritten.extend_from_slice(b"\\x00");
+            spans.push((i, 1, 4));
+            i += 1;
+        } else {
+            rewritten.push(b);
+            i += 1;
+        }
+    }
+
+    // Only ASCII bytes were inserted or removed, so the result is still valid UTF-8.
+    (String::from_utf8(rewritten).unwrap(), NulEscapes { spans })
+}
@@ -6,91 +6,150 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-extern crate enum_set;
 extern crate libc;
+#[cfg(feature = "libpcre-sys")]
 extern crate libpcre_sys;
+#[cfg(feature = "pcre2")]
+extern crate libpcre2_sys;
+extern crate memchr;
+#[cfg(feature = "serde")]
+extern crate serde;
 
-use enum_set::{CLike, EnumSet};
-use libc::{c_char, c_int, c_uchar, c_void};
+#[cfg(not(any(feature = "libpcre-sys", feature = "pcre2")))]
+compile_error!("either the default `libpcre-sys` feature or the `pcre2` feature must be enabled");
+
+use libc::{c_char, c_int, c_uchar, c_void, size_t};
 use std::collections::{BTreeMap};
+use std::cmp;
 use std::ffi::{CStr, CString};
-use std::marker::{PhantomData};
+use std::fmt;
+use std::ops::{Index, Range};
 use std::option::{Option};
 use std::ptr;
 use std::result::{Result};
 use std::string::{String};
+use std::sync::{Arc};
+use std::sync::atomic::{AtomicPtr, Ordering};
 use std::vec::{Vec};
 
+mod cache;
+mod captures;
+mod config;
 mod detail;
-
-#[derive(Clone)]
-pub enum CompileOption {
-    Caseless = 0x00000001,
-    Multiline = 0x00000002,
-    DotAll = 0x00000004,
-    Extended = 0x00000008,
-    Anchored = 0x00000010,
-    DollarEndOnly = 0x00000020,
-    Extra = 0x00000040,
-    Ungreedy = 0x00000200,
-    NoAutoCapture = 0x00001000,
-    AutoCallout = 0x00004000,
-    FirstLine = 0x00040000,
-    DupNames = 0x00080000,
-    NewlineCR = 0x00100000,
-    NewlineLF = 0x00200000,
-    NewlineCRLF = 0x00300000,
-    NewlineAny = 0x00400000,
-    NewlineAnyCRLF = 0x00500000,
-    BsrAnyCRLF = 0x00800000,
-    BsrUnicode = 0x01000000,
-    JavaScriptCompat = 0x02000000,
-    Ucp = 0x20000000
+mod escape;
+mod literal;
+mod memory;
+mod options;
+mod position;
+mod prefilter;
+mod redos;
+mod safe;
+#[cfg(feature = "serde")]
+mod serialize;
+mod set;
+mod stack;
+mod template;
+mod version;
+#[cfg(any(feature = "pcre16", feature = "pcre32"))]
+mod wide;
+
+pub use cache::{CacheStats, PcreCache};
+pub use captures::{CapturesError, FromCaptures};
+pub use config::{config, JitTarget, PcreConfig};
+pub use escape::{escape, quote};
+pub use literal::{PerlLiteralError};
+pub use memory::{install_allocator, MemoryBudget, MemoryLimitExceeded};
+pub use options::{CompileOptions, ExecOptions, ParseOptionsError, StudyOptions};
+pub use position::{LineIndex, Position};
+pub use redos::{redos_warnings, ReDosKind, ReDosWarning};
+pub use safe::{SafeCompileError, SafeCompileOptions};
+pub use set::{PcreSet, SetCompilationError};
+pub use stack::{StackGuard};
+pub use template::{Template, TemplateError};
+pub use version::{version, Version};
+#[cfg(feature = "pcre16")]
+pub use wide::{Match16, MatchIterator16, Pcre16};
+#[cfg(feature = "pcre32")]
+pub use wide::{Match32, MatchIterator32, Pcre32};
+
+/// Compiles a regular expression once, the first time it is evaluated, and returns it as a
+/// `&'static Pcre`.
+///
+/// An optional second argument gives the compilation options. Panics if the regular
+/// expression fails to compile.
+///
+/// ```
+/// # #[macro_use] extern crate pcre;
+/// # fn main() {
+/// fn is_word(s: &str) -> bool {
+///     pcre!(r"^\w+$").exec(s).is_some()
+/// }
+/// assert!(is_word("hello"));
+/// assert!(pcre!("^HELLO$", pcre::CompileOptions::CASELESS).exec("hello").is_some());
+/// # }
+/// ```
+#[macro_export]
+macro_rules! pcre {
+    ($pattern:expr) => {
+        $crate::pcre!($pattern, $crate::CompileOptions::empty())
+    };
+    ($pattern:expr, $options:expr) => {{
+        static PCRE: ::std::sync::OnceLock<$crate::Pcre> = ::std::sync::OnceLock::new();
+        PCRE.get_or_init(|| match $crate::Pcre::compile_with_options($pattern, &$options) {
+            Err(err) => panic!("invalid pattern {:?}: {}", $pattern, err),
+            Ok(re) => re
+        })
+    }};
 }
 
-#[derive(Clone)]
-pub enum ExecOption {
-    ExecAnchored = 0x00000010,
-    ExecNotBol = 0x00000080,
-    ExecNotEol = 0x00000100,
-    ExecNotEmpty = 0x00000400,
-    ExecPartialSoft = 0x00008000,
-    ExecNewlineCR = 0x00100000,
-    ExecNewlineLF = 0x00200000,
-    ExecNewlineCRLF = 0x00300000,
-    ExecNewlineAny = 0x00400000,
-    ExecNewlineAnyCRLF = 0x00500000,
-    ExecBsrAnyCRLF = 0x00800000,
-    ExecBsrUnicode = 0x01000000,
-    ExecNoStartOptimise = 0x04000000,
-    ExecPartialHard = 0x08000000,
-    ExecNotEmptyAtStart = 0x10000000
-}
+#[derive(Debug)]
+pub struct CompilationError {
 
-#[allow(non_upper_case_globals)]
-pub const ExecPartial: ExecOption = ExecOption::ExecPartialSoft;
-#[allow(non_upper_case_globals)]
-pub const ExecNoStartOptimize: ExecOption = ExecOption::ExecNoStartOptimise;
+    opt_err: Option<String>,
 
-#[derive(Clone)]
-pub enum StudyOption {
-    StudyJitCompile = 0x0001,
-    StudyJitPartialSoftCompile = 0x0002,
-    StudyJitPartialHardCompile = 0x0004,
+    erroffset: c_int,
+
+    kind_: CompilationErrorKind
 
-    /// Always create an extra block. Note: Requires PCRE version 8.32 or later.
-    StudyExtraNeeded = 0x0008
 }
 
-#[derive(Debug)]
-pub struct CompilationError {
+/// The reason that a regular expression failed to compile, as returned by
+/// [CompilationError::kind()](struct.CompilationError.html#method.kind).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CompilationErrorKind {
 
-    opt_err: Option<String>,
+    /// The pattern is not a valid regular expression, or uses a feature that the linked
+    /// libpcre does not support.
+    Invalid,
+
+    /// Parentheses are nested more deeply than the limit that libpcre was built with. See
+    /// [PcreConfig::parens_limit](struct.PcreConfig.html#structfield.parens_limit).
+    NestingLimit,
+
+    /// Compiling the pattern would have used more stack than allowed by the
+    /// [StackGuard](struct.StackGuard.html) in effect.
+    StackGuard,
 
-    erroffset: c_int
+    /// libpcre failed to allocate memory, for example because a
+    /// [MemoryBudget](struct.MemoryBudget.html) was exhausted.
+    OutOfMemory
 
 }
 
+impl CompilationErrorKind {
+    /// Classifies a libpcre compile error code, as set by `pcre_compile2()`.
+    #[cfg(any(not(feature = "pcre2"), feature = "pcre16", feature = "pcre32"))]
+    fn from_libpcre(errorcode: c_int) -> CompilationErrorKind {
+        // libpcre does not name its compile error codes. These are ERR21, ERR82 and ERR85.
+        match errorcode {
+            21 => CompilationErrorKind::OutOfMemory,
+            82 => CompilationErrorKind::NestingLimit,
+            85 => CompilationErrorKind::StackGuard,
+            _ => CompilationErrorKind::Invalid
+        }
+    }
+}
+
 /// Wrapper for libpcre's `pcre` object (representing a compiled regular expression).
 #[derive(Debug)]
 pub struct Pcre {
@@ -101,214 +160,209 @@ pub struct Pcre {
 
     capture_count_: c_int,
 
-    /// A spot to place a pointer-to-mark name string.
-    mark_: *mut c_uchar
+    pattern_: String,
+
+    options_: CompileOptions,
+
+    prefilter_: prefilter::Prefilter,
+
+    /// The pointer-to-mark name string left by the last match.
+    mark_: AtomicPtr<c_uchar>,
+
+    /// The name table, shared with matches, if there are named capture groups.
+    name_table_: Option<Arc<BTreeMap<String, Vec<usize>>>>
 
 }
 
-pub type PcreExtra = libpcre_sys::pcre_extra;
+pub type PcreExtra = detail::pcre_extra;
+
+// The compiled code and the extra block are only modified through `&mut Pcre`, and libpcre
+// allows a compiled pattern to be matched from several threads at once. The mark is the
+// only state that matching writes to, and each match writes it through its own copy of the
+// extra block.
+unsafe impl Send for Pcre {}
+unsafe impl Sync for Pcre {}
 
 /// Represents a match of a subject string against a regular expression.
+///
+/// Capture groups can be accessed by number or by name, either with methods such as
+/// [get()](#method.get) and [named_group()](#method.named_group), which return `None` for
+/// groups that did not participate in the match, or by indexing, which panics instead:
+///
+/// ```
+/// let re = pcre::Pcre::compile("(?<key>\\w+)=(\\d+)?").unwrap();
+/// let m = re.exec("[key=]").unwrap();
+/// assert_eq!(&m[0], "key=");
+/// assert_eq!(&m["key"], "key");
+/// assert_eq!(m.get(2), None);
+/// assert_eq!(m.iter().collect::<Vec<_>>(), [Some("key="), Some("key"), None]);
+/// assert_eq!((m.pre_match(), m.post_match()), ("[", "]"));
+/// ```
+#[derive(Clone)]
 pub struct Match<'a> {
 
     subject: &'a str,
 
     partial_ovector: Vec<c_int>,
 
-    string_count_: c_int
+    string_count_: c_int,
+
+    /// The name table of the regular expression, if it has named capture groups.
+    name_table_: Option<Arc<BTreeMap<String, Vec<usize>>>>
 
 }
 
-/// Iterator type for iterating matches within a subject string.
-pub struct MatchIterator<'a, 'p> {
+/// Iterator over the capture groups of a match, created by [Match::iter()](struct.Match.html#method.iter).
+///
+/// Each item is the substring captured by a group, or `None` if the group did not
+/// participate in the match.
+#[derive(Clone, Debug)]
+pub struct Groups<'m, 'a: 'm> {
 
-    code: *const detail::pcre,
+    m: &'m Match<'a>,
+
+    range: std::ops::Range<usize>
 
-    extra: *const PcreExtra,
+}
+
+/// Iterator type for iterating matches within a subject string.
+pub struct MatchIterator<'a, 'p> {
 
-    capture_count: c_int,
+    re: &'p Pcre,
 
     subject: &'a str,
 
     offset: c_int,
 
-    options: EnumSet<ExecOption>,
+    options: ExecOptions,
 
     ovector: Vec<c_int>,
 
-    _marker: PhantomData<&'p mut Pcre>
+    /// The prefilter, until it has been checked against the subject string.
+    prefilter: Option<&'p prefilter::Prefilter>
 
 }
 
-impl CLike for CompileOption {
-    unsafe fn from_u32(n: u32) -> CompileOption {
-        use CompileOption::*;
-        match n {
-            1 => Caseless,
-            2 => Multiline,
-            3 => DotAll,
-            4 => Extended,
-            5 => Anchored,
-            6 => DollarEndOnly,
-            7 => Extra,
-            8 => Ungreedy,
-            9 => NoAutoCapture,
-            10 => AutoCallout,
-            11 => FirstLine,
-            12 => DupNames,
-            13 => NewlineCR,
-            14 => NewlineLF,
-            15 => NewlineCRLF,
-            16 => NewlineAny,
-            17 => NewlineAnyCRLF,
-            18 => BsrAnyCRLF,
-            19 => BsrUnicode,
-            20 => JavaScriptCompat,
-            21 => Ucp,
-            _ => panic!("unknown CompileOption number {}", n)
-        }
+impl CompilationError {
+    pub fn message(&self) -> Option<String> {
+        self.opt_err.clone()
     }
 
-    fn to_u32(&self) -> u32 {
-        use CompileOption::*;
-        match *self {
-            Caseless => 1,
-            Multiline => 2,
-            DotAll => 3,
-            Extended => 4,
-            Anchored => 5,
-            DollarEndOnly => 6,
-            Extra => 7,
-            Ungreedy => 8,
-            NoAutoCapture => 9,
-            AutoCallout => 10,
-            FirstLine => 11,
-            DupNames => 12,
-            NewlineCR => 13,
-            NewlineLF => 14,
-            NewlineCRLF => 15,
-            NewlineAny => 16,
-            NewlineAnyCRLF => 17,
-            BsrAnyCRLF => 18,
-            BsrUnicode => 19,
-            JavaScriptCompat => 20,
-            Ucp => 21
-        }
+    pub fn offset(&self) -> usize {
+        self.erroffset as usize
     }
-}
 
-impl CLike for ExecOption {
-    unsafe fn from_u32(n: u32) -> ExecOption {
-        use ExecOption::*;
-        match n {
-            1 => ExecAnchored,
-            2 => ExecNotBol,
-            3 => ExecNotEol,
-            4 => ExecNotEmpty,
-            5 => ExecPartialSoft,
-            6 => ExecNewlineCR,
-            7 => ExecNewlineLF,
-            8 => ExecNewlineCRLF,
-            9 => ExecNewlineAny,
-            10 => ExecNewlineAnyCRLF,
-            11 => ExecBsrAnyCRLF,
-            12 => ExecBsrUnicode,
-            13 => ExecNoStartOptimise,
-            14 => ExecPartialHard,
-            15 => ExecNotEmptyAtStart,
-            _ => panic!("unknown ExecOption number {}", n)
-        }
+    /// Returns the reason that compilation failed.
+    ///
+    /// ```
+    /// use pcre::{CompilationErrorKind, Pcre, StackGuard};
+    ///
+    /// let err = Pcre::compile("a)").unwrap_err();
+    /// assert_eq!(err.kind(), CompilationErrorKind::Invalid);
+    ///
+    /// let nested = format!("{}a{}", "(".repeat(100), ")".repeat(100));
+    /// let err = StackGuard::new(1024).scope(|| Pcre::compile(&nested)).unwrap_err();
+    /// # if pcre::version().supports_stack_guard() {
+    /// assert_eq!(err.kind(), CompilationErrorKind::StackGuard);
+    /// # }
+    /// ```
+    pub fn kind(&self) -> CompilationErrorKind {
+        self.kind_
     }
+}
 
-    fn to_u32(&self) -> u32 {
-        use ExecOption::*;
-        match *self {
-            ExecAnchored => 1,
-            ExecNotBol => 2,
-            ExecNotEol => 3,
-            ExecNotEmpty => 4,
-            ExecPartialSoft => 5,
-            ExecNewlineCR => 6,
-            ExecNewlineLF => 7,
-            ExecNewlineCRLF => 8,
-            ExecNewlineAny => 9,
-            ExecNewlineAnyCRLF => 10,
-            ExecBsrAnyCRLF => 11,
-            ExecBsrUnicode => 12,
-            ExecNoStartOptimise => 13,
-            ExecPartialHard => 14,
-            ExecNotEmptyAtStart => 15
+impl std::fmt::Display for CompilationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.opt_err {
+            None => write!(f, "compilation failed at offset {}", self.erroffset as usize),
+            Some(ref s) => write!(f, "compilation failed at offset {}: {}", self.erroffset as usize, s)
         }
     }
 }
 
-impl CLike for StudyOption {
-    unsafe fn from_u32(n: u32) -> StudyOption {
-        use StudyOption::*;
-        match n {
-            1 => StudyJitCompile,
-            2 => StudyJitPartialSoftCompile,
-            3 => StudyJitPartialHardCompile,
-            4 => StudyExtraNeeded,
-            _ => panic!("unknown StudyOption number {}", n)
-        }
-    }
+impl std::error::Error for CompilationError {}
 
-    fn to_u32(&self) -> u32 {
-        use StudyOption::*;
-        match *self {
-            StudyJitCompile => 1,
-            StudyJitPartialSoftCompile => 2,
-            StudyJitPartialHardCompile => 3,
-            StudyExtraNeeded => 4
-        }
-    }
-}
+/// The error returned by [Pcre::try_exec()](struct.Pcre.html#method.try_exec) when libpcre
+/// abandons a match because it exceeded a limit, or rejects the matching options.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ExecError {
 
-impl CompilationError {
-    pub fn message(&self) -> Option<String> {
-        self.opt_err.clone()
-    }
+    /// The match exceeded the match limit. See
+    /// [PcreExtra::set_match_limit()](../libpcre_sys/struct.PcreExtra.html#method.set_match_limit).
+    MatchLimit,
 
-    pub fn offset(&self) -> usize {
-        self.erroffset as usize
+    /// The match exceeded the recursion depth limit, or with libpcre2, the depth or heap
+    /// limit. See
+    /// [PcreExtra::set_match_limit_recursion()](../libpcre_sys/struct.PcreExtra.html#method.set_match_limit_recursion).
+    RecursionLimit,
+
+    /// The matching options are not supported by the linked library. libpcre2 only accepts
+    /// the newline and `\R` conventions and `NO_START_OPTIMIZE` when compiling.
+    UnsupportedOptions
+
+}
+
+impl ExecError {
+    /// Classifies a negative return code of `detail::pcre_exec()`.
+    fn from_rc(rc: c_int) -> Option<ExecError> {
+        match rc {
+            detail::PCRE_ERROR_MATCHLIMIT => Some(ExecError::MatchLimit),
+            detail::PCRE_ERROR_RECURSIONLIMIT => Some(ExecError::RecursionLimit),
+            detail::PCRE_ERROR_BADOPTION => Some(ExecError::UnsupportedOptions),
+            _ => None
+        }
     }
 }
 
-impl std::fmt::Display for CompilationError {
+impl std::fmt::Display for ExecError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self.opt_err {
-            None => write!(f, "compilation failed at offset {}", self.erroffset as usize),
-            Some(ref s) => write!(f, "compilation failed at offset {}: {}", self.erroffset as usize, s)
+        match *self {
+            ExecError::MatchLimit => write!(f, "match limit exceeded"),
+            ExecError::RecursionLimit => write!(f, "recursion limit exceeded"),
+            ExecError::UnsupportedOptions => write!(f, "matching options not supported")
         }
     }
 }
 
+impl std::error::Error for ExecError {}
+
 impl Pcre {
     /// Compiles the given regular expression.
     ///
     /// # Argument
     /// * `pattern` - The regular expression.
     pub fn compile(pattern: &str) -> Result<Pcre, CompilationError> {
-        let no_options: EnumSet<CompileOption> = EnumSet::new();
+        let no_options = CompileOptions::empty();
         Pcre::compile_with_options(pattern, &no_options)
     }
 
-    /// Compiles a regular expression using the given bitwise-OR'd options `options`.
+    /// Compiles a regular expression using the given compilation options `options`.
     ///
     /// # Arguments
     /// * `pattern` - The regular expression.
-    /// * `options` - Bitwise-OR'd compilation options. See the libpcre manpages,
+    /// * `options` - Compilation options, combined with `|`. See the libpcre manpages,
     ///   `man 3 pcre_compile`, for more information.
-    pub fn compile_with_options(pattern: &str, options: &EnumSet<CompileOption>) -> Result<Pcre, CompilationError> {
-        let pattern_cstring = CString::new(pattern).unwrap();
+    ///
+    /// NUL characters in `pattern` match a literal NUL. They are passed to libpcre as `\x00`
+    /// escapes, and the offset of a compilation error refers to `pattern` as given.
+    pub fn compile_with_options(pattern: &str, options: &CompileOptions) -> Result<Pcre, CompilationError> {
+        let (pattern_cstring, nul_escapes) = if pattern.contains('\0') {
+            let (escaped_pattern, nul_escapes) = escape::escape_nul(pattern);
+            (CString::new(escaped_pattern).unwrap(), Some(nul_escapes))
+        } else {
+            (CString::new(pattern).unwrap(), None)
+        };
         unsafe {
             // Use the default character tables.
             let tableptr: *const c_uchar = ptr::null();
             match detail::pcre_compile(pattern_cstring.as_ptr(), options, tableptr) {
-                Err((opt_err, erroffset)) => Err(CompilationError {
-                    opt_err: opt_err,
-                    erroffset: erroffset
+                Err((opt_err, erroffset, kind)) => Err(CompilationError {
+                    opt_err,
+                    erroffset: match nul_escapes {
+                        None => erroffset,
+                        Some(ref nul_escapes) => nul_escapes.original_offset(erroffset as usize) as c_int
+                    },
+                    kind_: kind
                 }),
                 Ok(mut_code) => {
                     let code = mut_code as *const detail::pcre;
@@ -319,20 +373,64 @@ impl Pcre {
                     let extra: *mut PcreExtra = ptr::null_mut();
 
                     let mut capture_count: c_int = 0;
-                    detail::pcre_fullinfo(code, extra as *const PcreExtra, libpcre_sys::PCRE_INFO_CAPTURECOUNT,
+                    detail::pcre_fullinfo(code, extra as *const PcreExtra, detail::PCRE_INFO_CAPTURECOUNT,
                         &mut capture_count as *mut c_int as *mut c_void);
 
-                    Ok(Pcre {
-                        code: code,
-                        extra: extra,
+                    let mut re = Pcre {
+                        code,
+                        extra,
                         capture_count_: capture_count,
-                        mark_: ptr::null_mut()
-                    })
+                        pattern_: pattern.to_string(),
+                        options_: *options,
+                        prefilter_: prefilter::Prefilter::default(),
+                        mark_: AtomicPtr::new(ptr::null_mut()),
+                        name_table_: None
+                    };
+                    re.prefilter_ = prefilter::Prefilter::new(&re);
+                    if re.name_count() > 0 {
+                        re.name_table_ = Some(Arc::new(re.name_table()));
+                    }
+                    Ok(re)
                 }
             }
         }
     }
 
+    /// Compiles a regular expression written as a Perl-style pattern literal, such as
+    /// `/foo.*bar/i` or `m{a/b}x`.
+    ///
+    /// The pattern may be delimited by `/`, or by `m` followed by any punctuation character.
+    /// The bracketing delimiters `()`, `[]`, `{}` and `<>` may nest within the pattern. The
+    /// trailing modifiers are parsed as described in [CompileOptions](struct.CompileOptions.html).
+    ///
+    /// # Argument
+    /// * `literal` - The pattern literal.
+    pub fn compile_perl_literal(literal: &str) -> Result<Pcre, PerlLiteralError> {
+        let (pattern, options) = literal::parse_perl_literal(literal)?;
+        Ok(Pcre::compile_with_options(pattern, &options)?)
+    }
+
+    /// Returns the regular expression as a Perl-style pattern literal that
+    /// [compile_perl_literal()](#method.compile_perl_literal) accepts.
+    ///
+    /// # Return value
+    /// `None` if the regular expression was compiled with an option that has no Perl
+    /// modifier letter, or if its pattern contains every delimiter and a slash within
+    /// `\Q...\E`.
+    pub fn to_perl_literal(&self) -> Option<String> {
+        self.options_.to_perl_modifiers().and_then(|modifiers| literal::to_perl_literal(&self.pattern_, &modifiers))
+    }
+
+    /// Returns the pattern that the regular expression was compiled from.
+    pub fn pattern(&self) -> &str {
+        &self.pattern_
+    }
+
+    /// Returns the options that the regular expression was compiled with.
+    pub fn compile_options(&self) -> CompileOptions {
+        self.options_
+    }
+
     /// Returns the number of capture groups in the regular expression, including one for
     /// each named capture group.
     ///
@@ -345,11 +443,34 @@ impl Pcre {
         self.capture_count_ as usize
     }
 
+    /// Looks for constructs in the regular expression that are prone to catastrophic
+    /// backtracking. See [redos_warnings()](fn.redos_warnings.html).
+    ///
+    /// ```
+    /// let re = pcre::Pcre::compile("^(a|a)*$").unwrap();
+    /// let warnings = re.redos_warnings();
+    /// assert_eq!(warnings[0].suggestion, "(a|a)*+");
+    /// assert!(pcre::Pcre::compile(&warnings[0].suggestion).is_ok());
+    /// ```
+    pub fn redos_warnings(&self) -> Vec<ReDosWarning> {
+        redos::redos_warnings(&self.pattern_, &self.options_)
+    }
+
+    /// Returns the size of the compiled regular expression in bytes, not including the data
+    /// added by studying it.
+    pub fn size(&self) -> usize {
+        let mut size: size_t = 0;
+        unsafe {
+            detail::pcre_fullinfo(self.code, self.extra as *const PcreExtra, detail::PCRE_INFO_SIZE, &mut size as *mut size_t as *mut c_void);
+        }
+        size
+    }
+
     /// Enables the use of the mark field when matching the compiled regular expression. The
     /// pattern must have been previously studied and an extra block must have been created.
     ///
     /// To ensure that an extra block has been created, call [study_with_options()](#method.study_with_options)
-    /// passing the [`StudyExtraNeeded`](enum.StudyOption.html#variant.StudyExtraNeeded) study option.
+    /// passing the [`StudyOptions::EXTRA_NEEDED`](struct.StudyOptions.html#associatedconstant.EXTRA_NEEDED) study option.
     ///
     /// # Return value
     /// `true` if the use of the mark field could be enabled. `false` otherwise, which signifies
@@ -359,7 +480,9 @@ impl Pcre {
             if self.extra.is_null() {
                 false
             } else {
-                (*self.extra).set_mark(&mut self.mark_);
+                // Subjects skipped by the prefilter would leave a stale mark behind.
+                self.prefilter_ = prefilter::Prefilter::default();
+                (*self.extra).set_mark(self.mark_.get_mut());
                 true
             }
         }
@@ -390,7 +513,7 @@ impl Pcre {
     /// If a regular expression will be used often, it might be worth studying it to possibly
     /// speed up matching. See the [study()](#method.study) method.
     #[inline]
-    pub fn exec<'a, 'p>(&'p self, subject: &'a str) -> Option<Match<'a>> {
+    pub fn exec<'a>(&self, subject: &'a str) -> Option<Match<'a>> {
         self.exec_from(subject, 0)
     }
 
@@ -411,14 +534,14 @@ impl Pcre {
     /// If a regular expression will be used often, it might be worth studying it to possibly
     /// speed up matching. See the [study()](#method.study) method.
     #[inline]
-    pub fn exec_from<'a, 'p>(&'p self, subject: &'a str, startoffset: usize) -> Option<Match<'a>> {
-        let no_options: EnumSet<ExecOption> = EnumSet::new();
+    pub fn exec_from<'a>(&self, subject: &'a str, startoffset: usize) -> Option<Match<'a>> {
+        let no_options = ExecOptions::empty();
         self.exec_from_with_options(subject, startoffset, &no_options)
     }
 
     /// Matches the compiled regular expression against a given subject string `subject`
     /// starting at offset `startoffset` within the subject string and using the given
-    /// bitwise-OR'd matching options `options`. If no match is found, then `None` is
+    /// matching options `options`. If no match is found, then `None` is
     /// returned. Otherwise, a `Match` object is returned which provides access to the
     /// captured substrings as slices of the subject string.
     ///
@@ -426,7 +549,7 @@ impl Pcre {
     /// * `subject` - The subject string.
     /// * `startoffset` - Starting offset within `subject` at which to begin looking for
     ///   a match.
-    /// * `options` - Bitwise-OR'd matching options. See the libpcre manpages, `man 3 pcre_exec`,
+    /// * `options` - Matching options, combined with `|`. See the libpcre manpages, `man 3 pcre_exec`,
     ///   for more information.
     ///
     /// # Performance notes
@@ -435,28 +558,152 @@ impl Pcre {
     ///
     /// If a regular expression will be used often, it might be worth studying it to possibly
     /// speed up matching. See the [study()](#method.study) method.
+    ///
+    /// Subjects that do not contain a literal substring or character that every match must
+    /// contain are rejected without calling into libpcre. This is disabled by
+    /// [enable_mark()](#method.enable_mark) and for partial matching.
+    #[inline]
+    pub fn exec_from_with_options<'a>(&self, subject: &'a str, startoffset: usize, options: &ExecOptions) -> Option<Match<'a>> {
+        self.exec_from_with_groups(subject, startoffset, options, self.capture_count())
+    }
+
+    /// Like [exec_from_with_options()](#method.exec_from_with_options), but only records
+    /// the first `groups` capture groups, in addition to the whole match.
+    ///
+    /// Recording fewer groups makes matching cheaper. The returned match behaves as if the
+    /// regular expression had only `groups` capture groups: [len()](struct.Match.html#method.len)
+    /// is `groups + 1`, and later groups are reported as unset.
+    ///
+    /// # Arguments
+    /// * `subject` - The subject string.
+    /// * `startoffset` - Starting offset within `subject` at which to begin looking for
+    ///   a match.
+    /// * `options` - Matching options, combined with `|`.
+    /// * `groups` - The number of capture groups to record. Values larger than
+    ///   [capture_count()](#method.capture_count) record every group.
+    ///
+    /// Matching options that the linked library does not support match nothing.
+    ///
+    /// # Panics
+    /// If the match exceeds the match or recursion limit. See [try_exec()](#method.try_exec).
+    pub fn exec_from_with_groups<'a>(&self, subject: &'a str, startoffset: usize, options: &ExecOptions, groups: usize) -> Option<Match<'a>> {
+        match self.try_exec_from_with_groups(subject, startoffset, options, groups) {
+            Ok(m) => m,
+            Err(ExecError::UnsupportedOptions) => None,
+            Err(err) => panic!("pcre_exec: {}", err)
+        }
+    }
+
+    /// Like [exec()](#method.exec), but returns an error instead of panicking if libpcre
+    /// abandons the match because it exceeded the match or recursion limit, and instead of
+    /// not matching if it does not support the matching options.
+    ///
+    /// ```
+    /// use pcre::{ExecError, Pcre, StudyOptions};
+    ///
+    /// let mut re = Pcre::compile("^(a+)+$").unwrap();
+    /// re.study_with_options(&StudyOptions::EXTRA_NEEDED);
+    /// # if let Some(extra) = re.extra() {
+    /// extra.set_match_limit(1000);
+    /// assert_eq!(re.try_exec("aaaaaaaaaaaaaaaaaaaaaaaaaaaaab").unwrap_err(), ExecError::MatchLimit);
+    /// assert!(re.try_exec("aaaa").unwrap().is_some());
+    /// # }
+    /// ```
     #[inline]
-    pub fn exec_from_with_options<'a, 'p>(&'p self, subject: &'a str, startoffset: usize, options: &EnumSet<ExecOption>) -> Option<Match<'a>> {
-        let ovecsize = (self.capture_count_ + 1) * 3;
-        let mut ovector = vec![0 as c_int; ovecsize as usize];
+    pub fn try_exec<'a>(&self, subject: &'a str) -> Result<Option<Match<'a>>, ExecError> {
+        self.try_exec_from_with_options(subject, 0, &ExecOptions::empty())
+    }
+
+    /// Like [exec_from_with_options()](#method.exec_from_with_options), but returns an error
+    /// instead of panicking if libpcre abandons the match because it exceeded the match or
+    /// recursion limit.
+    #[inline]
+    pub fn try_exec_from_with_options<'a>(&self, subject: &'a str, startoffset: usize, options: &ExecOptions) -> Result<Option<Match<'a>>, ExecError> {
+        self.try_exec_from_with_groups(subject, startoffset, options, self.capture_count())
+    }
+
+    fn try_exec_from_with_groups<'a>(&self, subject: &'a str, startoffset: usize, options: &ExecOptions, groups: usize) -> Result<Option<Match<'a>>, ExecError> {
+        if self.prefilter_.excludes(subject, startoffset, options) {
+            return Ok(None);
+        }
+
+        Ok(self.exec_with_mark(subject, startoffset, options, groups)?.map(|(m, _)| m))
+    }
+
+    /// Like [try_exec_from_with_groups()](#method.try_exec_from_with_groups), but also returns
+    /// the mark left by the match, which a match running on another thread cannot overwrite.
+    fn exec_with_mark<'a, 'p>(&'p self, subject: &'a str, startoffset: usize, options: &ExecOptions, groups: usize) -> Result<Option<MarkedMatch<'a, 'p>>, ExecError> {
+        let mut ovector = vec![-1 as c_int; self.ovector_len(groups)];
+
+        let (rc, mark) = self.exec_ovector(subject, startoffset as c_int, options, &mut ovector);
+        if rc >= 0 {
+            let m = Match::new(subject, &ovector, rc, self.name_table_.clone());
+            Ok(Some((m, unsafe { mark_bytes(mark) })))
+        } else {
+            ExecError::from_rc(rc).map_or(Ok(None), Err)
+        }
+    }
+
+    /// Returns the length of an ovector that records the first `groups` capture groups.
+    fn ovector_len(&self, groups: usize) -> usize {
+        (cmp::min(groups, self.capture_count()) + 1) * 3
+    }
 
+    /// Calls `pcre_exec()`, recording the mark if the use of the mark field is enabled.
+    ///
+    /// # Return value
+    /// The return code of `pcre_exec()` and the mark, which is null if there is none.
+    fn exec_ovector(&self, subject: &str, startoffset: c_int, options: &ExecOptions, ovector: &mut [c_int]) -> (c_int, *mut c_uchar) {
+        // Groups that pcre_exec() does not reach must read as unset, not as a previous match.
+        for offset in ovector.iter_mut() {
+            *offset = -1;
+        }
         unsafe {
+            if self.extra.is_null() || !(*self.extra).has_mark() {
+                let rc = detail::pcre_exec(self.code,
+                                         self.extra as *const PcreExtra,
+                                         subject.as_ptr() as *const c_char,
+                                         subject.len() as c_int,
+                                         startoffset,
+                                         options,
+                                         ovector.as_mut_ptr(),
+                                         ovector.len() as c_int);
+                return (rc, ptr::null_mut());
+            }
+
+            // Matches running on other threads must not write to the same mark pointer.
+            let mut extra = *self.extra;
+            let mut mark: *mut c_uchar = ptr::null_mut();
+            extra.set_mark(&mut mark);
             let rc = detail::pcre_exec(self.code,
-                                       self.extra as *const PcreExtra,
+                                       &extra,
                                        subject.as_ptr() as *const c_char,
                                        subject.len() as c_int,
-                                       startoffset as c_int,
+                                       startoffset,
                                        options,
                                        ovector.as_mut_ptr(),
-                                       ovecsize as c_int);
-            if rc >= 0 {
-                Some(Match {
-                    subject: subject,
-                    partial_ovector: ovector[..(((self.capture_count_ + 1) * 2) as usize)].to_vec(),
-                    string_count_: rc
-                })
-            } else {
-                None
+                                       ovector.len() as c_int);
+            self.mark_.store(mark, Ordering::Relaxed);
+            (rc, mark)
+        }
+    }
+
+    /// Matches the compiled regular expression against `subject` and builds a `T` from the
+    /// named capture groups of the match.
+    ///
+    /// The group names that `T` reads are looked up in the [name_table()](#method.name_table)
+    /// before matching.
+    ///
+    /// # Return value
+    /// `Ok(None)` if there is no match. An error if the regular expression lacks a group that
+    /// `T` reads, or if `T` could not be built from the captured substrings.
+    pub fn captures_into<T: FromCaptures>(&self, subject: &str) -> Result<Option<T>, CapturesError> {
+        let numbers = captures::group_numbers(T::GROUP_NAMES, self.name_table_.as_deref())?;
+        match self.exec(subject) {
+            None => Ok(None),
+            Some(m) => {
+                let groups: Vec<Option<&str>> = numbers.iter().map(|numbers| captures::first_set_group(&m, numbers)).collect();
+                T::from_groups(&groups).map(Some)
             }
         }
     }
@@ -480,14 +727,7 @@ impl Pcre {
     /// or was unsuccessful.
     #[inline]
     pub fn mark_bytes(&self) -> Option<&[u8]> {
-        unsafe {
-            if self.mark_.is_null() {
-                None
-            } else {
-                let mark_cstr = CStr::from_ptr(self.mark_ as *const c_char);
-                Some(mark_cstr.to_bytes())
-            }
-        }
+        unsafe { mark_bytes(self.mark_.load(Ordering::Relaxed)) }
     }
 
     /// Creates a `MatchIterator` for iterating through matches within the given subject
@@ -497,31 +737,48 @@ impl Pcre {
     /// * `subject` - The subject string.
     #[inline]
     pub fn matches<'a, 'p>(&'p self, subject: &'a str) -> MatchIterator<'a, 'p> {
-        let no_options: EnumSet<ExecOption> = EnumSet::new();
+        let no_options = ExecOptions::empty();
         self.matches_with_options(subject, &no_options)
     }
 
     /// Creates a `MatchIterator` for iterating through matches within the given subject
-    /// string `subject` using the given bitwise-OR'd matching options `options`.
+    /// string `subject` using the given matching options `options`.
     ///
     /// # Arguments
     /// * `subject` - The subject string.
-    /// * `options` - Bitwise-OR'd matching options. See the libpcre manpages, `man 3 pcre_exec`,
+    /// * `options` - Matching options, combined with `|`. See the libpcre manpages, `man 3 pcre_exec`,
     ///   for more information.
     #[inline]
-    pub fn matches_with_options<'a, 'p>(&'p self, subject: &'a str, options: &EnumSet<ExecOption>) -> MatchIterator<'a, 'p> {
+    pub fn matches_with_options<'a, 'p>(&'p self, subject: &'a str, options: &ExecOptions) -> MatchIterator<'a, 'p> {
+        self.matches_with_groups(subject, options, self.capture_count())
+    }
+
+    /// Creates a `MatchIterator` for iterating through matches within the given subject
+    /// string `subject` using the given matching options `options`, recording only the first
+    /// `groups` capture groups of each match. See
+    /// [exec_from_with_groups()](#method.exec_from_with_groups).
+    ///
+    /// # Arguments
+    /// * `subject` - The subject string.
+    /// * `options` - Matching options, combined with `|`.
+    /// * `groups` - The number of capture groups to record.
+    pub fn matches_with_groups<'a, 'p>(&'p self, subject: &'a str, options: &ExecOptions, groups: usize) -> MatchIterator<'a, 'p> {
+        MatchIterator {
+            re: self,
+            subject,
+            offset: 0,
+            options: *options,
+            ovector: vec![-1 as c_int; self.ovector_len(groups)],
+            prefilter: Some(&self.prefilter_)
+        }
+    }
+
+    /// Queries an `int`-valued item of information about the compiled regular expression.
+    fn fullinfo_int(&self, what: detail::fullinfo_field) -> c_int {
         unsafe {
-            let ovecsize = (self.capture_count_ + 1) * 3;
-            MatchIterator {
-                code: { detail::pcre_refcount(self.code as *mut detail::pcre, 1); self.code },
-                extra: self.extra as *const PcreExtra,
-                capture_count: self.capture_count_,
-                subject: subject,
-                offset: 0,
-                options: options.clone(),
-                ovector: vec![0 as c_int; ovecsize as usize],
-                _marker: PhantomData
-            }
+            let mut value: c_int = 0;
+            detail::pcre_fullinfo(self.code, self.extra as *const PcreExtra, what, &mut value as *mut c_int as *mut c_void);
+            value
         }
     }
 
@@ -529,7 +786,7 @@ impl Pcre {
     pub fn name_count(&self) -> usize {
         unsafe {
             let mut name_count: c_int = 0;
-            detail::pcre_fullinfo(self.code, self.extra as *const PcreExtra, libpcre_sys::PCRE_INFO_NAMECOUNT, &mut name_count as *mut c_int as *mut c_void);
+            detail::pcre_fullinfo(self.code, self.extra as *const PcreExtra, detail::PCRE_INFO_NAMECOUNT, &mut name_count as *mut c_int as *mut c_void);
             name_count as usize
         }
     }
@@ -544,9 +801,9 @@ impl Pcre {
         unsafe {
             let name_count = self.name_count();
             let mut tabptr: *const c_uchar = ptr::null();
-            detail::pcre_fullinfo(self.code, self.extra as *const PcreExtra, libpcre_sys::PCRE_INFO_NAMETABLE, &mut tabptr as *mut *const c_uchar as *mut c_void);
+            detail::pcre_fullinfo(self.code, self.extra as *const PcreExtra, detail::PCRE_INFO_NAMETABLE, &mut tabptr as *mut *const c_uchar as *mut c_void);
             let mut name_entry_size: c_int = 0;
-            detail::pcre_fullinfo(self.code, self.extra as *const PcreExtra, libpcre_sys::PCRE_INFO_NAMEENTRYSIZE, &mut name_entry_size as *mut c_int as *mut c_void);
+            detail::pcre_fullinfo(self.code, self.extra as *const PcreExtra, detail::PCRE_INFO_NAMEENTRYSIZE, &mut name_entry_size as *mut c_int as *mut c_void);
 
             let mut name_table: BTreeMap<String, Vec<usize>> = BTreeMap::new();
 
@@ -556,13 +813,7 @@ impl Pcre {
                 let name_cstr = CStr::from_ptr(tabptr.offset(2) as *const c_char);
                 // TODO Check memory allocations
                 let name: String = String::from_utf8(Vec::from(name_cstr.to_bytes())).unwrap();
-                // TODO Avoid the double lookup.
-                // https://github.com/mozilla/rust/issues/9068
-                if !name_table.contains_key(&name) {
-                    name_table.insert(name, vec![n]);
-                } else {
-                    name_table.get_mut(&name).unwrap().push(n);
-                }
+                name_table.entry(name).or_default().push(n);
                 tabptr = tabptr.offset(name_entry_size as isize);
                 i += 1;
             }
@@ -577,11 +828,11 @@ impl Pcre {
     /// # Return value
     /// `true` if additional information could be extracted. `false` otherwise.
     pub fn study(&mut self) -> bool {
-        let no_options: EnumSet<StudyOption> = EnumSet::new();
+        let no_options = StudyOptions::empty();
         self.study_with_options(&no_options)
     }
 
-    /// Studies the regular expression using the given bitwise-OR'd study options `options`
+    /// Studies the regular expression using the given study options `options`
     /// to see if additional information can be extracted which might speed up matching.
     ///
     /// # Argument
@@ -589,9 +840,9 @@ impl Pcre {
     ///   information about each option.
     ///
     /// # Return value
-    /// `true` if additional information could be extracted or the [`StudyExtraNeeded`](enum.StudyOption.html#variant.StudyExtraNeeded)
+    /// `true` if additional information could be extracted or the [`StudyOptions::EXTRA_NEEDED`](struct.StudyOptions.html#associatedconstant.EXTRA_NEEDED)
     /// option was passed. `false` otherwise.
-    pub fn study_with_options(&mut self, options: &EnumSet<StudyOption>) -> bool {
+    pub fn study_with_options(&mut self, options: &StudyOptions) -> bool {
         unsafe {
             // If something else has a reference to `code` then it probably has a pointer to
             // the current study data (if any). Thus, we shouldn't free the current study data
@@ -625,12 +876,30 @@ impl Drop for Pcre {
 }
 
 impl<'a> Match<'a> {
+    /// Creates a match from the ovector filled in by `pcre_exec()` and its return code `rc`.
+    fn new(subject: &'a str, ovector: &[c_int], rc: c_int, name_table: Option<Arc<BTreeMap<String, Vec<usize>>>>) -> Match<'a> {
+        let partial_ovector = ovector[..(ovector.len() / 3 * 2)].to_vec();
+        // pcre_exec() returns 0 if the ovector is too small to record every group that is
+        // set, in which case the highest group that it recorded is found by hand.
+        let string_count = if rc > 0 {
+            rc
+        } else {
+            partial_ovector.chunks(2).rposition(|offsets| offsets[0] >= 0).map_or(0, |n| n + 1) as c_int
+        };
+        Match {
+            subject,
+            partial_ovector,
+            string_count_: string_count,
+            name_table_: name_table
+        }
+    }
+
     /// Returns the start index within the subject string of capture group `n`.
     ///
     /// If the capture group is present in the pattern but wasn't captured then the start of it will be `usize::max_value()`.
     /// Happens with the optional groups, `/(optional)?/`.
     pub fn group_start(&self, n: usize) -> usize {
-        self.partial_ovector[(n * 2) as usize] as usize
+        self.partial_ovector[n * 2] as usize
     }
 
     /// Returns the end index within the subject string of capture group `n`.
@@ -638,77 +907,261 @@ impl<'a> Match<'a> {
     /// If the capture group is present in the pattern but wasn't captured then the end of it will be `usize::max_value()`.
     /// Happens with the optional groups, `/(optional)?/`.
     pub fn group_end(&self, n: usize) -> usize {
-        self.partial_ovector[(n * 2 + 1) as usize] as usize
+        self.partial_ovector[n * 2 + 1] as usize
     }
 
     /// Returns the length of the substring for capture group `n`.
     pub fn group_len(&self, n: usize) -> usize {
-        let group_offsets = &self.partial_ovector[((n * 2) as usize)..];
+        let group_offsets = &self.partial_ovector[(n * 2)..];
         (group_offsets[1] - group_offsets[0]) as usize
     }
 
     /// Returns the substring for capture group `n` as a slice.
     #[inline]
     pub fn group(&self, n: usize) -> &'a str {
-        let group_offsets = &self.partial_ovector[((n * 2) as usize)..];
+        let group_offsets = &self.partial_ovector[(n * 2)..];
         let start = group_offsets[0];
         let end = group_offsets[1];
         &self.subject[(start as usize)..(end as usize)]
     }
 
-    /// Returns the number of substrings captured.
+    /// Returns the substring for the capture group named `name`, or `None` if there is no
+    /// such group or it did not participate in the match.
+    ///
+    /// If the [`CompileOptions::DUPNAMES`](struct.CompileOptions.html#associatedconstant.DUPNAMES)
+    /// option allows several groups to share the name, the first of them that participated
+    /// in the match is used.
+    pub fn named_group(&self, name: &str) -> Option<&'a str> {
+        self.name_table_.as_ref()
+            .and_then(|name_table| name_table.get(name))
+            .and_then(|numbers| captures::first_set_group(self, numbers))
+    }
+
+    /// Appends `template`, rendered using this match, to `dst`. See
+    /// [Template](struct.Template.html) for the syntax of templates.
+    ///
+    /// Parse the template once with [Template::parse()](struct.Template.html#method.parse)
+    /// to render it for many matches.
+    pub fn expand(&self, template: &str, dst: &mut String) -> Result<(), TemplateError> {
+        Template::parse(template)?.expand(self, dst);
+        Ok(())
+    }
+
+    /// Returns the number of the highest capture group that is set, plus one.
+    ///
+    /// Groups below this number may still be unset; use [is_set()](#method.is_set) or
+    /// [get()](#method.get) to tell.
     pub fn string_count(&self) -> usize {
         self.string_count_ as usize
     }
+
+    /// Returns `true` if capture group `n` exists and participated in the match.
+    pub fn is_set(&self, n: usize) -> bool {
+        n < self.string_count() && self.partial_ovector[n * 2] >= 0
+    }
+
+    /// Returns the substring for capture group `n`, or `None` if there is no such group or it
+    /// did not participate in the match.
+    pub fn get(&self, n: usize) -> Option<&'a str> {
+        if self.is_set(n) {
+            Some(self.group(n))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the byte range within the subject string of capture group `n`.
+    ///
+    /// # Panics
+    /// If there is no such group or it did not participate in the match.
+    pub fn range(&self, n: usize) -> Range<usize> {
+        match self.get(n) {
+            None => panic!("capture group {} did not participate in the match", n),
+            Some(_) => self.group_start(n)..self.group_end(n)
+        }
+    }
+
+    /// Returns the range within the subject string of capture group `n`, counted in
+    /// characters (code points).
+    ///
+    /// This counts the characters before the group on every call. To convert the offsets of
+    /// many matches within the same subject, use a [LineIndex](struct.LineIndex.html).
+    ///
+    /// # Panics
+    /// If there is no such group or it did not participate in the match.
+    pub fn char_range(&self, n: usize) -> Range<usize> {
+        let range = self.range(n);
+        let start = self.subject[..range.start].chars().count();
+        start..(start + self.subject[range].chars().count())
+    }
+
+    /// Returns the range within the subject string of capture group `n`, counted in UTF-16
+    /// code units.
+    ///
+    /// This counts the code units before the group on every call. To convert the offsets of
+    /// many matches within the same subject, use a [LineIndex](struct.LineIndex.html).
+    ///
+    /// # Panics
+    /// If there is no such group or it did not participate in the match.
+    pub fn utf16_range(&self, n: usize) -> Range<usize> {
+        let range = self.range(n);
+        let start = position::utf16_len(&self.subject[..range.start]);
+        start..(start + position::utf16_len(&self.subject[range]))
+    }
+
+    /// Returns the line and column positions of the start and end of capture group `n`,
+    /// looked up in `index`, which must index the subject string of the match. Columns are
+    /// counted in characters (code points).
+    ///
+    /// # Panics
+    /// If there is no such group or it did not participate in the match.
+    pub fn positions(&self, n: usize, index: &LineIndex) -> Range<Position> {
+        let range = self.range(n);
+        index.position(range.start)..index.position(range.end)
+    }
+
+    /// Returns the number of capture groups in the regular expression, or the number of
+    /// groups requested from [Pcre::exec_from_with_groups()](struct.Pcre.html#method.exec_from_with_groups),
+    /// plus one for the whole match.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.partial_ovector.len() / 2
+    }
+
+    /// Returns an iterator over every capture group, starting with the whole match.
+    pub fn iter<'m>(&'m self) -> Groups<'m, 'a> {
+        Groups {
+            m: self,
+            range: 0..self.len()
+        }
+    }
+
+    /// Returns the substring that matched, which is the same as `group(0)`.
+    pub fn as_str(&self) -> &'a str {
+        self.group(0)
+    }
+
+    /// Returns the part of the subject string before the match.
+    pub fn pre_match(&self) -> &'a str {
+        &self.subject[..self.group_start(0)]
+    }
+
+    /// Returns the part of the subject string after the match.
+    pub fn post_match(&self) -> &'a str {
+        &self.subject[self.group_end(0)..]
+    }
 }
 
-impl<'a, 'p> Drop for MatchIterator<'a, 'p> {
-    fn drop(&mut self) {
-        unsafe {
-            if detail::pcre_refcount(self.code as *mut detail::pcre, -1) == 0 {
-                detail::pcre_free_study(self.extra as *mut PcreExtra);
-                detail::pcre_free(self.code as *mut detail::pcre as *mut c_void);
-            }
-            self.extra = ptr::null();
-            self.code = ptr::null();
+impl<'a> Index<usize> for Match<'a> {
+    type Output = str;
+
+    /// Returns the substring for capture group `n`.
+    ///
+    /// # Panics
+    /// If there is no such group or it did not participate in the match.
+    fn index(&self, n: usize) -> &str {
+        match self.get(n) {
+            None => panic!("capture group {} did not participate in the match", n),
+            Some(group) => group
+        }
+    }
+}
+
+impl<'a, 'n> Index<&'n str> for Match<'a> {
+    type Output = str;
+
+    /// Returns the substring for the capture group named `name`.
+    ///
+    /// # Panics
+    /// If there is no such group or it did not participate in the match.
+    fn index(&self, name: &'n str) -> &str {
+        match self.named_group(name) {
+            None => panic!("capture group `{}` did not participate in the match", name),
+            Some(group) => group
         }
     }
 }
 
+impl<'a> fmt::Debug for Match<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Match")
+            .field("range", &(self.group_start(0)..self.group_end(0)))
+            .field("groups", &self.iter().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl<'m, 'a> Iterator for Groups<'m, 'a> {
+    type Item = Option<&'a str>;
+
+    fn next(&mut self) -> Option<Option<&'a str>> {
+        self.range.next().map(|n| self.m.get(n))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl<'m, 'a> DoubleEndedIterator for Groups<'m, 'a> {
+    fn next_back(&mut self) -> Option<Option<&'a str>> {
+        self.range.next_back().map(|n| self.m.get(n))
+    }
+}
+
+impl<'m, 'a> ExactSizeIterator for Groups<'m, 'a> {}
+
+impl<'m, 'a> IntoIterator for &'m Match<'a> {
+    type Item = Option<&'a str>;
+    type IntoIter = Groups<'m, 'a>;
+
+    fn into_iter(self) -> Groups<'m, 'a> {
+        self.iter()
+    }
+}
+
 impl<'a, 'p> Iterator for MatchIterator<'a, 'p> {
     type Item = Match<'a>;
 
     /// Gets the next match.
     #[inline]
     fn next(&mut self) -> Option<Match<'a>> {
-        unsafe {
-            if self.subject.len() < self.offset as usize {
+        if self.subject.len() < self.offset as usize {
+            return None;
+        }
+        if let Some(prefilter) = self.prefilter.take() {
+            if prefilter.excludes(self.subject, self.offset as usize, &self.options) {
+                self.offset = self.subject.len() as c_int + 1;
                 return None;
             }
-            let rc = detail::pcre_exec(self.code,
-                                       self.extra,
-                                       self.subject.as_ptr() as *const c_char,
-                                       self.subject.len() as c_int,
-                                       self.offset,
-                                       &self.options,
-                                       self.ovector.as_mut_ptr(),
-                                       self.ovector.len() as c_int);
-            if rc >= 0 {
-                // Update the iterator state (make sure to always advance).
-                self.offset = std::cmp::max(self.offset + 1, self.ovector[1]);
-
-                Some(Match {
-                    subject: self.subject,
-                    partial_ovector: self.ovector[..(((self.capture_count + 1) * 2) as usize)].to_vec(),
-                    string_count_: rc
-                })
-            } else {
-                None
+        }
+        let (rc, _) = self.re.exec_ovector(self.subject, self.offset, &self.options, &mut self.ovector);
+        if rc >= 0 {
+            // Update the iterator state (make sure to always advance).
+            self.offset = std::cmp::max(self.offset + 1, self.ovector[1]);
+
+            Some(Match::new(self.subject, &self.ovector, rc, self.re.name_table_.clone()))
+        } else {
+            match ExecError::from_rc(rc) {
+                None | Some(ExecError::UnsupportedOptions) => None,
+                Some(err) => panic!("pcre_exec: {}", err)
             }
         }
     }
 }
 
+/// A match and the mark name that it left, if any.
+type MarkedMatch<'a, 'p> = (Match<'a>, Option<&'p [u8]>);
+
+/// Returns the mark name that `mark` points to within a compiled regular expression.
+unsafe fn mark_bytes<'p>(mark: *const c_uchar) -> Option<&'p [u8]> {
+    if mark.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(mark as *const c_char).to_bytes())
+    }
+}
+
 /// Returns libpcre version information.
 pub fn pcre_version() -> String {
     detail::pcre_version()
@@ -0,0 +1,152 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::error::{Error};
+use std::fmt;
+use std::string::{String};
+
+use {CompilationError, CompileOptions, ParseOptionsError};
+
+/// The error returned by [Pcre::compile_perl_literal()](struct.Pcre.html#method.compile_perl_literal).
+#[derive(Debug)]
+pub enum PerlLiteralError {
+    /// The literal does not start with `/` or `m` followed by a delimiter.
+    MissingDelimiter,
+
+    /// The closing delimiter was not found.
+    Unterminated,
+
+    /// A trailing modifier is not supported.
+    UnknownModifier(ParseOptionsError),
+
+    /// The pattern between the delimiters could not be compiled.
+    Compilation(CompilationError)
+}
+
+impl fmt::Display for PerlLiteralError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PerlLiteralError::MissingDelimiter => write!(f, "missing opening delimiter in pattern literal"),
+            PerlLiteralError::Unterminated => write!(f, "missing closing delimiter in pattern literal"),
+            PerlLiteralError::UnknownModifier(ref err) => write!(f, "{}", err),
+            PerlLiteralError::Compilation(ref err) => write!(f, "{}", err)
+        }
+    }
+}
+
+impl Error for PerlLiteralError {}
+
+impl From<CompilationError> for PerlLiteralError {
+    fn from(err: CompilationError) -> PerlLiteralError {
+        PerlLiteralError::Compilation(err)
+    }
+}
+
+/// Returns the closing delimiter for the opening delimiter `open`.
+fn closing_delimiter(open: char) -> char {
+    match open {
+        '(' => ')',
+        '[' => ']',
+        '{' => '}',
+        '<' => '>',
+        _ => open
+    }
+}
+
+/// Splits a Perl-style pattern literal such as `/foo/i` or `m{a/b}x` into the pattern
+/// and the compile options given by the trailing modifiers.
+pub fn parse_perl_literal(literal: &str) -> Result<(&str, CompileOptions), PerlLiteralError> {
+    let body = if literal.starts_with('/') {
+        literal
+    } else {
+        match literal.strip_prefix('m') {
+            None => return Err(PerlLiteralError::MissingDelimiter),
+            Some(body) => body
+        }
+    };
+
+    let open = match body.chars().next() {
+        Some(c) if !c.is_alphanumeric() && !c.is_whitespace() && c != '\\' => c,
+        _ => return Err(PerlLiteralError::MissingDelimiter)
+    };
+    let close = closing_delimiter(open);
+    let pattern_start = open.len_utf8();
+
+    // Bracketing delimiters nest, so track the depth of unescaped pairs.
+    let mut depth = 0;
+    let mut escaped = false;
+    let mut pattern_end = None;
+    for (i, c) in body[pattern_start..].char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == close && depth == 0 {
+            pattern_end = Some(pattern_start + i);
+            break;
+        } else if c == close {
+            depth -= 1;
+        } else if c == open {
+            depth += 1;
+        }
+    }
+
+    let pattern_end = match pattern_end {
+        None => return Err(PerlLiteralError::Unterminated),
+        Some(pattern_end) => pattern_end
+    };
+    let modifiers = &body[(pattern_end + close.len_utf8())..];
+    let options = match modifiers.parse::<CompileOptions>() {
+        Err(err) => return Err(PerlLiteralError::UnknownModifier(err)),
+        Ok(options) => options
+    };
+
+    Ok((&body[pattern_start..pattern_end], options))
+}
+
+/// Formats `pattern` as a Perl-style pattern literal, using `/` as the delimiter unless
+/// the pattern contains a slash.
+///
+/// # Return value
+/// `None` if every delimiter appears in the pattern and a slash is quoted by `\Q...\E`,
+/// where it cannot be escaped.
+pub fn to_perl_literal(pattern: &str, modifiers: &str) -> Option<String> {
+    if !pattern.contains('/') {
+        return Some(format!("/{}/{}", pattern, modifiers));
+    }
+
+    for &(open, close) in [('{', '}'), ('!', '!'), ('#', '#'), ('|', '|'), ('~', '~')].iter() {
+        if !pattern.contains(open) && !pattern.contains(close) {
+            return Some(format!("m{}{}{}{}", open, pattern, close, modifiers));
+        }
+    }
+
+    // Escaping a slash does not change its meaning in a regular expression, but an
+    // already-escaped slash must be left alone. Within `\Q...\E` the backslash would be
+    // matched literally.
+    let mut escaped_pattern = String::with_capacity(pattern.len() + 8);
+    let mut escaped = false;
+    let mut quoted = false;
+    for c in pattern.chars() {
+        if quoted {
+            if c == '/' {
+                return None;
+            }
+            quoted = !(escaped && c == 'E');
+            escaped = c == '\\' && quoted;
+        } else {
+            if c == '/' && !escaped {
+                escaped_pattern.push('\\');
+            }
+            quoted = escaped && c == 'Q';
+            escaped = c == '\\' && !escaped;
+        }
+        escaped_pattern.push(c);
+    }
+    Some(format!("/{}/{}", escaped_pattern, modifiers))
+}
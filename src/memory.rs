@@ -0,0 +1,307 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use libc::{c_void};
+use std::alloc;
+use std::alloc::{Layout};
+use std::cell::{Cell, RefCell};
+use std::error::{Error};
+use std::fmt;
+use std::mem;
+use std::option::{Option};
+use std::ptr;
+use std::result::{Result};
+use std::sync::{Arc, Once};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use {detail};
+
+/// The alignment of the memory handed to libpcre, which is at least that of `malloc()`.
+const ALIGN: usize = 16;
+
+/// The header in front of each allocation made for libpcre, which takes up `ALIGN` bytes so
+/// that the memory after it stays aligned.
+struct Header {
+
+    /// The size of the allocation, including the header.
+    size: usize,
+
+    /// The budget that the allocation is charged to, from `Arc::into_raw()`, or null.
+    budget: *const Budget
+
+}
+
+const _: () = assert!(mem::size_of::<Header>() <= ALIGN);
+
+/// Completed once the allocation functions of libpcre have been replaced.
+static INSTALL: Once = Once::new();
+
+/// The byte counts of a [MemoryBudget](struct.MemoryBudget.html).
+#[derive(Debug)]
+struct Budget {
+
+    limit: usize,
+
+    used: AtomicUsize,
+
+    peak: AtomicUsize
+
+}
+
+impl Budget {
+    /// Accounts for `size` more bytes, unless that would exceed the limit.
+    fn reserve(&self, size: usize) -> bool {
+        let mut used = self.used.load(Ordering::Relaxed);
+        loop {
+            let new_used = match used.checked_add(size) {
+                Some(new_used) if new_used <= self.limit => new_used,
+                _ => return false
+            };
+            match self.used.compare_exchange_weak(used, new_used, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => {
+                    self.peak.fetch_max(new_used, Ordering::Relaxed);
+                    return true;
+                },
+                Err(current) => used = current
+            }
+        }
+    }
+
+    fn release(&self, size: usize) {
+        self.used.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+/// A limit on the memory that libpcre allocates, and an account of the memory it has
+/// allocated and not yet freed.
+///
+/// Allocations are charged to a budget while running [scope()](#method.scope), and stay
+/// charged until libpcre frees them, even if that happens after the scope has ended or on
+/// another thread. So the memory of a compiled regular expression is accounted for until
+/// the `Pcre` is dropped.
+///
+/// Clones share the same budget.
+///
+/// ```
+/// use pcre::{MemoryBudget, Pcre};
+///
+/// unsafe { pcre::install_allocator() };
+///
+/// let budget = MemoryBudget::new(64 * 1024);
+/// let re = budget.scope(|| Pcre::compile("(?:ab|cd)+e")).unwrap().unwrap();
+/// assert!(budget.used() > 0);
+/// drop(re);
+/// assert_eq!(budget.used(), 0);
+///
+/// let small = MemoryBudget::new(16);
+/// assert!(small.scope(|| Pcre::compile("(?:ab|cd)+e")).is_err());
+/// ```
+///
+/// Budgets require the allocation functions of libpcre to be replaced first, by calling
+/// [install_allocator()](fn.install_allocator.html). Memory that the JIT compiler allocates
+/// for machine code is not accounted for, nor are the allocations of
+/// [Pcre16](struct.Pcre16.html) and [Pcre32](struct.Pcre32.html).
+#[derive(Clone, Debug)]
+pub struct MemoryBudget {
+
+    inner: Arc<Budget>
+
+}
+
+/// The error returned by [MemoryBudget::scope()](struct.MemoryBudget.html#method.scope) when
+/// libpcre requested more memory than the budget had left, or than the Rust global allocator
+/// could provide.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryLimitExceeded {
+
+    /// The size of the refused or failed allocation, in bytes.
+    pub requested: usize,
+
+    /// The number of bytes charged to the budget when the allocation was refused.
+    pub used: usize,
+
+    /// The limit of the budget, in bytes.
+    pub limit: usize
+
+}
+
+impl fmt::Display for MemoryLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "libpcre allocation of {} bytes exceeds the memory budget ({} of {} bytes in use)", self.requested, self.used, self.limit)
+    }
+}
+
+impl Error for MemoryLimitExceeded {}
+
+thread_local! {
+    /// The budget of the innermost scope running on this thread.
+    static CURRENT: RefCell<Option<Arc<Budget>>> = const { RefCell::new(None) };
+
+    /// The first allocation refused or failed within the innermost scope running on this
+    /// thread.
+    static REFUSED: Cell<Option<MemoryLimitExceeded>> = const { Cell::new(None) };
+}
+
+/// Replaces the allocation functions of libpcre, so that its allocations are made by the
+/// Rust global allocator and can be charged to a [MemoryBudget](struct.MemoryBudget.html).
+///
+/// Calling this again has no effect.
+///
+/// # Safety
+/// Must be called before any other use of libpcre, and in particular while no other thread
+/// uses libpcre: memory that libpcre allocated before cannot be freed once the allocation
+/// functions are replaced.
+pub unsafe fn install_allocator() {
+    INSTALL.call_once(|| detail::install_allocator());
+}
+
+/// Allocates `size` bytes for libpcre, charged to the budget of the current scope.
+///
+/// # Return value
+/// A null pointer if the allocation would exceed the budget or failed.
+pub fn allocate(size: usize) -> *mut c_void {
+    let layout = match size.checked_add(ALIGN).map(|total| Layout::from_size_align(total, ALIGN)) {
+        Some(Ok(layout)) => layout,
+        _ => return ptr::null_mut()
+    };
+    // The thread-local budget is gone if libpcre allocates while the thread is exiting.
+    let budget = CURRENT.try_with(|current| current.borrow().clone()).unwrap_or(None);
+    if let Some(ref budget) = budget {
+        if !budget.reserve(layout.size()) {
+            refuse(budget, size);
+            return ptr::null_mut();
+        }
+    }
+
+    let start = unsafe { alloc::alloc(layout) };
+    if start.is_null() {
+        if let Some(ref budget) = budget {
+            budget.release(layout.size());
+            refuse(budget, size);
+        }
+        return ptr::null_mut();
+    }
+    unsafe {
+        (start as *mut Header).write(Header {
+            size: layout.size(),
+            budget: budget.map_or(ptr::null(), Arc::into_raw)
+        });
+        start.add(ALIGN) as *mut c_void
+    }
+}
+
+/// Records the first allocation of `size` bytes that could not be made within the current
+/// scope, so that the scope reports it instead of the result of the failed libpcre call.
+fn refuse(budget: &Budget, size: usize) {
+    REFUSED.with(|refused| if refused.get().is_none() {
+        refused.set(Some(MemoryLimitExceeded {
+            requested: size,
+            used: budget.used.load(Ordering::Relaxed),
+            limit: budget.limit
+        }));
+    });
+}
+
+/// Frees memory that libpcre allocated.
+///
+/// # Safety
+/// `ptr` must be null or a pointer that libpcre allocated and has not freed yet.
+pub unsafe fn deallocate(ptr: *mut c_void) {
+    if ptr.is_null() {
+        return;
+    }
+    let start = (ptr as *mut u8).sub(ALIGN);
+    let header = (start as *mut Header).read();
+    alloc::dealloc(start, Layout::from_size_align_unchecked(header.size, ALIGN));
+    if !header.budget.is_null() {
+        let budget = Arc::from_raw(header.budget);
+        budget.release(header.size);
+    }
+}
+
+/// Returns `true` if a scope is running on this thread.
+#[cfg(feature = "pcre2")]
+pub fn in_scope() -> bool {
+    CURRENT.try_with(|current| current.borrow().is_some()).unwrap_or(false)
+}
+
+/// Returns `true` if an allocation failed within the scope running on this thread, in which
+/// case libpcre running out of memory is reported by the scope.
+pub fn refused() -> bool {
+    REFUSED.try_with(|refused| refused.get().is_some()).unwrap_or(false)
+}
+
+/// Restores the enclosing scope when a scope ends, even by panicking.
+struct ScopeGuard {
+
+    previous: Option<Arc<Budget>>,
+
+    previous_refused: Option<MemoryLimitExceeded>
+
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        CURRENT.with(|current| *current.borrow_mut() = self.previous.take());
+        REFUSED.with(|refused| refused.set(self.previous_refused));
+    }
+}
+
+impl MemoryBudget {
+    /// Creates a budget that allows libpcre to have up to `limit` bytes allocated at once.
+    pub fn new(limit: usize) -> MemoryBudget {
+        MemoryBudget {
+            inner: Arc::new(Budget {
+                limit,
+                used: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0)
+            })
+        }
+    }
+
+    /// Returns the limit of the budget, in bytes.
+    pub fn limit(&self) -> usize {
+        self.inner.limit
+    }
+
+    /// Returns the number of bytes that libpcre allocated within scopes of this budget and
+    /// has not freed yet.
+    pub fn used(&self) -> usize {
+        self.inner.used.load(Ordering::Relaxed)
+    }
+
+    /// Returns the highest number of bytes that were charged to the budget at once.
+    pub fn peak(&self) -> usize {
+        self.inner.peak.load(Ordering::Relaxed)
+    }
+
+    /// Runs `f`, charging the libpcre allocations that it makes on this thread to the budget.
+    ///
+    /// # Return value
+    /// The result of `f`, or `MemoryLimitExceeded` if libpcre requested more memory than the
+    /// budget had left or than could be allocated. The result of `f` is then dropped: libpcre fails compilations that
+    /// run out of memory, and matches that run out of memory do not match.
+    ///
+    /// # Panics
+    /// If [install_allocator()](fn.install_allocator.html) has not been called.
+    pub fn scope<F, R>(&self, f: F) -> Result<R, MemoryLimitExceeded>
+        where F: FnOnce() -> R
+    {
+        assert!(INSTALL.is_completed(), "pcre::install_allocator() must be called before using a MemoryBudget");
+
+        let _guard = ScopeGuard {
+            previous: CURRENT.with(|current| current.borrow_mut().replace(self.inner.clone())),
+            previous_refused: REFUSED.with(|refused| refused.replace(None))
+        };
+        let result = f();
+        match REFUSED.with(|refused| refused.get()) {
+            None => Ok(result),
+            Some(err) => Err(err)
+        }
+    }
+}
@@ -0,0 +1,471 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use libc::{c_int};
+#[cfg(feature = "serde")]
+use std::convert::{TryFrom};
+use std::error::{Error};
+use std::fmt;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not, Sub, SubAssign};
+use std::str::{FromStr};
+
+/// Defines a set of libpcre option bits.
+///
+/// Each flag is an associated constant holding the same bit value as the corresponding
+/// `PCRE_*` macro in `pcre.h`, so converting a set to the raw `c_int` expected by libpcre
+/// is a no-op. Multi-bit values such as the newline conventions are listed before their
+/// component bits so that `Debug` prints the most specific name.
+macro_rules! option_set {
+    (
+        $(#[$attr:meta])*
+        pub struct $name:ident {
+            $(
+                $(#[$flag_attr:meta])*
+                const $flag:ident = $value:expr;
+            )+
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+        pub struct $name {
+            bits: c_int
+        }
+
+        impl $name {
+            $(
+                $(#[$flag_attr])*
+                pub const $flag: $name = $name { bits: $value };
+            )+
+
+            const NAMED: &[(&str, c_int)] = &[$((stringify!($flag), $value)),+];
+
+            /// Returns an empty set.
+            #[inline]
+            pub fn empty() -> $name {
+                $name { bits: 0 }
+            }
+
+            /// Returns the raw libpcre option bits.
+            #[inline]
+            pub fn bits(&self) -> c_int {
+                self.bits
+            }
+
+            /// Creates a set from raw libpcre option bits. Bits that do not correspond to a
+            /// known option are kept as is.
+            #[inline]
+            pub fn from_bits(bits: c_int) -> $name {
+                $name { bits }
+            }
+
+            /// Returns `true` if no options are set.
+            #[inline]
+            pub fn is_empty(&self) -> bool {
+                self.bits == 0
+            }
+
+            /// Returns `true` if all of the options in `other` are set in `self`.
+            #[inline]
+            pub fn contains(&self, other: $name) -> bool {
+                (self.bits & other.bits) == other.bits
+            }
+
+            /// Sets the options in `other`.
+            #[inline]
+            pub fn insert(&mut self, other: $name) {
+                self.bits |= other.bits;
+            }
+
+            /// Clears the options in `other`.
+            #[inline]
+            pub fn remove(&mut self, other: $name) {
+                self.bits &= !other.bits;
+            }
+
+            /// Returns the names of the options in the set, most specific first, and the
+            /// bits that do not correspond to a known option.
+            fn names(&self) -> (Vec<&'static str>, c_int) {
+                let mut names = Vec::new();
+                let mut remaining = self.bits;
+                for &(name, value) in $name::NAMED.iter() {
+                    if value != 0 && (remaining & value) == value {
+                        names.push(name);
+                        remaining &= !value;
+                    }
+                }
+                (names, remaining)
+            }
+
+            /// Returns the option named `name`, such as `"CASELESS"`.
+            #[cfg(feature = "serde")]
+            fn from_name(name: &str) -> Option<$name> {
+                $name::NAMED.iter().find(|&&(n, _)| n == name).map(|&(_, value)| $name { bits: value })
+            }
+        }
+
+        impl BitOr for $name {
+            type Output = $name;
+
+            #[inline]
+            fn bitor(self, other: $name) -> $name {
+                $name { bits: self.bits | other.bits }
+            }
+        }
+
+        impl BitOrAssign for $name {
+            #[inline]
+            fn bitor_assign(&mut self, other: $name) {
+                self.bits |= other.bits;
+            }
+        }
+
+        impl BitAnd for $name {
+            type Output = $name;
+
+            #[inline]
+            fn bitand(self, other: $name) -> $name {
+                $name { bits: self.bits & other.bits }
+            }
+        }
+
+        impl BitAndAssign for $name {
+            #[inline]
+            fn bitand_assign(&mut self, other: $name) {
+                self.bits &= other.bits;
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+
+            #[inline]
+            fn sub(self, other: $name) -> $name {
+                $name { bits: self.bits & !other.bits }
+            }
+        }
+
+        impl SubAssign for $name {
+            #[inline]
+            fn sub_assign(&mut self, other: $name) {
+                self.bits &= !other.bits;
+            }
+        }
+
+        impl Not for $name {
+            type Output = $name;
+
+            #[inline]
+            fn not(self) -> $name {
+                $name { bits: !self.bits }
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let (names, remaining) = self.names();
+                f.write_str(&names.join(" | "))?;
+                if remaining != 0 {
+                    if !names.is_empty() {
+                        f.write_str(" | ")?;
+                    }
+                    write!(f, "{:#x}", remaining)?;
+                } else if names.is_empty() {
+                    f.write_str("(empty)")?;
+                }
+                Ok(())
+            }
+        }
+
+        /// Serializes as a sequence of option names, followed by the bits that do not
+        /// correspond to a known option, if any.
+        #[cfg(feature = "serde")]
+        impl ::serde::Serialize for $name {
+            fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                use serde::ser::{SerializeSeq};
+
+                let (names, remaining) = self.names();
+                let mut seq = serializer.serialize_seq(None)?;
+                for name in names {
+                    seq.serialize_element(name)?;
+                }
+                if remaining != 0 {
+                    seq.serialize_element(&remaining)?;
+                }
+                seq.end()
+            }
+        }
+
+        /// Deserializes from a sequence of option names and raw bits, or from a string of
+        /// option names separated by `|`, such as `"CASELESS | MULTILINE"`.
+        #[cfg(feature = "serde")]
+        impl<'de> ::serde::Deserialize<'de> for $name {
+            fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<$name, D::Error> {
+                struct Visitor;
+
+                impl<'de> ::serde::de::Visitor<'de> for Visitor {
+                    type Value = $name;
+
+                    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                        f.write_str("a sequence of option names")
+                    }
+
+                    fn visit_str<E: ::serde::de::Error>(self, s: &str) -> Result<$name, E> {
+                        let mut options = $name::empty();
+                        for name in s.split('|').map(str::trim).filter(|name| !name.is_empty()) {
+                            match $name::from_name(name) {
+                                None => return Err(E::custom(format_args!("unknown option `{}`", name))),
+                                Some(option) => options.insert(option)
+                            }
+                        }
+                        Ok(options)
+                    }
+
+                    fn visit_seq<A: ::serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<$name, A::Error> {
+                        use serde::de::{Error};
+
+                        let mut options = $name::empty();
+                        while let Some(element) = seq.next_element::<OptionElement>()? {
+                            match element {
+                                OptionElement::Bits(bits) => options.insert($name::from_bits(bits)),
+                                OptionElement::Name(name) => match $name::from_name(&name) {
+                                    None => return Err(A::Error::custom(format_args!("unknown option `{}`", name))),
+                                    Some(option) => options.insert(option)
+                                }
+                            }
+                        }
+                        Ok(options)
+                    }
+                }
+
+                deserializer.deserialize_any(Visitor)
+            }
+        }
+    }
+}
+
+/// An element of a serialized option set.
+#[cfg(feature = "serde")]
+enum OptionElement {
+    Name(String),
+    Bits(c_int)
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for OptionElement {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<OptionElement, D::Error> {
+        struct Visitor;
+
+        impl<'de> ::serde::de::Visitor<'de> for Visitor {
+            type Value = OptionElement;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an option name or raw option bits")
+            }
+
+            fn visit_str<E: ::serde::de::Error>(self, s: &str) -> Result<OptionElement, E> {
+                Ok(OptionElement::Name(s.to_string()))
+            }
+
+            fn visit_i64<E: ::serde::de::Error>(self, bits: i64) -> Result<OptionElement, E> {
+                match c_int::try_from(bits) {
+                    Err(_) => Err(E::invalid_value(::serde::de::Unexpected::Signed(bits), &self)),
+                    Ok(bits) => Ok(OptionElement::Bits(bits))
+                }
+            }
+
+            fn visit_u64<E: ::serde::de::Error>(self, bits: u64) -> Result<OptionElement, E> {
+                match c_int::try_from(bits) {
+                    Err(_) => Err(E::invalid_value(::serde::de::Unexpected::Unsigned(bits), &self)),
+                    Ok(bits) => Ok(OptionElement::Bits(bits))
+                }
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+option_set! {
+    /// A set of options for [Pcre::compile_with_options()](struct.Pcre.html#method.compile_with_options).
+    ///
+    /// See the libpcre manpages, `man 3 pcre_compile`, for more information about each option.
+    ///
+    /// A set can also be parsed from Perl-style modifier letters:
+    ///
+    /// ```
+    /// use pcre::CompileOptions;
+    ///
+    /// let options: CompileOptions = "imx".parse().unwrap();
+    /// assert!(options.contains(CompileOptions::CASELESS | CompileOptions::MULTILINE));
+    /// ```
+    pub struct CompileOptions {
+        const CASELESS = 0x00000001;
+        const MULTILINE = 0x00000002;
+        const DOTALL = 0x00000004;
+        const EXTENDED = 0x00000008;
+        const ANCHORED = 0x00000010;
+        const DOLLAR_ENDONLY = 0x00000020;
+        const EXTRA = 0x00000040;
+        const UNGREEDY = 0x00000200;
+        /// Note: The wrapper always compiles patterns in UTF-8 mode because subjects are `&str`.
+        const UTF8 = 0x00000800;
+        const NO_AUTO_CAPTURE = 0x00001000;
+        const NO_UTF8_CHECK = 0x00002000;
+        const AUTO_CALLOUT = 0x00004000;
+        /// Note: Requires PCRE version 8.33 or later. Every compilation with this option
+        /// fails, because the wrapper always sets [`UTF8`](#associatedconstant.UTF8).
+        const NEVER_UTF = 0x00010000;
+        /// Note: Requires PCRE version 8.34 or later.
+        const NO_AUTO_POSSESS = 0x00020000;
+        const FIRSTLINE = 0x00040000;
+        const DUPNAMES = 0x00080000;
+        const NEWLINE_CRLF = 0x00300000;
+        const NEWLINE_ANYCRLF = 0x00500000;
+        const NEWLINE_CR = 0x00100000;
+        const NEWLINE_LF = 0x00200000;
+        const NEWLINE_ANY = 0x00400000;
+        const BSR_ANYCRLF = 0x00800000;
+        const BSR_UNICODE = 0x01000000;
+        const JAVASCRIPT_COMPAT = 0x02000000;
+        const NO_START_OPTIMIZE = 0x04000000;
+        const UCP = 0x20000000;
+    }
+}
+
+option_set! {
+    /// A set of options for [Pcre::exec_from_with_options()](struct.Pcre.html#method.exec_from_with_options)
+    /// and [Pcre::matches_with_options()](struct.Pcre.html#method.matches_with_options).
+    ///
+    /// See the libpcre manpages, `man 3 pcre_exec`, for more information about each option.
+    pub struct ExecOptions {
+        const ANCHORED = 0x00000010;
+        const NOTBOL = 0x00000080;
+        const NOTEOL = 0x00000100;
+        const NOTEMPTY = 0x00000400;
+        const NO_UTF8_CHECK = 0x00002000;
+        const PARTIAL_SOFT = 0x00008000;
+        const NEWLINE_CRLF = 0x00300000;
+        const NEWLINE_ANYCRLF = 0x00500000;
+        const NEWLINE_CR = 0x00100000;
+        const NEWLINE_LF = 0x00200000;
+        const NEWLINE_ANY = 0x00400000;
+        const BSR_ANYCRLF = 0x00800000;
+        const BSR_UNICODE = 0x01000000;
+        const NO_START_OPTIMIZE = 0x04000000;
+        const PARTIAL_HARD = 0x08000000;
+        const NOTEMPTY_ATSTART = 0x10000000;
+    }
+}
+
+option_set! {
+    /// A set of options for [Pcre::study_with_options()](struct.Pcre.html#method.study_with_options).
+    ///
+    /// See the libpcre manpages, `man 3 pcre_study`, for more information about each option.
+    pub struct StudyOptions {
+        const JIT_COMPILE = 0x0001;
+        const JIT_PARTIAL_SOFT_COMPILE = 0x0002;
+        const JIT_PARTIAL_HARD_COMPILE = 0x0004;
+        /// Always create an extra block. Note: Requires PCRE version 8.32 or later.
+        const EXTRA_NEEDED = 0x0008;
+    }
+}
+
+impl ExecOptions {
+    /// Synonym for [`PARTIAL_SOFT`](#associatedconstant.PARTIAL_SOFT).
+    pub const PARTIAL: ExecOptions = ExecOptions::PARTIAL_SOFT;
+}
+
+impl CompileOptions {
+    /// Returns the option corresponding to the Perl pattern modifier `c`, if any.
+    ///
+    /// The supported modifiers are `i`, `m`, `s`, `x`, `U` (ungreedy), `J` (duplicate
+    /// names), `X` (extra) and `u` (Unicode properties).
+    pub fn from_perl_modifier(c: char) -> Option<CompileOptions> {
+        match c {
+            'i' => Some(CompileOptions::CASELESS),
+            'm' => Some(CompileOptions::MULTILINE),
+            's' => Some(CompileOptions::DOTALL),
+            'x' => Some(CompileOptions::EXTENDED),
+            'U' => Some(CompileOptions::UNGREEDY),
+            'J' => Some(CompileOptions::DUPNAMES),
+            'X' => Some(CompileOptions::EXTRA),
+            'u' => Some(CompileOptions::UCP),
+            _ => None
+        }
+    }
+
+    /// Returns the Perl pattern modifiers corresponding to this set, or `None` if the set
+    /// contains an option that has no modifier letter.
+    pub fn to_perl_modifiers(&self) -> Option<String> {
+        const MODIFIERS: &str = "imsxUJXu";
+
+        let mut modifiers = String::new();
+        let mut remaining = *self;
+        for c in MODIFIERS.chars() {
+            let option = CompileOptions::from_perl_modifier(c).unwrap();
+            if remaining.contains(option) {
+                modifiers.push(c);
+                remaining.remove(option);
+            }
+        }
+        if remaining.is_empty() {
+            Some(modifiers)
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for CompileOptions {
+    type Err = ParseOptionsError;
+
+    /// Parses a string of Perl pattern modifiers such as `"imsx"`.
+    fn from_str(s: &str) -> Result<CompileOptions, ParseOptionsError> {
+        let mut options = CompileOptions::empty();
+        for (offset, c) in s.char_indices() {
+            match CompileOptions::from_perl_modifier(c) {
+                None => return Err(ParseOptionsError {
+                    modifier: c,
+                    offset
+                }),
+                Some(option) => options.insert(option)
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// The error returned when parsing a string of Perl pattern modifiers fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseOptionsError {
+
+    modifier: char,
+
+    offset: usize
+
+}
+
+impl ParseOptionsError {
+    /// Returns the unrecognized modifier.
+    pub fn modifier(&self) -> char {
+        self.modifier
+    }
+
+    /// Returns the byte offset of the unrecognized modifier within the parsed string.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for ParseOptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown pattern modifier '{}' at offset {}", self.modifier, self.offset)
+    }
+}
+
+impl Error for ParseOptionsError {}
@@ -0,0 +1,175 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::ops::{Range};
+use std::vec::{Vec};
+
+/// A line and column within a subject string, both counted from zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+
+    /// The line number. Lines are terminated by `\n`.
+    pub line: usize,
+
+    /// The column within the line, in the unit of the method that returned the position.
+    pub column: usize
+
+}
+
+/// The start of a line within the subject string.
+#[derive(Clone, Copy, Debug)]
+struct Line {
+
+    /// The byte offset of the start of the line.
+    start: usize,
+
+    /// The number of characters before the line.
+    chars: usize,
+
+    /// The number of UTF-16 code units before the line.
+    utf16: usize
+
+}
+
+/// An index of the lines of a subject string, which converts byte offsets, such as those of
+/// a [Match](struct.Match.html), to character offsets, UTF-16 offsets and line and column
+/// positions.
+///
+/// Building the index takes one pass over the subject. Each conversion then takes time
+/// proportional to the length of the line that contains the offset, so build one index and
+/// reuse it for every match within the same subject.
+///
+/// ```
+/// use pcre::{LineIndex, Pcre, Position};
+///
+/// let subject = "première\nligne é deux";
+/// let index = LineIndex::new(subject);
+/// let re = Pcre::compile("deux").unwrap();
+/// let positions: Vec<Position> = re.matches(subject).map(|m| index.position(m.group_start(0))).collect();
+/// assert_eq!(positions, [Position { line: 1, column: 8 }]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct LineIndex<'a> {
+
+    subject: &'a str,
+
+    lines: Vec<Line>
+
+}
+
+impl<'a> LineIndex<'a> {
+    /// Builds the index of the lines of `subject`.
+    pub fn new(subject: &'a str) -> LineIndex<'a> {
+        let mut lines = vec![Line { start: 0, chars: 0, utf16: 0 }];
+        let (mut chars, mut utf16) = (0, 0);
+        for (offset, c) in subject.char_indices() {
+            chars += 1;
+            utf16 += c.len_utf16();
+            if c == '\n' {
+                lines.push(Line { start: offset + 1, chars, utf16 });
+            }
+        }
+        LineIndex {
+            subject,
+            lines
+        }
+    }
+
+    /// Returns the subject string.
+    pub fn subject(&self) -> &'a str {
+        self.subject
+    }
+
+    /// Returns the number of lines in the subject string. A trailing `\n` starts an empty
+    /// last line.
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Returns the byte range of line `line`, including its terminating `\n`, if any.
+    ///
+    /// # Panics
+    /// If there is no such line.
+    pub fn line_range(&self, line: usize) -> Range<usize> {
+        let end = match self.lines.get(line + 1) {
+            None => self.subject.len(),
+            Some(next) => next.start
+        };
+        self.lines[line].start..end
+    }
+
+    /// Returns the number of characters (code points) before the byte offset `offset`.
+    ///
+    /// # Panics
+    /// If `offset` is past the end of the subject string or not on a character boundary.
+    pub fn char_offset(&self, offset: usize) -> usize {
+        let (line, before) = self.locate(offset);
+        line.chars + before.chars().count()
+    }
+
+    /// Returns the number of UTF-16 code units before the byte offset `offset`.
+    ///
+    /// # Panics
+    /// If `offset` is past the end of the subject string or not on a character boundary.
+    pub fn utf16_offset(&self, offset: usize) -> usize {
+        let (line, before) = self.locate(offset);
+        line.utf16 + utf16_len(before)
+    }
+
+    /// Returns the line and column of the byte offset `offset`, where the column is counted
+    /// in characters (code points).
+    ///
+    /// # Panics
+    /// If `offset` is past the end of the subject string or not on a character boundary.
+    pub fn position(&self, offset: usize) -> Position {
+        let n = self.line_of(offset);
+        Position {
+            line: n,
+            column: self.line_prefix(n, offset).chars().count()
+        }
+    }
+
+    /// Returns the line and column of the byte offset `offset`, where the column is counted
+    /// in UTF-16 code units, as used by the Language Server Protocol.
+    ///
+    /// # Panics
+    /// If `offset` is past the end of the subject string or not on a character boundary.
+    pub fn utf16_position(&self, offset: usize) -> Position {
+        let n = self.line_of(offset);
+        Position {
+            line: n,
+            column: utf16_len(self.line_prefix(n, offset))
+        }
+    }
+
+    /// Returns the number of the line that contains the byte offset `offset`.
+    fn line_of(&self, offset: usize) -> usize {
+        assert!(offset <= self.subject.len(), "offset {} is past the end of the subject", offset);
+        match self.lines.binary_search_by(|line| line.start.cmp(&offset)) {
+            Ok(n) => n,
+            Err(n) => n - 1
+        }
+    }
+
+    /// Returns the part of line `n` before the byte offset `offset`.
+    fn line_prefix(&self, n: usize, offset: usize) -> &'a str {
+        &self.subject[self.lines[n].start..offset]
+    }
+
+    /// Returns the line that contains the byte offset `offset`, and the part of it before
+    /// `offset`.
+    fn locate(&self, offset: usize) -> (Line, &'a str) {
+        let n = self.line_of(offset);
+        (self.lines[n], self.line_prefix(n, offset))
+    }
+}
+
+/// Returns the number of UTF-16 code units needed to encode `s`.
+pub fn utf16_len(s: &str) -> usize {
+    s.chars().map(char::len_utf16).sum()
+}
@@ -0,0 +1,298 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use libc::{c_int};
+use memchr::{memchr, memchr2};
+use memchr::memmem::{Finder};
+use std::vec::{Vec};
+
+use {detail, CompileOptions, ExecOptions, Pcre};
+
+/// A cheap test that rules out subjects which cannot match a compiled regular expression.
+///
+/// The test is built from the first and required characters that libpcre records when
+/// compiling a pattern, and from the longest literal substring that every match of the
+/// pattern must contain.
+///
+/// Only ASCII first and required characters are used, and letters are always searched for
+/// in both cases: libpcre does not report whether a character must be matched caselessly,
+/// but for ASCII characters its own start-of-match optimizations never consider any other
+/// case. The literal substring is only used when the pattern cannot match caselessly.
+#[derive(Clone, Debug, Default)]
+pub struct Prefilter {
+
+    first: Option<u8>,
+
+    required: Option<u8>,
+
+    literal: Option<Finder<'static>>
+
+}
+
+impl Prefilter {
+    /// Creates the prefilter for the compiled regular expression `re`.
+    pub fn new(re: &Pcre) -> Prefilter {
+        // PCRE_INFO_FIRSTBYTE and PCRE_INFO_LASTLITERAL are available in every supported
+        // version of libpcre, unlike their PCRE_INFO_FIRSTCHARACTER counterparts.
+        let first = re.fullinfo_int(detail::PCRE_INFO_FIRSTBYTE);
+        let required = re.fullinfo_int(detail::PCRE_INFO_LASTLITERAL);
+        let literal = required_literal(re.pattern(), &re.compile_options())
+            .map(|literal| Finder::new(&literal).into_owned());
+        Prefilter {
+            first: ascii_byte(first),
+            required: ascii_byte(required),
+            literal
+        }
+    }
+
+    /// Returns `false` if `subject` cannot contain a match.
+    pub fn may_match(&self, subject: &[u8]) -> bool {
+        if let Some(required) = self.required {
+            if !contains_caseless(subject, required) {
+                return false;
+            }
+        }
+        if let Some(first) = self.first {
+            if !contains_caseless(subject, first) {
+                return false;
+            }
+        }
+        if let Some(ref literal) = self.literal {
+            if literal.find(subject).is_none() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Returns `true` if matching `subject` from `startoffset` using the matching options
+    /// `options` cannot find a match.
+    pub fn excludes(&self, subject: &str, startoffset: usize, options: &ExecOptions) -> bool {
+        // A partial match need not contain the required characters.
+        if options.contains(ExecOptions::PARTIAL_SOFT) || options.contains(ExecOptions::PARTIAL_HARD) {
+            return false;
+        }
+        match subject.as_bytes().get(startoffset..) {
+            None => false,
+            Some(rest) => !self.may_match(rest)
+        }
+    }
+}
+
+fn ascii_byte(c: c_int) -> Option<u8> {
+    if (0..128).contains(&c) {
+        Some(c as u8)
+    } else {
+        None
+    }
+}
+
+fn contains_caseless(haystack: &[u8], needle: u8) -> bool {
+    let lower = needle.to_ascii_lowercase();
+    let upper = needle.to_ascii_uppercase();
+    if lower == upper {
+        memchr(needle, haystack).is_some()
+    } else {
+        memchr2(lower, upper, haystack).is_some()
+    }
+}
+
+/// Returns the number of bytes at the start of `rest` taken by a quantifier, and its minimum
+/// repeat count.
+fn quantifier(rest: &[u8]) -> Option<(usize, usize)> {
+    let (len, min) = match rest.first() {
+        Some(&b'?') | Some(&b'*') => (1, 0),
+        Some(&b'+') => (1, 1),
+        Some(&b'{') => {
+            let digits = rest[1..].iter().take_while(|c| c.is_ascii_digit()).count();
+            let mut len = 1 + digits;
+            if rest.get(len) == Some(&b',') {
+                len += 1;
+                len += rest[len..].iter().take_while(|c| c.is_ascii_digit()).count();
+            }
+            // A brace that does not start a valid quantifier is a literal.
+            if digits == 0 || rest.get(len) != Some(&b'}') {
+                return None;
+            }
+            let min = ::std::str::from_utf8(&rest[1..(1 + digits)]).unwrap().parse().unwrap_or(usize::MAX);
+            (len + 1, min)
+        },
+        _ => return None
+    };
+
+    // Skip a lazy or possessive suffix.
+    match rest.get(len) {
+        Some(&b'?') | Some(&b'+') => Some((len + 1, min)),
+        _ => Some((len, min))
+    }
+}
+
+/// Returns the number of bytes at the start of `rest`, which starts with `(`, up to and
+/// including the matching `)`.
+fn group_len(rest: &[u8]) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < rest.len() {
+        // A comment such as `(?#...)` ends at the first closing parenthesis.
+        if rest[i..].starts_with(b"(?#") {
+            match rest[i..].iter().position(|&c| c == b')') {
+                None => return None,
+                Some(end) => {
+                    if depth == 0 {
+                        return Some(end + 1);
+                    }
+                    i += end + 1;
+                    continue;
+                }
+            }
+        }
+        match rest[i] {
+            b'\\' => {
+                if rest[(i + 1)..].starts_with(b"Q") {
+                    i = quoted_len(&rest[i..]) + i - 1;
+                } else {
+                    i += 1;
+                }
+            },
+            b'[' => i += class_len(&rest[i..]) - 1,
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            },
+            _ => ()
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Returns the number of bytes at the start of `rest`, which starts with `[`, up to and
+/// including the closing `]` of the character class.
+fn class_len(rest: &[u8]) -> usize {
+    let mut i = 1;
+    if rest.get(i) == Some(&b'^') {
+        i += 1;
+    }
+    // A `]` right after the opening bracket is a literal.
+    if rest.get(i) == Some(&b']') {
+        i += 1;
+    }
+    while i < rest.len() {
+        match rest[i] {
+            b'\\' => i += 1,
+            b'[' if rest[(i + 1)..].starts_with(b":") => {
+                // A POSIX class such as `[:alpha:]`.
+                if let Some(end) = rest[(i + 2)..].windows(2).position(|w| w == b":]") {
+                    i += end + 3;
+                }
+            },
+            b']' => return i + 1,
+            _ => ()
+        }
+        i += 1;
+    }
+    rest.len()
+}
+
+/// Returns the number of bytes at the start of `rest`, which starts with `\Q`, up to and
+/// including the closing `\E`, if any.
+fn quoted_len(rest: &[u8]) -> usize {
+    match rest[2..].windows(2).position(|w| w == b"\\E") {
+        None => rest.len(),
+        Some(end) => end + 4
+    }
+}
+
+/// Returns the longest literal substring that every match of `pattern` must contain, or
+/// `None` if it cannot be determined simply.
+///
+/// The analysis only looks at the top level of the pattern and gives up on top-level
+/// alternations, on `(*ACCEPT)` and on anything that changes how literal characters are
+/// matched, such as caseless or extended mode.
+pub fn required_literal(pattern: &str, options: &CompileOptions) -> Option<Vec<u8>> {
+    if options.contains(CompileOptions::CASELESS) || options.contains(CompileOptions::EXTENDED) {
+        return None;
+    }
+    // A match ends at `(*ACCEPT)`, so the literals after it, even outside of the group that
+    // contains it, are not required.
+    if pattern.contains("(*ACCEPT") {
+        return None;
+    }
+
+    let bytes = pattern.as_bytes();
+    let mut best: Vec<u8> = Vec::new();
+    let mut run: Vec<u8> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        // Find the extent of the next atom and whether it is a single literal character.
+        let (atom_len, is_literal) = match c {
+            b'|' => return None,
+            b'(' => {
+                let len = group_len(&bytes[i..])?;
+                // An option setting such as `(?i)` applies to the rest of the pattern.
+                let group = &bytes[i..(i + len)];
+                let is_option_setting = group.starts_with(b"(?") &&
+                    group[2..(len - 1)].iter().all(|&c| c == b'-' || c.is_ascii_alphabetic());
+                if is_option_setting && (group.contains(&b'i') || group.contains(&b'x')) {
+                    return None;
+                }
+                (len, false)
+            },
+            b'[' => (class_len(&bytes[i..]), false),
+            b'\\' => match bytes.get(i + 1) {
+                None => return None,
+                Some(&b'Q') => (quoted_len(&bytes[i..]), false),
+                Some(&e) if e.is_ascii_punctuation() => (2, true),
+                // Escapes that stand for a single item. Escapes that take an argument, such
+                // as `\x41` or `\p{L}`, are not worth parsing.
+                Some(&e) if b"dDwWsShHvVRXbBAzZGKEntrfea".contains(&e) => (2, false),
+                Some(_) => return None
+            },
+            b'.' | b'^' | b'$' => (1, false),
+            b'?' | b'*' | b'+' => return None,
+            b'{' if quantifier(&bytes[i..]).is_some() => return None,
+            // libpcre2 10.43 and later also read `{,n}` and braces with spaces as quantifiers,
+            // so only libpcre is known to take any other brace literally.
+            #[cfg(feature = "pcre2")]
+            b'{' => return None,
+            _ => {
+                // A whole UTF-8 sequence.
+                let len = 1 + bytes[(i + 1)..].iter().take_while(|&&b| (b & 0xC0) == 0x80).count();
+                (len, true)
+            }
+        };
+        let atom = &bytes[i..(i + atom_len)];
+        i += atom_len;
+
+        let (quantifier_len, min) = quantifier(&bytes[i..]).unwrap_or((0, 1));
+        i += quantifier_len;
+
+        if is_literal && min > 0 {
+            run.extend_from_slice(if atom[0] == b'\\' { &atom[1..] } else { atom });
+        }
+        if !is_literal || quantifier_len > 0 {
+            if run.len() > best.len() {
+                best = run.clone();
+            }
+            run.clear();
+        }
+    }
+    if run.len() > best.len() {
+        best = run;
+    }
+
+    if best.is_empty() {
+        None
+    } else {
+        Some(best)
+    }
+}
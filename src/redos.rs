@@ -0,0 +1,960 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cmp;
+use std::fmt;
+use std::ops::{Range};
+use std::option::{Option};
+use std::string::{String};
+use std::vec::{Vec};
+
+use {CompileOptions};
+
+/// The construct that a [ReDosWarning](struct.ReDosWarning.html) is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReDosKind {
+    /// A repeated item contains another unbounded repeat that can match the same text, such
+    /// as `(a+)+` or `(\w+\s?)*`.
+    NestedQuantifier,
+
+    /// A repeated group has alternatives that can match the same text, such as `(a|a)*` or
+    /// `(\w|\d)+`.
+    OverlappingAlternatives
+}
+
+/// A construct that may make matching take exponential time, as found by
+/// [redos_warnings()](fn.redos_warnings.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReDosWarning {
+
+    /// What was found.
+    pub kind: ReDosKind,
+
+    /// The byte range within the pattern of the repeated item, including its quantifier.
+    pub range: Range<usize>,
+
+    /// The byte range within the pattern of the nested repeat, or of the alternative that
+    /// overlaps an earlier one.
+    pub inner: Range<usize>,
+
+    /// A rewrite of the repeated item that cannot backtrack into itself: its quantifier
+    /// made possessive, or the item wrapped in an atomic group if it is lazy. The rewrite
+    /// only matches the same strings if nothing after the item needs it to give up part of
+    /// what it matched.
+    pub suggestion: String
+
+}
+
+impl fmt::Display for ReDosWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.kind {
+            ReDosKind::NestedQuantifier => "nested quantifier",
+            ReDosKind::OverlappingAlternatives => "repeated overlapping alternatives"
+        };
+        write!(f, "{} at offset {} may backtrack catastrophically; consider `{}`", what, self.range.start, self.suggestion)
+    }
+}
+
+/// Looks for constructs in `pattern` that are prone to catastrophic backtracking, where a
+/// subject that almost matches makes libpcre try exponentially many ways of matching it.
+///
+/// Two constructs are reported, when they are repeated without bound by a quantifier that
+/// is not possessive:
+///
+/// * a nested unbounded repeat, where the outer item can match a string made only of
+///   characters that the inner item can match, as in `(a+)+`, `(a*b?)*` or `((ab)+)+`.
+///   Inner repeats within atomic groups, or that are possessive, are not counted.
+/// * alternatives that can start with the same character and match a string made only of
+///   characters that both can match, as in `(a|a)*`, `(a|aa)+` or `(\w|\d)+`
+///
+/// The analysis is a heuristic over an approximation of the characters that each item can
+/// match. It reports constructs that are slow only in combination with the rest of the
+/// pattern, and misses those that involve backreferences, recursion or repeats that are
+/// adjacent rather than nested, such as `\d+\d+`. Patterns that fail to compile are
+/// analyzed as well as possible, and groups nested more than 1000 deep are not analyzed.
+///
+/// [Pcre::redos_warnings()](struct.Pcre.html#method.redos_warnings) analyzes a compiled
+/// regular expression.
+///
+/// ```
+/// use pcre::{CompileOptions, ReDosKind};
+///
+/// let warnings = pcre::redos_warnings("^(\\w+\\s?)*$", &CompileOptions::empty());
+/// assert_eq!(warnings.len(), 1);
+/// assert_eq!(warnings[0].kind, ReDosKind::NestedQuantifier);
+/// assert_eq!((warnings[0].range.clone(), warnings[0].inner.clone()), (1..10, 2..5));
+/// assert_eq!(warnings[0].suggestion, "(\\w+\\s?)*+");
+///
+/// assert!(pcre::redos_warnings("^(\\w+\\s)*$", &CompileOptions::empty()).is_empty());
+/// ```
+pub fn redos_warnings(pattern: &str, options: &CompileOptions) -> Vec<ReDosWarning> {
+    let flags = Flags {
+        caseless: options.contains(CompileOptions::CASELESS),
+        dotall: options.contains(CompileOptions::DOTALL),
+        extended: options.contains(CompileOptions::EXTENDED),
+        ungreedy: options.contains(CompileOptions::UNGREEDY),
+        javascript: options.contains(CompileOptions::JAVASCRIPT_COMPAT)
+    };
+    let mut parser = Parser {
+        pattern,
+        bytes: pattern.as_bytes(),
+        pos: 0,
+        depth: 0
+    };
+    let mut warnings = Vec::new();
+    check(&parser.alternation(flags), pattern, &mut warnings);
+    // Analyze the rest of a pattern with unbalanced parentheses as well.
+    while parser.pos < parser.bytes.len() {
+        parser.pos += 1;
+        check(&parser.alternation(flags), pattern, &mut warnings);
+    }
+    warnings
+}
+
+/// The nesting depth of groups beyond which the rest of a pattern is not analyzed, which
+/// is above the default nesting limit of libpcre.
+const MAX_DEPTH: usize = 1000;
+
+/// The largest code point.
+const MAX_CHAR: u32 = 0x10FFFF;
+
+/// A set of characters, as sorted, disjoint and non-adjacent ranges of code points.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CharSet {
+
+    ranges: Vec<(u32, u32)>
+
+}
+
+impl CharSet {
+    fn empty() -> CharSet {
+        CharSet {
+            ranges: Vec::new()
+        }
+    }
+
+    fn all() -> CharSet {
+        CharSet::range(0, MAX_CHAR)
+    }
+
+    fn range(first: u32, last: u32) -> CharSet {
+        CharSet::from_ranges(vec![(first, last)])
+    }
+
+    fn chars(chars: &[u32]) -> CharSet {
+        CharSet::from_ranges(chars.iter().map(|&c| (c, c)).collect())
+    }
+
+    fn from_ranges(mut ranges: Vec<(u32, u32)>) -> CharSet {
+        ranges.retain(|&(first, last)| first <= last);
+        ranges.sort();
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+        for (first, last) in ranges {
+            match merged.last_mut() {
+                Some(prev) if first <= prev.1.saturating_add(1) => prev.1 = cmp::max(prev.1, last),
+                _ => merged.push((first, last))
+            }
+        }
+        CharSet {
+            ranges: merged
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    fn union(&self, other: &CharSet) -> CharSet {
+        CharSet::from_ranges(self.ranges.iter().chain(other.ranges.iter()).cloned().collect())
+    }
+
+    fn intersection(&self, other: &CharSet) -> CharSet {
+        let mut ranges = Vec::new();
+        for &(a, b) in &self.ranges {
+            for &(c, d) in &other.ranges {
+                if cmp::max(a, c) <= cmp::min(b, d) {
+                    ranges.push((cmp::max(a, c), cmp::min(b, d)));
+                }
+            }
+        }
+        CharSet::from_ranges(ranges)
+    }
+
+    fn complement(&self) -> CharSet {
+        let mut ranges = Vec::new();
+        let mut next = 0;
+        for &(first, last) in &self.ranges {
+            if first > next {
+                ranges.push((next, first - 1));
+            }
+            next = last + 1;
+        }
+        if next <= MAX_CHAR {
+            ranges.push((next, MAX_CHAR));
+        }
+        CharSet::from_ranges(ranges)
+    }
+
+    /// Adds the other case of the ASCII letters in the set.
+    fn caseless(&self) -> CharSet {
+        let upper = self.intersection(&CharSet::range(0x41, 0x5A));
+        let lower = self.intersection(&CharSet::range(0x61, 0x7A));
+        let mut ranges = self.ranges.clone();
+        ranges.extend(upper.ranges.iter().map(|&(first, last)| (first + 0x20, last + 0x20)));
+        ranges.extend(lower.ranges.iter().map(|&(first, last)| (first - 0x20, last - 0x20)));
+        CharSet::from_ranges(ranges)
+    }
+}
+
+/// Returns the set of characters matched by the escape `\e` that stands for a class of
+/// characters, such as `\d`, or `None` if it does not.
+fn escape_class(e: u8, flags: Flags) -> Option<CharSet> {
+    let digit = || CharSet::range(0x30, 0x39);
+    let word = || CharSet::from_ranges(vec![(0x30, 0x39), (0x41, 0x5A), (0x5F, 0x5F), (0x61, 0x7A)]);
+    let space = || CharSet::chars(&[0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x20]);
+    let horizontal = || CharSet::from_ranges(vec![(0x09, 0x09), (0x20, 0x20), (0xA0, 0xA0), (0x1680, 0x1680), (0x180E, 0x180E),
+                                                  (0x2000, 0x200A), (0x202F, 0x202F), (0x205F, 0x205F), (0x3000, 0x3000)]);
+    let vertical = || CharSet::from_ranges(vec![(0x0A, 0x0D), (0x85, 0x85), (0x2028, 0x2029)]);
+    Some(match e {
+        b'd' => digit(),
+        b'D' => digit().complement(),
+        b'w' => word(),
+        b'W' => word().complement(),
+        b's' => space(),
+        b'S' => space().complement(),
+        b'h' => horizontal(),
+        b'H' => horizontal().complement(),
+        b'v' | b'R' => vertical(),
+        b'V' => vertical().complement(),
+        b'N' => dot(Flags { dotall: false, ..flags }),
+        // Unicode properties and extended grapheme clusters are not worth modelling.
+        b'p' | b'P' | b'X' | b'C' => CharSet::all(),
+        _ => return None
+    })
+}
+
+/// Returns the set of characters matched by `.`.
+fn dot(flags: Flags) -> CharSet {
+    if flags.dotall {
+        CharSet::all()
+    } else {
+        CharSet::chars(&[0x0A]).complement()
+    }
+}
+
+/// Returns the set of characters in the POSIX class `name`, as in `[:name:]`.
+fn posix_class(name: &str) -> CharSet {
+    match name {
+        "alpha" => CharSet::from_ranges(vec![(0x41, 0x5A), (0x61, 0x7A)]),
+        "digit" => CharSet::range(0x30, 0x39),
+        "alnum" => CharSet::from_ranges(vec![(0x30, 0x39), (0x41, 0x5A), (0x61, 0x7A)]),
+        "word" => CharSet::from_ranges(vec![(0x30, 0x39), (0x41, 0x5A), (0x5F, 0x5F), (0x61, 0x7A)]),
+        "upper" => CharSet::range(0x41, 0x5A),
+        "lower" => CharSet::range(0x61, 0x7A),
+        "space" => CharSet::chars(&[0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x20]),
+        "blank" => CharSet::chars(&[0x09, 0x20]),
+        "xdigit" => CharSet::from_ranges(vec![(0x30, 0x39), (0x41, 0x46), (0x61, 0x66)]),
+        "punct" => CharSet::from_ranges(vec![(0x21, 0x2F), (0x3A, 0x40), (0x5B, 0x60), (0x7B, 0x7E)]),
+        "cntrl" => CharSet::from_ranges(vec![(0x00, 0x1F), (0x7F, 0x7F)]),
+        "print" => CharSet::range(0x20, 0x7E),
+        "graph" => CharSet::range(0x21, 0x7E),
+        "ascii" => CharSet::range(0x00, 0x7F),
+        _ => CharSet::all()
+    }
+}
+
+/// The options that affect how a part of a pattern is parsed.
+#[derive(Clone, Copy, Debug)]
+struct Flags {
+
+    caseless: bool,
+
+    dotall: bool,
+
+    extended: bool,
+
+    ungreedy: bool,
+
+    javascript: bool
+
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Greedy,
+    Lazy,
+    Possessive
+}
+
+#[derive(Debug)]
+enum NodeKind {
+    /// A single character from the set.
+    Char(CharSet),
+
+    /// Matches the empty string, such as an anchor or an option setting.
+    Empty,
+
+    /// Matches anything, such as a backreference or a recursion.
+    Unknown,
+
+    Concat(Vec<Node>),
+
+    Alternation(Vec<Node>),
+
+    /// A group that does not backtrack into itself: an atomic group or a lookaround, which
+    /// does not consume characters if `lookaround` is `true`.
+    Atomic(Box<Node>, bool),
+
+    Repeat(Box<Node>, u32, Option<u32>, Mode)
+}
+
+#[derive(Debug)]
+struct Node {
+
+    kind: NodeKind,
+
+    /// The byte range of the node within the pattern.
+    span: Range<usize>
+
+}
+
+impl Node {
+    /// Returns `true` if the node can match the empty string.
+    fn nullable(&self) -> bool {
+        match self.kind {
+            NodeKind::Char(_) => false,
+            NodeKind::Empty | NodeKind::Unknown => true,
+            NodeKind::Concat(ref nodes) => nodes.iter().all(Node::nullable),
+            NodeKind::Alternation(ref nodes) => nodes.iter().any(Node::nullable),
+            NodeKind::Atomic(ref node, lookaround) => lookaround || node.nullable(),
+            NodeKind::Repeat(ref node, min, _, _) => min == 0 || node.nullable()
+        }
+    }
+
+    /// Returns the characters that can appear in a match of the node.
+    fn chars(&self) -> CharSet {
+        match self.kind {
+            NodeKind::Char(ref set) => set.clone(),
+            NodeKind::Empty | NodeKind::Atomic(_, true) | NodeKind::Repeat(_, _, Some(0), _) => CharSet::empty(),
+            NodeKind::Unknown => CharSet::all(),
+            NodeKind::Concat(ref nodes) | NodeKind::Alternation(ref nodes) =>
+                nodes.iter().fold(CharSet::empty(), |set, node| set.union(&node.chars())),
+            NodeKind::Atomic(ref node, false) | NodeKind::Repeat(ref node, _, _, _) => node.chars()
+        }
+    }
+
+    /// Returns the characters that can start a match of the node.
+    fn first(&self) -> CharSet {
+        match self.kind {
+            NodeKind::Concat(ref nodes) => {
+                let mut set = CharSet::empty();
+                for node in nodes {
+                    set = set.union(&node.first());
+                    if !node.nullable() {
+                        break;
+                    }
+                }
+                set
+            },
+            NodeKind::Alternation(ref nodes) => nodes.iter().fold(CharSet::empty(), |set, node| set.union(&node.first())),
+            NodeKind::Atomic(ref node, false) => node.first(),
+            NodeKind::Repeat(ref node, _, max, _) if max != Some(0) => node.first(),
+            _ => self.chars()
+        }
+    }
+
+    /// Returns `true` if the node can match a string made only of characters in `set`.
+    fn within(&self, set: &CharSet) -> bool {
+        match self.kind {
+            NodeKind::Char(ref chars) => !chars.intersection(set).is_empty(),
+            NodeKind::Empty | NodeKind::Unknown | NodeKind::Atomic(_, true) => true,
+            NodeKind::Concat(ref nodes) => nodes.iter().all(|node| node.within(set)),
+            NodeKind::Alternation(ref nodes) => nodes.iter().any(|node| node.within(set)),
+            NodeKind::Atomic(ref node, false) => node.within(set),
+            NodeKind::Repeat(ref node, min, _, _) => min == 0 || node.within(set)
+        }
+    }
+}
+
+struct Parser<'p> {
+
+    pattern: &'p str,
+
+    bytes: &'p [u8],
+
+    pos: usize,
+
+    /// The nesting depth of the group being parsed.
+    depth: usize
+
+}
+
+impl<'p> Parser<'p> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).cloned()
+    }
+
+    fn rest(&self) -> &'p [u8] {
+        &self.bytes[cmp::min(self.pos, self.bytes.len())..]
+    }
+
+    /// Skips past the next occurrence of `end`, or to the end of the pattern.
+    fn skip_past(&mut self, end: &[u8]) {
+        self.pos = match self.rest().windows(end.len()).position(|w| w == end) {
+            None => self.bytes.len(),
+            Some(i) => self.pos + i + end.len()
+        };
+    }
+
+    /// Reads the character at the current position.
+    fn next_char(&mut self) -> u32 {
+        let c = self.pattern[self.pos..].chars().next().unwrap();
+        self.pos += c.len_utf8();
+        c as u32
+    }
+
+    /// Reads the digits in the given radix at the current position, at most `max_len` of
+    /// them.
+    fn number(&mut self, radix: u32, max_len: usize) -> u32 {
+        let len = self.rest().iter().take(max_len).take_while(|&&c| (c as char).is_digit(radix)).count();
+        let digits = &self.pattern[self.pos..(self.pos + len)];
+        self.pos += len;
+        u32::from_str_radix(digits, radix).unwrap_or(0)
+    }
+
+    /// Skips white space and comments in extended mode.
+    fn skip_extended(&mut self, flags: Flags) {
+        if !flags.extended {
+            return;
+        }
+        loop {
+            match self.peek() {
+                Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') | Some(0x0B) | Some(0x0C) => self.pos += 1,
+                Some(b'#') => self.skip_past(b"\n"),
+                _ => break
+            }
+        }
+    }
+
+    /// Parses alternatives up to the end of the enclosing group.
+    fn alternation(&mut self, mut flags: Flags) -> Node {
+        let start = self.pos;
+        let mut alternatives = vec![self.concat(&mut flags)];
+        while self.peek() == Some(b'|') {
+            self.pos += 1;
+            alternatives.push(self.concat(&mut flags));
+        }
+        if alternatives.len() == 1 {
+            alternatives.pop().unwrap()
+        } else {
+            Node {
+                kind: NodeKind::Alternation(alternatives),
+                span: start..self.pos
+            }
+        }
+    }
+
+    /// Parses a sequence of items up to the next `|` or the end of the enclosing group. An
+    /// option setting such as `(?i)` changes `flags` for the rest of the group.
+    fn concat(&mut self, flags: &mut Flags) -> Node {
+        let start = self.pos;
+        let mut nodes = Vec::new();
+        loop {
+            self.skip_extended(*flags);
+            match self.peek() {
+                None | Some(b'|') | Some(b')') => break,
+                _ => ()
+            }
+            let atom_start = self.pos;
+            let atom = self.atom(flags);
+            self.skip_extended(*flags);
+            let node = self.quantifier(atom, atom_start, *flags);
+            nodes.push(node);
+        }
+        Node {
+            kind: NodeKind::Concat(nodes),
+            span: start..self.pos
+        }
+    }
+
+    /// Parses the quantifier, if any, that follows `atom`.
+    fn quantifier(&mut self, atom: Node, start: usize, flags: Flags) -> Node {
+        let (min, max) = match self.peek() {
+            Some(b'*') => (0, None),
+            Some(b'+') => (1, None),
+            Some(b'?') => (0, Some(1)),
+            Some(b'{') => match braces(self.rest()) {
+                None => return atom,
+                Some((len, min, max)) => {
+                    self.pos += len - 1;
+                    (min, max)
+                }
+            },
+            _ => return atom
+        };
+        self.pos += 1;
+        let mode = match self.peek() {
+            Some(b'+') => Mode::Possessive,
+            Some(b'?') if flags.ungreedy => Mode::Greedy,
+            Some(b'?') => Mode::Lazy,
+            _ if flags.ungreedy => Mode::Lazy,
+            _ => Mode::Greedy
+        };
+        if mode == Mode::Possessive || self.peek() == Some(b'?') {
+            self.pos += 1;
+        }
+        Node {
+            kind: NodeKind::Repeat(Box::new(atom), min, max, mode),
+            span: start..self.pos
+        }
+    }
+
+    fn atom(&mut self, flags: &mut Flags) -> Node {
+        let start = self.pos;
+        let kind = match self.peek().unwrap() {
+            b'\\' => self.escape(*flags),
+            b'[' => NodeKind::Char(self.class(*flags)),
+            b'(' => self.group(flags),
+            b'.' => {
+                self.pos += 1;
+                NodeKind::Char(dot(*flags))
+            },
+            b'^' | b'$' => {
+                self.pos += 1;
+                NodeKind::Empty
+            },
+            _ => {
+                let c = self.next_char();
+                NodeKind::Char(literal(c, *flags))
+            }
+        };
+        Node {
+            kind,
+            span: start..self.pos
+        }
+    }
+
+    /// Parses an escape outside of a character class.
+    fn escape(&mut self, flags: Flags) -> NodeKind {
+        self.pos += 1;
+        let e = match self.peek() {
+            None => return NodeKind::Char(literal(b'\\' as u32, flags)),
+            Some(e) => e
+        };
+        if let Some(set) = escape_class(e, flags) {
+            self.skip_class_escape(e);
+            return NodeKind::Char(set);
+        }
+        match e {
+            b'b' | b'B' | b'A' | b'z' | b'Z' | b'G' | b'K' | b'E' => {
+                self.pos += 1;
+                NodeKind::Empty
+            },
+            b'Q' => {
+                self.pos += 1;
+                let start = self.pos;
+                let nodes = self.quoted().char_indices().map(|(i, c)| Node {
+                    kind: NodeKind::Char(literal(c as u32, flags)),
+                    span: (start + i)..(start + i + c.len_utf8())
+                }).collect();
+                NodeKind::Concat(nodes)
+            },
+            b'1'..=b'9' | b'g' | b'k' => {
+                // A backreference or a subroutine call.
+                self.pos += 1;
+                match self.peek() {
+                    Some(b'{') => self.skip_past(b"}"),
+                    Some(b'<') => self.skip_past(b">"),
+                    Some(b'\'') => {
+                        self.pos += 1;
+                        self.skip_past(b"'");
+                    },
+                    _ => {
+                        if self.peek() == Some(b'-') || self.peek() == Some(b'+') {
+                            self.pos += 1;
+                        }
+                        self.number(10, usize::MAX);
+                    }
+                }
+                NodeKind::Unknown
+            },
+            _ => NodeKind::Char(literal(self.escaped_char(), flags))
+        }
+    }
+
+    /// Skips the escape `\e` that stands for a class of characters, after the backslash,
+    /// including the property name of `\p` and `\P`.
+    fn skip_class_escape(&mut self, e: u8) {
+        self.pos += 1;
+        if e == b'p' || e == b'P' {
+            match self.peek() {
+                None => (),
+                Some(b'{') => self.skip_past(b"}"),
+                Some(_) => {
+                    self.next_char();
+                }
+            }
+        }
+    }
+
+    /// Reads an escaped character after the backslash, such as `\x41`, `\n` or `\.`.
+    fn escaped_char(&mut self) -> u32 {
+        let e = self.next_char();
+        match e as u8 {
+            _ if e > 0x7F => e,
+            b'x' if self.peek() == Some(b'{') => {
+                self.pos += 1;
+                let c = self.number(16, usize::MAX);
+                self.skip_past(b"}");
+                c
+            },
+            b'x' => self.number(16, 2),
+            b'o' if self.peek() == Some(b'{') => {
+                self.pos += 1;
+                let c = self.number(8, usize::MAX);
+                self.skip_past(b"}");
+                c
+            },
+            b'0' => self.number(8, 2),
+            b'c' => match self.peek() {
+                Some(c) if c.is_ascii() => {
+                    self.pos += 1;
+                    (c.to_ascii_uppercase() ^ 0x40) as u32
+                },
+                _ => b'c' as u32
+            },
+            b'a' => 0x07,
+            b'e' => 0x1B,
+            b'f' => 0x0C,
+            b'n' => 0x0A,
+            b'r' => 0x0D,
+            b't' => 0x09,
+            _ => e
+        }
+    }
+
+    /// Parses a character class.
+    fn class(&mut self, flags: Flags) -> CharSet {
+        self.pos += 1;
+        let negated = self.peek() == Some(b'^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut set = CharSet::empty();
+        // A `]` right after the opening bracket is a literal, except in JavaScript, where
+        // `[]` is an empty class.
+        let mut first = !flags.javascript;
+        loop {
+            let item = match self.peek() {
+                None => break,
+                Some(b']') if !first => {
+                    self.pos += 1;
+                    break;
+                },
+                Some(b'[') if self.rest().starts_with(b"[:") || self.rest().starts_with(b"[=") || self.rest().starts_with(b"[.") => {
+                    let end = self.rest()[2..].windows(2).position(|w| w == b":]" || w == b"=]" || w == b".]");
+                    match end {
+                        None => CharSet::chars(&[self.next_char()]),
+                        Some(end) => {
+                            let name = &self.pattern[(self.pos + 2)..(self.pos + 2 + end)];
+                            self.pos += end + 4;
+                            match name.strip_prefix('^') {
+                                None => posix_class(name),
+                                Some(name) => posix_class(name).complement()
+                            }
+                        }
+                    }
+                },
+                Some(b'\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        None => CharSet::empty(),
+                        Some(b'Q') => {
+                            self.pos += 1;
+                            let quoted = self.quoted();
+                            CharSet::chars(&quoted.chars().map(|c| c as u32).collect::<Vec<_>>())
+                        },
+                        Some(b'E') => {
+                            self.pos += 1;
+                            CharSet::empty()
+                        },
+                        Some(b'b') => {
+                            self.pos += 1;
+                            CharSet::chars(&[0x08])
+                        },
+                        Some(e) => match escape_class(e, flags) {
+                            Some(class) => {
+                                self.skip_class_escape(e);
+                                class
+                            },
+                            None => {
+                                let c = self.escaped_char();
+                                self.range(c, flags)
+                            }
+                        }
+                    }
+                },
+                Some(_) => {
+                    let c = self.next_char();
+                    self.range(c, flags)
+                }
+            };
+            set = set.union(&item);
+            first = false;
+        }
+        if flags.caseless {
+            set = set.caseless();
+        }
+        if negated {
+            set.complement()
+        } else {
+            set
+        }
+    }
+
+    /// Reads the text quoted by `\Q`, up to and past the closing `\E`, if any.
+    fn quoted(&mut self) -> &'p str {
+        let start = self.pos;
+        let end = match self.rest().windows(2).position(|w| w == b"\\E") {
+            None => self.bytes.len(),
+            Some(i) => start + i
+        };
+        self.pos = cmp::min(end + 2, self.bytes.len());
+        &self.pattern[start..end]
+    }
+
+    /// Parses the rest of a range within a character class that starts with `c`.
+    fn range(&mut self, c: u32, flags: Flags) -> CharSet {
+        if self.peek() != Some(b'-') || self.bytes.get(self.pos + 1).is_none() || self.bytes[self.pos + 1] == b']' {
+            return CharSet::chars(&[c]);
+        }
+        let saved = self.pos;
+        self.pos += 1;
+        let last = match self.peek() {
+            Some(b'\\') => {
+                self.pos += 1;
+                match self.peek() {
+                    // A class such as `\d` cannot end a range, so the `-` is a literal.
+                    Some(e) if escape_class(e, flags).is_some() => None,
+                    Some(_) => Some(self.escaped_char()),
+                    None => None
+                }
+            },
+            Some(b'[') => None,
+            Some(_) => Some(self.next_char()),
+            None => None
+        };
+        match last {
+            Some(last) => CharSet::range(c, last),
+            None => {
+                self.pos = saved;
+                CharSet::chars(&[c])
+            }
+        }
+    }
+
+    /// Parses a group, or an item that starts with `(`.
+    fn group(&mut self, flags: &mut Flags) -> NodeKind {
+        // The condition of a conditional group is parsed as a group as well, so the depth
+        // counts every kind of group.
+        if self.depth == MAX_DEPTH {
+            self.pos = self.bytes.len();
+            return NodeKind::Unknown;
+        }
+        self.depth += 1;
+        let kind = self.group_body(flags);
+        self.depth -= 1;
+        kind
+    }
+
+    /// Parses a group, or an item that starts with `(`, at the current depth.
+    fn group_body(&mut self, flags: &mut Flags) -> NodeKind {
+        let rest = self.rest();
+        if rest.starts_with(b"(?#") {
+            self.skip_past(b")");
+            return NodeKind::Empty;
+        }
+        if rest.starts_with(b"(*") || rest.starts_with(b"(?C") {
+            // A backtracking control verb, a start-of-pattern setting or a callout.
+            self.skip_past(b")");
+            return NodeKind::Empty;
+        }
+
+        let mut inner = *flags;
+        let (skip, kind): (usize, Option<bool>) = if !rest.starts_with(b"(?") {
+            (1, None)
+        } else if rest.starts_with(b"(?:") || rest.starts_with(b"(?|") {
+            (3, None)
+        } else if rest.starts_with(b"(?>") {
+            (3, Some(false))
+        } else if rest.starts_with(b"(?=") || rest.starts_with(b"(?!") {
+            (3, Some(true))
+        } else if rest.starts_with(b"(?<=") || rest.starts_with(b"(?<!") {
+            (4, Some(true))
+        } else if rest.starts_with(b"(?<") || rest.starts_with(b"(?P<") || rest.starts_with(b"(?'") {
+            // A named group.
+            let close = if rest[2] == b'\'' { b"'" } else { b">" };
+            self.skip_past(close);
+            (0, None)
+        } else if rest.starts_with(b"(?(") {
+            // A conditional group, whose condition is an assertion or a reference.
+            self.pos += 3;
+            if self.peek() == Some(b'?') {
+                self.pos -= 1;
+                self.group(flags);
+            } else {
+                self.skip_past(b")");
+            }
+            (0, None)
+        } else {
+            let options = rest[2..].iter().take_while(|&&c| c == b'-' || c.is_ascii_alphabetic()).count();
+            match rest.get(2 + options) {
+                Some(&b')') | Some(&b':') => {
+                    // An option setting, such as `(?i)` or `(?x-i:...)`.
+                    let mut on = true;
+                    for &c in &rest[2..(2 + options)] {
+                        match c {
+                            b'-' => on = false,
+                            b'i' => inner.caseless = on,
+                            b's' => inner.dotall = on,
+                            b'x' => inner.extended = on,
+                            b'U' => inner.ungreedy = on,
+                            _ => ()
+                        }
+                    }
+                    if rest[2 + options] == b')' {
+                        *flags = inner;
+                        self.pos += 3 + options;
+                        return NodeKind::Empty;
+                    }
+                    (3 + options, None)
+                },
+                _ => {
+                    // A recursion, a subroutine call or a named backreference, such as
+                    // `(?R)`, `(?1)` or `(?P=name)`.
+                    self.skip_past(b")");
+                    return NodeKind::Unknown;
+                }
+            }
+        };
+        self.pos += skip;
+        let node = self.alternation(inner);
+        if self.peek() == Some(b')') {
+            self.pos += 1;
+        }
+        match kind {
+            None => node.kind,
+            Some(lookaround) => NodeKind::Atomic(Box::new(node), lookaround)
+        }
+    }
+}
+
+/// Returns the set matching the literal character `c`.
+fn literal(c: u32, flags: Flags) -> CharSet {
+    let set = CharSet::chars(&[c]);
+    if flags.caseless {
+        set.caseless()
+    } else {
+        set
+    }
+}
+
+/// Returns the length of the quantifier in braces at the start of `rest`, such as `{2,5}`,
+/// and its minimum and maximum, or `None` if the brace is a literal.
+fn braces(rest: &[u8]) -> Option<(usize, u32, Option<u32>)> {
+    let digits = |from: usize| rest[from..].iter().take_while(|c| c.is_ascii_digit()).count();
+    let parse = |from: usize, len: usize| ::std::str::from_utf8(&rest[from..(from + len)]).unwrap().parse().unwrap_or(u32::MAX);
+    let min_len = digits(1);
+    if min_len == 0 {
+        return None;
+    }
+    let min = parse(1, min_len);
+    let mut len = 1 + min_len;
+    let max = if rest.get(len) == Some(&b',') {
+        let max_len = digits(len + 1);
+        let max = if max_len == 0 { None } else { Some(parse(len + 1, max_len)) };
+        len += 1 + max_len;
+        max
+    } else {
+        Some(min)
+    };
+    if rest.get(len) == Some(&b'}') {
+        Some((len + 1, min, max))
+    } else {
+        None
+    }
+}
+
+/// Reports the constructs within `node` that may backtrack catastrophically.
+fn check(node: &Node, pattern: &str, warnings: &mut Vec<ReDosWarning>) {
+    match node.kind {
+        NodeKind::Concat(ref nodes) | NodeKind::Alternation(ref nodes) => {
+            for node in nodes {
+                check(node, pattern, warnings);
+            }
+        },
+        NodeKind::Atomic(ref body, _) => check(body, pattern, warnings),
+        NodeKind::Repeat(ref body, _, max, mode) => {
+            // libpcre does not backtrack into a possessive repeat once it has matched.
+            if max.is_none() && mode != Mode::Possessive {
+                let found = nested_repeat(body).filter(|inner| body.within(&inner.chars()))
+                    .map(|inner| (ReDosKind::NestedQuantifier, inner.span.clone()))
+                    .or_else(|| overlapping_alternative(body).map(|inner| (ReDosKind::OverlappingAlternatives, inner.span.clone())));
+                if let Some((kind, inner)) = found {
+                    let item = &pattern[node.span.clone()];
+                    warnings.push(ReDosWarning {
+                        kind,
+                        range: node.span.clone(),
+                        inner,
+                        suggestion: if mode == Mode::Greedy { format!("{}+", item) } else { format!("(?>{})", item) }
+                    });
+                    return;
+                }
+            }
+            check(body, pattern, warnings);
+        },
+        _ => ()
+    }
+}
+
+/// Returns the first unbounded repeat within `node` that libpcre can backtrack into.
+fn nested_repeat(node: &Node) -> Option<&Node> {
+    match node.kind {
+        NodeKind::Concat(ref nodes) | NodeKind::Alternation(ref nodes) => nodes.iter().filter_map(nested_repeat).next(),
+        NodeKind::Repeat(ref body, _, max, mode) if mode != Mode::Possessive => {
+            if max.is_none() && !body.nullable() {
+                Some(node)
+            } else {
+                nested_repeat(body)
+            }
+        },
+        _ => None
+    }
+}
+
+/// Returns the first alternative of a group at the top of `node` that can match the same
+/// text as an earlier alternative.
+fn overlapping_alternative(node: &Node) -> Option<&Node> {
+    let alternatives = match node.kind {
+        NodeKind::Alternation(ref alternatives) => alternatives,
+        NodeKind::Concat(ref nodes) if nodes.len() == 1 => return overlapping_alternative(&nodes[0]),
+        _ => return None
+    };
+    for (i, later) in alternatives.iter().enumerate() {
+        for earlier in &alternatives[..i] {
+            if later.nullable() || earlier.nullable() || earlier.first().intersection(&later.first()).is_empty() {
+                continue;
+            }
+            let common = earlier.chars().intersection(&later.chars());
+            if earlier.within(&common) && later.within(&common) {
+                return Some(later);
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,249 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::error::{Error};
+use std::fmt;
+use std::option::{Option};
+use std::result::{Result};
+use std::string::{String};
+
+use {CompilationError, CompileOptions, Pcre, StackGuard, StudyOptions};
+
+/// The error returned by [SafeCompileOptions::compile()](struct.SafeCompileOptions.html#method.compile),
+/// naming the rule that rejected the pattern.
+#[derive(Debug)]
+pub enum SafeCompileError {
+    /// The pattern is longer than
+    /// [max_pattern_len](struct.SafeCompileOptions.html#structfield.max_pattern_len). Holds
+    /// the length of the pattern, in bytes.
+    PatternTooLong(usize),
+
+    /// The compile options include options that are not allowed, such as `AUTO_CALLOUT`.
+    /// Holds the options that are not allowed.
+    ForbiddenOptions(CompileOptions),
+
+    /// The pattern contains a callout at the given byte offset.
+    Callout(usize),
+
+    /// The pattern starts with a setting that overrides UTF or Unicode property support, or
+    /// sets a match limit. Holds the byte offset and the name of the setting, such as `UCP`
+    /// or `LIMIT_MATCH`.
+    StartSetting(usize, String),
+
+    /// The compiled pattern is larger than
+    /// [max_compiled_size](struct.SafeCompileOptions.html#structfield.max_compiled_size).
+    /// Holds the size of the compiled pattern, in bytes.
+    CompiledTooLarge(usize),
+
+    /// The linked libpcre cannot set match limits on the compiled pattern, which requires
+    /// libpcre 8.32 or later.
+    MatchLimitsUnsupported,
+
+    /// libpcre failed to compile the pattern, which includes being stopped by the stack
+    /// guard.
+    Compilation(CompilationError)
+}
+
+impl fmt::Display for SafeCompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SafeCompileError::PatternTooLong(len) => write!(f, "pattern of {} bytes is too long", len),
+            SafeCompileError::ForbiddenOptions(options) => write!(f, "compile options {:?} are not allowed", options),
+            SafeCompileError::Callout(offset) => write!(f, "callout at offset {} is not allowed", offset),
+            SafeCompileError::StartSetting(offset, ref name) => write!(f, "(*{}) at offset {} is not allowed", name, offset),
+            SafeCompileError::CompiledTooLarge(size) => write!(f, "compiled pattern of {} bytes is too large", size),
+            SafeCompileError::MatchLimitsUnsupported => write!(f, "libpcre 8.32 or later is required to set match limits"),
+            SafeCompileError::Compilation(ref err) => err.fmt(f)
+        }
+    }
+}
+
+impl Error for SafeCompileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            SafeCompileError::Compilation(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+/// A profile for compiling regular expressions from untrusted sources.
+///
+/// [compile()](#method.compile) rejects patterns that:
+///
+/// * are longer than [max_pattern_len](#structfield.max_pattern_len)
+/// * contain callouts, or are compiled with the `AUTO_CALLOUT` option
+/// * start with `(*UTF)`, `(*UTF8)` or `(*UCP)`, which change what the pattern matches, or
+///   with `(*LIMIT_MATCH=..)` or another setting that replaces the match limits
+/// * use more than [stack_limit](#structfield.stack_limit) bytes of stack to compile, as
+///   checked by a [StackGuard](struct.StackGuard.html)
+/// * compile to more than [max_compiled_size](#structfield.max_compiled_size) bytes
+///
+/// The compiled regular expression is studied, without JIT compilation, and its match and
+/// recursion limits are set, so that matches that exceed them are abandoned instead of
+/// running for a long time. Use [Pcre::try_exec()](struct.Pcre.html#method.try_exec) to match
+/// it, which reports an abandoned match as an error. Studying it again resets the limits.
+///
+/// libpcre does not offer a way to forbid start-of-pattern settings, as `NEVER_UTF` cannot
+/// be combined with the UTF-8 mode that every pattern is compiled in. They are found by
+/// scanning the pattern instead, as are callouts. The scan is conservative: a callout in a
+/// comment of an `EXTENDED` pattern is rejected as well.
+///
+/// ```
+/// use pcre::{SafeCompileError, SafeCompileOptions};
+///
+/// let safe = SafeCompileOptions::default();
+/// let re = safe.compile("(?<user>\\w+)@example\\.com").unwrap();
+/// assert_eq!(re.exec("me@example.com").unwrap().named_group("user"), Some("me"));
+///
+/// match safe.compile("(*LIMIT_MATCH=1000000000)(a+)+$") {
+///     Err(SafeCompileError::StartSetting(0, ref name)) => assert_eq!(name, "LIMIT_MATCH"),
+///     result => panic!("{:?}", result)
+/// }
+/// ```
+///
+/// Requires libpcre 8.32 or later, which can study a pattern into an extra block that holds
+/// the limits. With earlier versions, [compile()](#method.compile) fails with
+/// [SafeCompileError::MatchLimitsUnsupported](enum.SafeCompileError.html#variant.MatchLimitsUnsupported).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SafeCompileOptions {
+
+    /// The options to compile patterns with. Default: none.
+    pub options: CompileOptions,
+
+    /// The maximum length of a pattern, in bytes. Default: 4096.
+    pub max_pattern_len: usize,
+
+    /// The maximum size of a compiled pattern, in bytes, as returned by
+    /// [Pcre::size()](struct.Pcre.html#method.size). Default: 64 KiB.
+    pub max_compiled_size: usize,
+
+    /// The maximum stack used to compile a pattern, in bytes. Default: 64 KiB.
+    pub stack_limit: usize,
+
+    /// The match limit set for compiled patterns. Default: 100,000, where libpcre defaults to
+    /// 10 million.
+    pub match_limit: u32,
+
+    /// The recursion depth limit set for compiled patterns. Default: 1,000.
+    pub match_limit_recursion: u32
+
+}
+
+impl Default for SafeCompileOptions {
+    fn default() -> SafeCompileOptions {
+        SafeCompileOptions {
+            options: CompileOptions::empty(),
+            max_pattern_len: 4096,
+            max_compiled_size: 64 * 1024,
+            stack_limit: 64 * 1024,
+            match_limit: 100_000,
+            match_limit_recursion: 1_000
+        }
+    }
+}
+
+/// The start-of-pattern settings that are rejected, by prefix of their name.
+const FORBIDDEN_SETTINGS: [&str; 3] = ["UTF", "UCP", "LIMIT_"];
+
+impl SafeCompileOptions {
+    /// Compiles `pattern` if it passes every rule of the profile.
+    pub fn compile(&self, pattern: &str) -> Result<Pcre, SafeCompileError> {
+        if pattern.len() > self.max_pattern_len {
+            return Err(SafeCompileError::PatternTooLong(pattern.len()));
+        }
+        if self.options.contains(CompileOptions::AUTO_CALLOUT) {
+            return Err(SafeCompileError::ForbiddenOptions(CompileOptions::AUTO_CALLOUT));
+        }
+        if let Some((offset, name)) = start_settings(pattern).find(|&(_, name)| FORBIDDEN_SETTINGS.iter().any(|prefix| name.starts_with(prefix))) {
+            let name = name.split('=').next().unwrap();
+            return Err(SafeCompileError::StartSetting(offset, name.to_string()));
+        }
+        if let Some(offset) = find_callout(pattern, self.options) {
+            return Err(SafeCompileError::Callout(offset));
+        }
+
+        let guard = StackGuard::new(self.stack_limit);
+        let mut re = guard.scope(|| Pcre::compile_with_options(pattern, &self.options)).map_err(SafeCompileError::Compilation)?;
+        let size = re.size();
+        if size > self.max_compiled_size {
+            return Err(SafeCompileError::CompiledTooLarge(size));
+        }
+
+        re.study_with_options(&StudyOptions::EXTRA_NEEDED);
+        let extra = match re.extra() {
+            None => return Err(SafeCompileError::MatchLimitsUnsupported),
+            Some(extra) => extra
+        };
+        extra.set_match_limit(self.match_limit);
+        extra.set_match_limit_recursion(self.match_limit_recursion);
+        Ok(re)
+    }
+}
+
+/// Returns the byte offsets and names of the `(*NAME)` items at the start of `pattern`.
+fn start_settings(pattern: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        let rest = &pattern[offset..];
+        if !rest.starts_with("(*") {
+            return None;
+        }
+        let end = rest.find(')')?;
+        let setting = (offset, &rest[2..end]);
+        offset += end + 1;
+        Some(setting)
+    })
+}
+
+/// Returns the byte offset of the first callout in `pattern`, skipping escapes, quoted
+/// text, character classes and comment groups.
+fn find_callout(pattern: &str, options: CompileOptions) -> Option<usize> {
+    let bytes = pattern.as_bytes();
+    let mut i = 0;
+    let mut in_class = false;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if bytes.get(i + 1) == Some(&b'Q') => {
+                i = match pattern[(i + 2)..].find("\\E") {
+                    None => return None,
+                    Some(end) => i + 2 + end + 2
+                };
+                continue;
+            },
+            b'\\' => i += 1,
+            b'[' if in_class && pattern[i..].starts_with("[:") => {
+                // A POSIX class such as [:alpha:].
+                if let Some(end) = pattern[(i + 2)..].find(":]") {
+                    i += 2 + end + 1;
+                }
+            },
+            b'[' if !in_class => {
+                in_class = true;
+                if bytes.get(i + 1) == Some(&b'^') {
+                    i += 1;
+                }
+                // A leading ] is a literal, except for JavaScript, where [] is an empty class.
+                if bytes.get(i + 1) == Some(&b']') && !options.contains(CompileOptions::JAVASCRIPT_COMPAT) {
+                    i += 1;
+                }
+            },
+            b']' if in_class => in_class = false,
+            b'(' if !in_class && pattern[i..].starts_with("(?#") => {
+                i = match pattern[i..].find(')') {
+                    None => return None,
+                    Some(end) => i + end
+                };
+            },
+            b'(' if !in_class && pattern[i..].starts_with("(?C") => return Some(i),
+            _ => ()
+        }
+        i += 1;
+    }
+    None
+}
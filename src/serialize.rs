@@ -0,0 +1,107 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `serde` support, enabled by the `serde` feature.
+
+use serde::de::{Error};
+use serde::ser::{SerializeMap, SerializeStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::string::{String};
+
+use {captures, CompileOptions, Match, Pcre};
+
+#[derive(Serialize)]
+#[serde(rename = "Pcre")]
+struct PcreRef<'a> {
+
+    pattern: &'a str,
+
+    options: CompileOptions
+
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Pcre")]
+struct PcreDef {
+
+    pattern: String,
+
+    #[serde(default)]
+    options: CompileOptions
+
+}
+
+/// Serializes as the pattern and the compile options. Study data is not serialized.
+impl Serialize for Pcre {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PcreRef {
+            pattern: self.pattern(),
+            options: self.compile_options()
+        }.serialize(serializer)
+    }
+}
+
+/// Deserializes the pattern and the compile options, which may be omitted, and compiles
+/// the pattern. A `CompilationError` becomes a custom deserialization error.
+impl<'de> Deserialize<'de> for Pcre {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Pcre, D::Error> {
+        let def = PcreDef::deserialize(deserializer)?;
+        Pcre::compile_with_options(&def.pattern, &def.options).map_err(D::Error::custom)
+    }
+}
+
+/// A capture group of a serialized match.
+#[derive(Serialize)]
+struct Group<'a> {
+
+    start: usize,
+
+    end: usize,
+
+    text: &'a str
+
+}
+
+/// The named capture groups of a serialized match.
+struct NamedGroups<'m, 'a: 'm>(&'m Match<'a>);
+
+impl<'m, 'a> Serialize for NamedGroups<'m, 'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let m = self.0;
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(ref name_table) = m.name_table_ {
+            for (name, numbers) in name_table.iter() {
+                map.serialize_entry(name, &captures::first_set_group(m, numbers))?;
+            }
+        }
+        map.end()
+    }
+}
+
+/// Serializes as an object with the `start` and `end` offsets of the match, the `groups`
+/// array of every capture group including group 0, each either `null` if the group did not
+/// participate in the match or an object with its `start`, `end` and `text`, and the
+/// `named` object mapping the name of each named capture group to its text or `null`.
+impl<'a> Serialize for Match<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let groups: Vec<Option<Group>> = self.iter().enumerate()
+            .map(|(n, group)| group.map(|text| Group {
+                start: self.group_start(n),
+                end: self.group_end(n),
+                text
+            }))
+            .collect();
+
+        let mut state = serializer.serialize_struct("Match", 4)?;
+        state.serialize_field("start", &self.group_start(0))?;
+        state.serialize_field("end", &self.group_end(0))?;
+        state.serialize_field("groups", &groups)?;
+        state.serialize_field("named", &NamedGroups(self))?;
+        state.end()
+    }
+}
@@ -0,0 +1,264 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::error::{Error};
+use std::fmt;
+use std::string::{String};
+use std::vec::{Vec};
+
+use prefilter::{Prefilter};
+use {detail, CompilationError, CompileOptions, ExecOptions, Match, Pcre, StudyOptions};
+
+/// The error returned when one of the patterns of a [PcreSet](struct.PcreSet.html) fails to
+/// compile.
+#[derive(Debug)]
+pub struct SetCompilationError {
+
+    index: usize,
+
+    error: CompilationError
+
+}
+
+/// A set of regular expressions that are matched against the same subject strings.
+///
+/// Each pattern is compiled on its own, and a prefilter built from the first and required
+/// characters that libpcre records for each pattern, and from the literal substring that
+/// each match must contain, skips patterns that cannot match a subject without calling into
+/// libpcre.
+///
+/// When the patterns allow it, the set also compiles a single alternation of all of the
+/// patterns, each tagged with `(*MARK:n)`, so that [exec()](#method.exec) finds the
+/// leftmost match of any pattern with one call to libpcre. Patterns that refer to capture
+/// groups by number or use backtracking control verbs such as `(*COMMIT)` cannot be
+/// combined, in which case [exec()](#method.exec) matches each pattern in turn instead.
+#[derive(Debug)]
+pub struct PcreSet {
+
+    pcres: Vec<Pcre>,
+
+    prefilters: Vec<Prefilter>,
+
+    /// The tagged alternation of all of the patterns.
+    combined: Option<Pcre>
+
+}
+
+impl SetCompilationError {
+    /// Returns the index of the pattern that failed to compile.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the compilation error.
+    pub fn error(&self) -> &CompilationError {
+        &self.error
+    }
+}
+
+impl fmt::Display for SetCompilationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "pattern {}: {}", self.index, self.error)
+    }
+}
+
+impl Error for SetCompilationError {}
+
+impl PcreSet {
+    /// Compiles the given regular expressions.
+    ///
+    /// # Argument
+    /// * `patterns` - The regular expressions.
+    pub fn new<I, S>(patterns: I) -> Result<PcreSet, SetCompilationError>
+        where I: IntoIterator<Item = S>, S: AsRef<str> {
+        PcreSet::with_options(patterns, &CompileOptions::empty())
+    }
+
+    /// Compiles the given regular expressions using the given compilation options `options`.
+    ///
+    /// # Arguments
+    /// * `patterns` - The regular expressions.
+    /// * `options` - Compilation options applied to every pattern. See
+    ///   [Pcre::compile_with_options()](struct.Pcre.html#method.compile_with_options).
+    pub fn with_options<I, S>(patterns: I, options: &CompileOptions) -> Result<PcreSet, SetCompilationError>
+        where I: IntoIterator<Item = S>, S: AsRef<str> {
+        let mut pcres = Vec::new();
+        for (index, pattern) in patterns.into_iter().enumerate() {
+            match Pcre::compile_with_options(pattern.as_ref(), options) {
+                Err(error) => return Err(SetCompilationError {
+                    index,
+                    error
+                }),
+                Ok(re) => pcres.push(re)
+            }
+        }
+
+        let prefilters = pcres.iter().map(Prefilter::new).collect();
+        let combined = combine(&pcres, options);
+        Ok(PcreSet {
+            pcres,
+            prefilters,
+            combined
+        })
+    }
+
+    /// Returns the number of patterns in the set.
+    pub fn len(&self) -> usize {
+        self.pcres.len()
+    }
+
+    /// Returns `true` if the set contains no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.pcres.is_empty()
+    }
+
+    /// Returns the compiled regular expression for the pattern at index `index`.
+    pub fn get(&self, index: usize) -> Option<&Pcre> {
+        self.pcres.get(index)
+    }
+
+    /// Returns `true` if the combined alternation of all of the patterns is used by
+    /// [exec()](#method.exec).
+    pub fn is_combined(&self) -> bool {
+        self.combined.is_some()
+    }
+
+    /// Returns `true` if any pattern in the set matches `subject`.
+    pub fn is_match(&self, subject: &str) -> bool {
+        self.candidates(subject).any(|index| self.pcres[index].exec(subject).is_some())
+    }
+
+    /// Returns the indices of all of the patterns that match `subject`, in increasing order.
+    pub fn matches(&self, subject: &str) -> Vec<usize> {
+        self.candidates(subject).filter(|&index| self.pcres[index].exec(subject).is_some()).collect()
+    }
+
+    /// Returns the index and first match of each pattern that matches `subject`, in
+    /// increasing order of index.
+    pub fn exec_all<'a>(&self, subject: &'a str) -> Vec<(usize, Match<'a>)> {
+        self.candidates(subject)
+            .filter_map(|index| self.pcres[index].exec(subject).map(|m| (index, m)))
+            .collect()
+    }
+
+    /// Finds the leftmost match of any pattern in the set within `subject`. If more than one
+    /// pattern matches at the leftmost position, the pattern with the lowest index wins, as
+    /// it would in an alternation of the patterns.
+    ///
+    /// # Return value
+    /// The index of the winning pattern and its match, whose capture groups are numbered as
+    /// in that pattern.
+    pub fn exec<'a>(&self, subject: &'a str) -> Option<(usize, Match<'a>)> {
+        // No need to run the combined alternation if no pattern can match.
+        self.candidates(subject).next()?;
+
+        if let Some(ref combined) = self.combined {
+            let found = combined.exec_with_mark(subject, 0, &ExecOptions::empty(), 0).unwrap_or_else(|err| panic!("pcre_exec: {}", err))
+                .map(|(m, mark)| {
+                    let index = mark.and_then(|mark| ::std::str::from_utf8(mark).ok())
+                        .and_then(|mark| mark.parse::<usize>().ok());
+                    (m.group_start(0), index)
+                });
+            // Without a tagged match, fall back to matching each pattern in turn.
+            if let Some((start, Some(index))) = found {
+                // Repeating the winning alternative on its own at the same position yields
+                // the same match, with the capture groups numbered as in its pattern.
+                let m = self.pcres[index].exec_from_with_options(subject, start, &ExecOptions::ANCHORED);
+                if let Some(m) = m {
+                    return Some((index, m));
+                }
+            }
+        }
+
+        let mut best: Option<(usize, Match<'a>)> = None;
+        for index in self.candidates(subject) {
+            if let Some(m) = self.pcres[index].exec(subject) {
+                let is_better = match best {
+                    None => true,
+                    Some((_, ref best_m)) => m.group_start(0) < best_m.group_start(0)
+                };
+                if is_better {
+                    best = Some((index, m));
+                }
+            }
+        }
+        best
+    }
+
+    /// Returns the indices of the patterns that the prefilters do not rule out for `subject`.
+    fn candidates<'s>(&'s self, subject: &'s str) -> impl Iterator<Item = usize> + 's {
+        self.prefilters.iter().enumerate()
+            .filter(move |&(_, prefilter)| prefilter.may_match(subject.as_bytes()))
+            .map(|(index, _)| index)
+    }
+}
+
+/// Returns `true` if `pattern` behaves the same within an alternation of patterns as it does
+/// on its own.
+fn is_combinable(pattern: &str, re: &Pcre) -> bool {
+    // Back references, recursion, subroutine calls and conditions may refer to groups by
+    // number or by a name that another pattern also uses.
+    if re.fullinfo_int(detail::PCRE_INFO_BACKREFMAX) > 0 {
+        return false;
+    }
+    if pattern.contains("\\g") || pattern.contains("(?&") || pattern.contains("(?P>") {
+        return false;
+    }
+    // Backtracking control verbs cut off the alternatives of the other patterns, and
+    // (*ACCEPT) skips the mark that identifies the pattern.
+    if ["(*COMMIT", "(*PRUNE", "(*SKIP", "(*THEN", "(*ACCEPT"].iter().any(|verb| pattern.contains(verb)) {
+        return false;
+    }
+    let bytes = pattern.as_bytes();
+    for i in 0..bytes.len() {
+        if bytes[i..].starts_with(b"(?") {
+            let mut next = &bytes[(i + 2)..];
+            if next.starts_with(b"(") {
+                next = &next[1..];
+            }
+            match next.first() {
+                Some(&b'R') | Some(&b'+') | Some(&(b'0'..=b'9')) => return false,
+                Some(&b'-') if next.get(1).is_some_and(u8::is_ascii_digit) => return false,
+                _ => ()
+            }
+        }
+    }
+    true
+}
+
+/// Compiles the alternation of all of the patterns, each tagged with its index, or returns
+/// `None` if the patterns cannot be combined.
+fn combine(pcres: &[Pcre], options: &CompileOptions) -> Option<Pcre> {
+    if pcres.len() < 2 || !pcres.iter().all(|re| is_combinable(re.pattern(), re)) {
+        return None;
+    }
+
+    // The mark follows each pattern so that it overrides any mark set by the pattern itself.
+    let mut pattern = String::new();
+    for (index, re) in pcres.iter().enumerate() {
+        if index > 0 {
+            pattern.push('|');
+        }
+        pattern.push_str(&format!("(?:{})(*MARK:{})", re.pattern(), index));
+    }
+
+    // Patterns that leave a comment or a quoted sequence open swallow what follows them,
+    // which leaves the alternation unbalanced.
+    let mut combined = match Pcre::compile_with_options(&pattern, &(*options | CompileOptions::DUPNAMES)) {
+        Err(_) => return None,
+        Ok(combined) => combined
+    };
+    let capture_count: usize = pcres.iter().map(|re| re.capture_count()).sum();
+    if combined.capture_count() != capture_count {
+        return None;
+    }
+    if !combined.study_with_options(&StudyOptions::EXTRA_NEEDED) || !combined.enable_mark() {
+        return None;
+    }
+    Some(combined)
+}
@@ -0,0 +1,129 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::{Cell};
+use std::option::{Option};
+
+/// A limit on the stack that libpcre may use to compile a regular expression.
+///
+/// libpcre compiles nested parentheses recursively, so a pattern such as `((((..))))` with
+/// enough levels of nesting overflows the stack and crashes the process, unless libpcre was
+/// built with a low enough parentheses nesting limit. To prevent this, every compilation is
+/// checked by a stack guard, which stops it with a
+/// [CompilationErrorKind::StackGuard](enum.CompilationErrorKind.html#variant.StackGuard)
+/// error once it has used more than the limit in effect: the limit of the innermost
+/// [scope()](#method.scope) running on the thread, or
+/// [DEFAULT_LIMIT](#associatedconstant.DEFAULT_LIMIT) outside of any scope.
+///
+/// ```
+/// use pcre::{CompilationErrorKind, Pcre, StackGuard};
+///
+/// let nested = format!("{}a{}", "(".repeat(100), ")".repeat(100));
+/// assert!(Pcre::compile(&nested).is_ok());
+///
+/// let guard = StackGuard::new(1024);
+/// match guard.scope(|| Pcre::compile(&nested)) {
+///     Err(ref err) if err.kind() == CompilationErrorKind::StackGuard => (),
+///     result => assert!(!pcre::version().supports_stack_guard(), "{:?}", result)
+/// }
+/// ```
+///
+/// libpcre uses several kilobytes of stack to compile any pattern, and several hundred more
+/// bytes for each level of nesting. Threads spawned with a stack smaller than the default
+/// limit, plus the stack already in use when compiling, should compile patterns within a
+/// scope of a lower limit.
+///
+/// The stack guard requires libpcre 8.35 or later and is not called for
+/// [Pcre16](struct.Pcre16.html) and [Pcre32](struct.Pcre32.html). With libpcre2, it limits
+/// the stack used by the parsing of a pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StackGuard {
+
+    limit: usize
+
+}
+
+thread_local! {
+    /// The limit of the innermost scope running on this thread.
+    static LIMIT: Cell<Option<usize>> = const { Cell::new(None) };
+
+    /// The stack address at which the compilation running on this thread started, and the
+    /// limit in effect for it.
+    static COMPILATION: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/// Restores the enclosing limit when a scope ends, even by panicking.
+struct ScopeGuard {
+
+    previous: Option<usize>
+
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        LIMIT.with(|limit| limit.set(self.previous));
+    }
+}
+
+impl StackGuard {
+    /// The stack limit, in bytes, outside of any scope. This leaves room within the 2 MiB
+    /// stack of threads spawned by the standard library.
+    pub const DEFAULT_LIMIT: usize = 1024 * 1024;
+
+    /// Creates a stack guard that stops compilations using more than `limit` bytes of stack.
+    pub fn new(limit: usize) -> StackGuard {
+        StackGuard {
+            limit
+        }
+    }
+
+    /// Returns the stack limit, in bytes.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Runs `f`, limiting the stack used by the compilations that it runs on this thread.
+    pub fn scope<F, R>(&self, f: F) -> R
+        where F: FnOnce() -> R
+    {
+        let _guard = ScopeGuard {
+            previous: LIMIT.with(|limit| limit.replace(Some(self.limit)))
+        };
+        f()
+    }
+}
+
+/// Returns the current position of the stack.
+#[inline(never)]
+fn stack_position() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+/// Runs the compilation `f`, measuring the stack used by [exceeded()](fn.exceeded.html)
+/// from here.
+pub fn guard<F, R>(f: F) -> R
+    where F: FnOnce() -> R
+{
+    let limit = LIMIT.with(|limit| limit.get()).unwrap_or(StackGuard::DEFAULT_LIMIT);
+    let previous = COMPILATION.with(|compilation| compilation.replace(Some((stack_position(), limit))));
+    let result = f();
+    COMPILATION.with(|compilation| compilation.set(previous));
+    result
+}
+
+/// Returns `true` if the compilation running on this thread has used more stack than its
+/// limit.
+pub fn exceeded() -> bool {
+    let position = stack_position();
+    // The thread-local is gone if libpcre compiles while the thread is exiting.
+    COMPILATION.try_with(|compilation| match compilation.get() {
+        None => false,
+        Some((start, limit)) => start.abs_diff(position) > limit
+    }).unwrap_or(false)
+}
@@ -0,0 +1,207 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::error::{Error};
+use std::fmt;
+use std::str::{FromStr};
+use std::string::{String};
+use std::vec::{Vec};
+
+use {Match, Pcre};
+
+/// The error returned when parsing or validating a [Template](struct.Template.html) fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    /// The `${` at the given byte offset is not closed by `}`.
+    Unterminated(usize),
+
+    /// The reference at the given byte offset is neither a group number nor a group name,
+    /// or is a `$name` without braces.
+    InvalidReference(usize),
+
+    /// The regular expression has no capture group with the given number.
+    NoSuchGroup(usize),
+
+    /// The regular expression has no capture group with the given name.
+    NoSuchName(String)
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TemplateError::Unterminated(offset) => write!(f, "missing closing brace for reference at offset {}", offset),
+            TemplateError::InvalidReference(offset) => write!(f, "invalid reference at offset {}", offset),
+            TemplateError::NoSuchGroup(n) => write!(f, "no capture group number {}", n),
+            TemplateError::NoSuchName(ref name) => write!(f, "no capture group named `{}`", name)
+        }
+    }
+}
+
+impl Error for TemplateError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Group(usize),
+    Named(String),
+    Prematch,
+    Postmatch
+}
+
+/// A parsed replacement template, which renders a match as a string.
+///
+/// Templates may contain the following references, and any other text is copied as is:
+///
+/// * `$n` or `${n}` - the substring captured by group number `n`; `$0` and `$&` are the
+///   whole match
+/// * `${name}` - the substring captured by the named group `name`
+/// * `` $` `` and `$'` - the parts of the subject before and after the match
+/// * `$$` - a literal `$`
+///
+/// The digits of `$n` are read greedily, so use `${1}0` for group 1 followed by a zero. Named
+/// groups must be written with braces: a `$` followed by a letter or `_`, as in `$name`, is
+/// an [InvalidReference](enum.TemplateError.html#variant.InvalidReference) error rather than
+/// being copied, so that it is not mistaken for a reference. Any other `$` that does not
+/// start a reference, such as the one in `5 $`, is copied as is. Groups that did not
+/// participate in the match expand to nothing.
+///
+/// ```
+/// use pcre::{Pcre, Template};
+///
+/// let re = Pcre::compile("(?<user>\\w+)@(\\w+)").unwrap();
+/// let template: Template = "https://$2/~${user}".parse().unwrap();
+/// template.validate(&re).unwrap();
+///
+/// let mut url = String::new();
+/// template.expand(&re.exec("mail alice@example now").unwrap(), &mut url);
+/// assert_eq!(url, "https://example/~alice");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+
+    parts: Vec<Part>
+
+}
+
+impl Template {
+    /// Parses `template`.
+    pub fn parse(template: &str) -> Result<Template, TemplateError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+        while let Some(dollar) = rest.find('$') {
+            literal.push_str(&rest[..dollar]);
+            let offset = template.len() - rest.len() + dollar;
+            let after = &rest[(dollar + 1)..];
+
+            let (part, len) = match after.as_bytes().first() {
+                Some(&b'$') => {
+                    literal.push('$');
+                    rest = &after[1..];
+                    continue;
+                },
+                Some(&b'&') => (Part::Group(0), 1),
+                Some(&b'`') => (Part::Prematch, 1),
+                Some(&b'\'') => (Part::Postmatch, 1),
+                Some(c) if c.is_ascii_digit() => {
+                    let digits = after.bytes().take_while(u8::is_ascii_digit).count();
+                    (Part::Group(parse_group_number(&after[..digits], offset)?), digits)
+                },
+                Some(&b'{') => {
+                    let close = match after.find('}') {
+                        None => return Err(TemplateError::Unterminated(offset)),
+                        Some(close) => close
+                    };
+                    let reference = &after[1..close];
+                    let part = if !reference.is_empty() && reference.bytes().all(|c| c.is_ascii_digit()) {
+                        Part::Group(parse_group_number(reference, offset)?)
+                    } else if is_group_name(reference) {
+                        Part::Named(reference.to_string())
+                    } else {
+                        return Err(TemplateError::InvalidReference(offset));
+                    };
+                    (part, close + 1)
+                },
+                Some(c) if c.is_ascii_alphabetic() || *c == b'_' => return Err(TemplateError::InvalidReference(offset)),
+                _ => {
+                    literal.push('$');
+                    rest = after;
+                    continue;
+                }
+            };
+
+            if !literal.is_empty() {
+                parts.push(Part::Literal(literal));
+                literal = String::new();
+            }
+            parts.push(part);
+            rest = &after[len..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Template { parts })
+    }
+
+    /// Checks that every group that the template refers to exists in the regular expression
+    /// `re`.
+    pub fn validate(&self, re: &Pcre) -> Result<(), TemplateError> {
+        let name_table = re.name_table();
+        for part in self.parts.iter() {
+            match *part {
+                Part::Group(n) if n > re.capture_count() => return Err(TemplateError::NoSuchGroup(n)),
+                Part::Named(ref name) if !name_table.contains_key(name) => return Err(TemplateError::NoSuchName(name.clone())),
+                _ => ()
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends the template, rendered using the match `m`, to `dst`.
+    pub fn expand(&self, m: &Match, dst: &mut String) {
+        for part in self.parts.iter() {
+            match *part {
+                Part::Literal(ref literal) => dst.push_str(literal),
+                Part::Group(n) => {
+                    if let Some(group) = m.get(n) {
+                        dst.push_str(group);
+                    }
+                },
+                Part::Named(ref name) => {
+                    if let Some(group) = m.named_group(name) {
+                        dst.push_str(group);
+                    }
+                },
+                Part::Prematch => dst.push_str(m.pre_match()),
+                Part::Postmatch => dst.push_str(m.post_match())
+            }
+        }
+    }
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Template, TemplateError> {
+        Template::parse(s)
+    }
+}
+
+fn parse_group_number(digits: &str, offset: usize) -> Result<usize, TemplateError> {
+    digits.parse().map_err(|_| TemplateError::InvalidReference(offset))
+}
+
+/// Returns `true` if `name` is a valid capture group name.
+fn is_group_name(name: &str) -> bool {
+    match name.as_bytes().first() {
+        Some(c) if !c.is_ascii_digit() => name.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_'),
+        _ => false
+    }
+}
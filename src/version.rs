@@ -0,0 +1,124 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::option::{Option};
+use std::string::{String};
+
+use {detail};
+
+/// The version of a libpcre release.
+///
+/// Versions compare by major version, then minor version, then release date.
+///
+/// ```
+/// use pcre::Version;
+///
+/// let version = Version::parse("8.39 2016-06-14").unwrap();
+/// assert_eq!((version.major, version.minor), (8, 39));
+/// assert_eq!(version.date, "2016-06-14");
+/// assert!(version > Version::parse("8.03 2010-01-15").unwrap());
+/// assert!(version.at_least(8, 20) && !version.at_least(10, 0));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+
+    /// The major version, 8 for libpcre and 10 for libpcre2.
+    pub major: u32,
+
+    /// The minor version.
+    pub minor: u32,
+
+    /// The release date, in YYYY-MM-DD form.
+    pub date: String
+
+}
+
+impl Version {
+    /// Parses a version string as returned by [pcre_version()](fn.pcre_version.html), such as
+    /// "8.39 2016-06-14".
+    ///
+    /// A suffix to the minor version, such as "-RC1", is ignored.
+    ///
+    /// # Return value
+    /// `None` if `version` does not start with a major and minor version.
+    pub fn parse(version: &str) -> Option<Version> {
+        let mut parts = version.trim().splitn(2, ' ');
+        let number = parts.next().unwrap_or("");
+        let date = parts.next().unwrap_or("").trim();
+
+        let mut numbers = number.splitn(2, '.');
+        let major = numbers.next().and_then(|major| major.parse().ok())?;
+        let minor: String = numbers.next()?.chars().take_while(char::is_ascii_digit).collect();
+        let minor = minor.parse().ok()?;
+
+        Some(Version {
+            major,
+            minor,
+            date: date.to_string()
+        })
+    }
+
+    /// Returns `true` if this version is `major`.`minor` or later.
+    pub fn at_least(&self, major: u32, minor: u32) -> bool {
+        (self.major, self.minor) >= (major, minor)
+    }
+
+    /// Returns `true` if this version reports mark names, as enabled by
+    /// [Pcre::enable_mark()](struct.Pcre.html#method.enable_mark). Marks were added in
+    /// libpcre 8.03.
+    pub fn supports_mark(&self) -> bool {
+        self.at_least(8, 3)
+    }
+
+    /// Returns `true` if this version supports the `NOTEMPTY_ATSTART` exec option, which was
+    /// added in libpcre 8.00.
+    pub fn supports_notempty_atstart(&self) -> bool {
+        self.at_least(8, 0)
+    }
+
+    /// Returns `true` if this version can JIT compile patterns when studying them, which was
+    /// added in libpcre 8.20. Whether the linked libpcre was built with the JIT compiler is
+    /// reported by [config()](fn.config.html).
+    pub fn supports_jit(&self) -> bool {
+        self.at_least(8, 20)
+    }
+
+    /// Returns `true` if this version reports the parentheses nesting limit, which was added
+    /// in libpcre 8.36.
+    pub fn supports_parens_limit(&self) -> bool {
+        self.at_least(8, 36)
+    }
+
+    /// Returns `true` if this version can call a stack guard while compiling patterns, which
+    /// was added in libpcre 8.35.
+    pub fn supports_stack_guard(&self) -> bool {
+        self.at_least(8, 35)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.date.is_empty() {
+            write!(f, "{}.{:02}", self.major, self.minor)
+        } else {
+            write!(f, "{}.{:02} {}", self.major, self.minor, self.date)
+        }
+    }
+}
+
+/// Returns the version of the linked libpcre.
+///
+/// ```
+/// let version = pcre::version();
+/// assert!(version.supports_jit());
+/// assert_eq!(version.to_string(), pcre::pcre_version());
+/// ```
+pub fn version() -> Version {
+    Version::parse(&detail::pcre_version()).expect("libpcre returned an unrecognized version")
+}
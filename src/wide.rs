@@ -0,0 +1,651 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Regular expressions over UTF-16 and UTF-32 subjects, backed by the 16-bit and 32-bit
+//! libpcre libraries and enabled by the `pcre16` and `pcre32` features.
+
+use libc::{c_char, c_int, c_void};
+use libpcre_sys;
+use std::collections::{BTreeMap};
+use std::ffi::{CStr};
+use std::ops::{Index, Range};
+use std::ptr;
+use std::string::{String};
+use std::sync::{Arc};
+use std::vec::{Vec};
+
+use {escape, CompilationError, CompilationErrorKind, CompileOptions, ExecError, ExecOptions, StudyOptions};
+
+/// Returns the message of a compilation or study error from libpcre.
+unsafe fn error_message(err: *const c_char) -> Option<String> {
+    String::from_utf8(Vec::from(CStr::from_ptr(err).to_bytes())).ok()
+}
+
+/// Returns the byte offset within `pattern` of the code unit offset `offset` within the
+/// encoding of `pattern` as code units of `unit_len` bytes.
+fn byte_offset(pattern: &str, offset: usize, unit_len: fn(char) -> usize) -> usize {
+    let mut units = 0;
+    for (byte, c) in pattern.char_indices() {
+        if units >= offset {
+            return byte;
+        }
+        units += unit_len(c);
+    }
+    pattern.len()
+}
+
+macro_rules! wide_pcre {
+    (
+        $(#[$pcre_attr:meta])*
+        pub struct $Pcre:ident;
+        $(#[$match_attr:meta])*
+        pub struct $Match:ident;
+        $(#[$iter_attr:meta])*
+        pub struct $MatchIterator:ident;
+
+        unit: $unit:ty,
+        raw: $raw:ident,
+        extra: $extra:ident,
+        utf: $utf:ident,
+        no_utf_check: $no_utf_check:ident,
+        bad_utf: $($bad_utf:ident)|+,
+        compile: $compile:ident,
+        exec: $exec:ident,
+        free: $free:ident,
+        free_study: $free_study:ident,
+        fullinfo: $fullinfo:ident,
+        study: $study:ident,
+        encode: $encode:expr,
+        unit_len: $unit_len:expr,
+        decode_name: $decode_name:expr,
+        char_len: $char_len:expr
+    ) => {
+        $(#[$pcre_attr])*
+        #[derive(Debug)]
+        pub struct $Pcre {
+
+            code: *const libpcre_sys::$raw,
+
+            extra: *mut libpcre_sys::$extra,
+
+            capture_count_: c_int,
+
+            pattern_: String,
+
+            options_: CompileOptions,
+
+            /// The name table, shared with matches, if there are named capture groups.
+            name_table_: Option<Arc<BTreeMap<String, Vec<usize>>>>
+
+        }
+
+        // The compiled code and the extra block are only modified through `&mut self`, and
+        // the mark field, the only state that matching could write to, is never enabled.
+        unsafe impl Send for $Pcre {}
+        unsafe impl Sync for $Pcre {}
+
+        $(#[$match_attr])*
+        #[derive(Clone, Debug)]
+        pub struct $Match<'a> {
+
+            subject: &'a [$unit],
+
+            partial_ovector: Vec<c_int>,
+
+            string_count_: c_int,
+
+            /// The name table of the regular expression, if it has named capture groups.
+            name_table_: Option<Arc<BTreeMap<String, Vec<usize>>>>
+
+        }
+
+        $(#[$iter_attr])*
+        pub struct $MatchIterator<'a, 'p> {
+
+            re: &'p $Pcre,
+
+            subject: &'a [$unit],
+
+            offset: c_int,
+
+            options: ExecOptions,
+
+            ovector: Vec<c_int>
+
+        }
+
+        impl $Pcre {
+            /// Compiles the given regular expression.
+            ///
+            /// # Argument
+            /// * `pattern` - The regular expression.
+            pub fn compile(pattern: &str) -> Result<$Pcre, CompilationError> {
+                $Pcre::compile_with_options(pattern, &CompileOptions::empty())
+            }
+
+            /// Compiles a regular expression using the given compilation options `options`.
+            ///
+            /// # Arguments
+            /// * `pattern` - The regular expression.
+            /// * `options` - Compilation options, combined with `|`.
+            ///
+            /// NUL characters in `pattern` match a literal NUL, and the offset of a
+            /// compilation error is a byte offset within `pattern` as given.
+            pub fn compile_with_options(pattern: &str, options: &CompileOptions) -> Result<$Pcre, CompilationError> {
+                let (escaped_pattern, nul_escapes) = escape::escape_nul(pattern);
+                let mut units: Vec<$unit> = $encode(&escaped_pattern);
+                units.push(0);
+                let converted_options = options.bits() | libpcre_sys::$utf | libpcre_sys::$no_utf_check;
+                unsafe {
+                    let mut errorcode: c_int = 0;
+                    let mut err: *const c_char = ptr::null();
+                    let mut erroffset: c_int = 0;
+                    let code = libpcre_sys::$compile(units.as_ptr(), converted_options, &mut errorcode, &mut err, &mut erroffset, ptr::null());
+                    if code.is_null() {
+                        let offset = byte_offset(&escaped_pattern, erroffset as usize, $unit_len);
+                        return Err(CompilationError {
+                            opt_err: error_message(err),
+                            erroffset: nul_escapes.original_offset(offset) as c_int,
+                            kind_: CompilationErrorKind::from_libpcre(errorcode)
+                        });
+                    }
+
+                    let mut re = $Pcre {
+                        code,
+                        extra: ptr::null_mut(),
+                        capture_count_: 0,
+                        pattern_: pattern.to_string(),
+                        options_: *options,
+                        name_table_: None
+                    };
+                    let mut capture_count: c_int = 0;
+                    re.fullinfo(libpcre_sys::PCRE_INFO_CAPTURECOUNT, &mut capture_count as *mut c_int as *mut c_void);
+                    re.capture_count_ = capture_count;
+                    if re.name_count() > 0 {
+                        re.name_table_ = Some(Arc::new(re.name_table()));
+                    }
+                    Ok(re)
+                }
+            }
+
+            /// Returns the pattern that the regular expression was compiled from.
+            pub fn pattern(&self) -> &str {
+                &self.pattern_
+            }
+
+            /// Returns the options that the regular expression was compiled with.
+            pub fn compile_options(&self) -> CompileOptions {
+                self.options_
+            }
+
+            /// Returns the number of capture groups in the regular expression, not counting
+            /// group 0, the whole match.
+            pub fn capture_count(&self) -> usize {
+                self.capture_count_ as usize
+            }
+
+            /// Returns the number of named capture groups in the regular expression.
+            pub fn name_count(&self) -> usize {
+                let mut name_count: c_int = 0;
+                unsafe {
+                    self.fullinfo(libpcre_sys::PCRE_INFO_NAMECOUNT, &mut name_count as *mut c_int as *mut c_void);
+                }
+                name_count as usize
+            }
+
+            /// Creates a name-to-number translation table that maps the name of each named
+            /// capture group to the assigned group numbers.
+            pub fn name_table(&self) -> BTreeMap<String, Vec<usize>> {
+                let mut name_table: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+                unsafe {
+                    let mut tabptr: *const $unit = ptr::null();
+                    self.fullinfo(libpcre_sys::PCRE_INFO_NAMETABLE, &mut tabptr as *mut *const $unit as *mut c_void);
+                    let mut name_entry_size: c_int = 0;
+                    self.fullinfo(libpcre_sys::PCRE_INFO_NAMEENTRYSIZE, &mut name_entry_size as *mut c_int as *mut c_void);
+
+                    for _ in 0..self.name_count() {
+                        // Each entry is the group number in one code unit, followed by the
+                        // NUL-terminated name.
+                        let entry = ::std::slice::from_raw_parts(tabptr, name_entry_size as usize);
+                        let name_len = entry[1..].iter().position(|&unit| unit == 0).unwrap_or(entry.len() - 1);
+                        let name: String = $decode_name(&entry[1..(1 + name_len)]);
+                        name_table.entry(name).or_default().push(entry[0] as usize);
+                        tabptr = tabptr.offset(name_entry_size as isize);
+                    }
+                }
+                name_table
+            }
+
+            /// Studies the regular expression to see if additional information can be
+            /// extracted which might speed up matching.
+            ///
+            /// # Return value
+            /// `true` if additional information could be extracted. `false` otherwise.
+            pub fn study(&mut self) -> bool {
+                self.study_with_options(&StudyOptions::empty())
+            }
+
+            /// Studies the regular expression using the given study options `options`.
+            ///
+            /// # Return value
+            /// `true` if additional information could be extracted or the
+            /// [`StudyOptions::EXTRA_NEEDED`](struct.StudyOptions.html#associatedconstant.EXTRA_NEEDED)
+            /// option was passed. `false` otherwise.
+            pub fn study_with_options(&mut self, options: &StudyOptions) -> bool {
+                unsafe {
+                    libpcre_sys::$free_study(self.extra);
+                    self.extra = ptr::null_mut();
+
+                    let mut err: *const c_char = ptr::null();
+                    let extra = libpcre_sys::$study(self.code, options.bits(), &mut err);
+                    if !err.is_null() {
+                        match error_message(err) {
+                            None => panic!("pcre_study() failed"),
+                            Some(err_str) => panic!("pcre_study() failed: {}", err_str)
+                        }
+                    }
+                    self.extra = extra;
+                    !extra.is_null()
+                }
+            }
+
+            /// Returns the extra block, if one has been created.
+            pub fn extra(&mut self) -> Option<&mut libpcre_sys::$extra> {
+                unsafe {
+                    if self.extra.is_null() {
+                        None
+                    } else {
+                        Some(&mut *(self.extra))
+                    }
+                }
+            }
+
+            /// Matches the compiled regular expression against a given subject `subject`.
+            #[inline]
+            pub fn exec<'a>(&self, subject: &'a [$unit]) -> Option<$Match<'a>> {
+                self.exec_from(subject, 0)
+            }
+
+            /// Matches the compiled regular expression against a given subject `subject`
+            /// starting at the code unit offset `startoffset`.
+            #[inline]
+            pub fn exec_from<'a>(&self, subject: &'a [$unit], startoffset: usize) -> Option<$Match<'a>> {
+                self.exec_from_with_options(subject, startoffset, &ExecOptions::empty())
+            }
+
+            /// Matches the compiled regular expression against a given subject `subject`
+            /// starting at the code unit offset `startoffset` and using the given matching
+            /// options `options`.
+            ///
+            /// The subject is checked to be valid before matching. An invalid subject, or
+            /// a `startoffset` that is not at the start of a character, does not match.
+            ///
+            /// # Panics
+            /// If the match exceeds the match or recursion limit. See [try_exec()](#method.try_exec).
+            pub fn exec_from_with_options<'a>(&self, subject: &'a [$unit], startoffset: usize, options: &ExecOptions) -> Option<$Match<'a>> {
+                self.exec_from_with_groups(subject, startoffset, options, self.capture_count())
+            }
+
+            /// Like [exec_from_with_options()](#method.exec_from_with_options), but only
+            /// records the first `groups` capture groups. The length of the match is
+            /// `groups + 1`, and later groups are reported as unset.
+            ///
+            /// # Panics
+            /// If the match exceeds the match or recursion limit. See [try_exec()](#method.try_exec).
+            pub fn exec_from_with_groups<'a>(&self, subject: &'a [$unit], startoffset: usize, options: &ExecOptions, groups: usize) -> Option<$Match<'a>> {
+                self.try_exec_from_with_groups(subject, startoffset, options, groups).unwrap_or_else(|err| panic!("pcre_exec: {}", err))
+            }
+
+            /// Like [exec()](#method.exec), but returns an error instead of panicking if
+            /// libpcre abandons the match because it exceeded the match or recursion limit.
+            #[inline]
+            pub fn try_exec<'a>(&self, subject: &'a [$unit]) -> Result<Option<$Match<'a>>, ExecError> {
+                self.try_exec_from_with_options(subject, 0, &ExecOptions::empty())
+            }
+
+            /// Like [exec_from_with_options()](#method.exec_from_with_options), but returns an
+            /// error instead of panicking if libpcre abandons the match because it exceeded
+            /// the match or recursion limit.
+            pub fn try_exec_from_with_options<'a>(&self, subject: &'a [$unit], startoffset: usize, options: &ExecOptions) -> Result<Option<$Match<'a>>, ExecError> {
+                self.try_exec_from_with_groups(subject, startoffset, options, self.capture_count())
+            }
+
+            /// Like [exec_from_with_groups()](#method.exec_from_with_groups), but returns an
+            /// error instead of panicking if libpcre abandons the match because it exceeded
+            /// the match or recursion limit.
+            pub fn try_exec_from_with_groups<'a>(&self, subject: &'a [$unit], startoffset: usize, options: &ExecOptions, groups: usize) -> Result<Option<$Match<'a>>, ExecError> {
+                if startoffset > subject.len() {
+                    return Ok(None);
+                }
+                let mut ovector = vec![-1 as c_int; self.ovector_len(groups)];
+                let rc = self.exec_ovector(subject, startoffset as c_int, options.bits(), &mut ovector);
+                if rc >= 0 {
+                    Ok(Some($Match::new(subject, &ovector, rc, self.name_table_.clone())))
+                } else {
+                    ExecError::from_rc(rc).map_or(Ok(None), Err)
+                }
+            }
+
+            /// Creates a `MatchIterator` for iterating through matches within the given
+            /// subject `subject`.
+            #[inline]
+            pub fn matches<'a, 'p>(&'p self, subject: &'a [$unit]) -> $MatchIterator<'a, 'p> {
+                self.matches_with_options(subject, &ExecOptions::empty())
+            }
+
+            /// Creates a `MatchIterator` for iterating through matches within the given
+            /// subject `subject` using the given matching options `options`.
+            ///
+            /// The subject is checked to be valid once, before the first match. The
+            /// iterator yields no matches for an invalid subject.
+            pub fn matches_with_options<'a, 'p>(&'p self, subject: &'a [$unit], options: &ExecOptions) -> $MatchIterator<'a, 'p> {
+                self.matches_with_groups(subject, options, self.capture_count())
+            }
+
+            /// Like [matches_with_options()](#method.matches_with_options), but only records
+            /// the first `groups` capture groups of each match. See
+            /// [exec_from_with_groups()](#method.exec_from_with_groups).
+            pub fn matches_with_groups<'a, 'p>(&'p self, subject: &'a [$unit], options: &ExecOptions, groups: usize) -> $MatchIterator<'a, 'p> {
+                $MatchIterator {
+                    re: self,
+                    subject,
+                    offset: 0,
+                    options: *options,
+                    ovector: vec![-1 as c_int; self.ovector_len(groups)]
+                }
+            }
+
+            /// Returns the length of an ovector that records the first `groups` capture groups.
+            fn ovector_len(&self, groups: usize) -> usize {
+                (::std::cmp::min(groups, self.capture_count()) + 1) * 3
+            }
+
+            /// Calls `pcre_fullinfo()` for the field `what`.
+            unsafe fn fullinfo(&self, what: libpcre_sys::fullinfo_field, where_: *mut c_void) {
+                let rc = libpcre_sys::$fullinfo(self.code, self.extra, what, where_);
+                if rc < 0 && rc != libpcre_sys::PCRE_ERROR_NULL {
+                    panic!("pcre_fullinfo");
+                }
+            }
+
+            /// Calls `pcre_exec()`, treating an invalid subject or start offset as no match.
+            ///
+            /// # Return value
+            /// The return code of `pcre_exec()`, or -1 if there is no match. Exceeding the
+            /// match or recursion limit returns the libpcre error code.
+            fn exec_ovector(&self, subject: &[$unit], startoffset: c_int, options: c_int, ovector: &mut [c_int]) -> c_int {
+                for offset in ovector.iter_mut() {
+                    *offset = -1;
+                }
+                let rc = unsafe {
+                    libpcre_sys::$exec(self.code, self.extra, subject.as_ptr(), subject.len() as c_int, startoffset,
+                                       options, ovector.as_mut_ptr(), ovector.len() as c_int)
+                };
+                match rc {
+                    libpcre_sys::PCRE_ERROR_NOMATCH $(| libpcre_sys::$bad_utf)+ => -1,
+                    libpcre_sys::PCRE_ERROR_MATCHLIMIT | libpcre_sys::PCRE_ERROR_RECURSIONLIMIT => rc,
+                    rc if rc < 0 => panic!("pcre_exec"),
+                    rc => rc
+                }
+            }
+        }
+
+        impl Drop for $Pcre {
+            fn drop(&mut self) {
+                unsafe {
+                    libpcre_sys::$free_study(self.extra);
+                    (libpcre_sys::$free)(self.code as *mut libpcre_sys::$raw as *mut c_void);
+                }
+                self.extra = ptr::null_mut();
+                self.code = ptr::null();
+            }
+        }
+
+        impl<'a> $Match<'a> {
+            fn new(subject: &'a [$unit], ovector: &[c_int], rc: c_int, name_table: Option<Arc<BTreeMap<String, Vec<usize>>>>) -> $Match<'a> {
+                let partial_ovector = ovector[..(ovector.len() / 3 * 2)].to_vec();
+                let string_count = if rc > 0 {
+                    rc
+                } else {
+                    partial_ovector.chunks(2).rposition(|offsets| offsets[0] >= 0).map_or(0, |n| n + 1) as c_int
+                };
+                $Match {
+                    subject,
+                    partial_ovector,
+                    string_count_: string_count,
+                    name_table_: name_table
+                }
+            }
+
+            /// Returns the code unit offset of the start of capture group `n`.
+            #[inline]
+            pub fn group_start(&self, n: usize) -> usize {
+                self.partial_ovector[n * 2] as usize
+            }
+
+            /// Returns the code unit offset of the end of capture group `n`.
+            #[inline]
+            pub fn group_end(&self, n: usize) -> usize {
+                self.partial_ovector[n * 2 + 1] as usize
+            }
+
+            /// Returns the length in code units of the substring for capture group `n`.
+            #[inline]
+            pub fn group_len(&self, n: usize) -> usize {
+                let group_offsets = &self.partial_ovector[(n * 2)..];
+                (group_offsets[1] - group_offsets[0]) as usize
+            }
+
+            /// Returns the substring for capture group `n` as a slice of the subject.
+            #[inline]
+            pub fn group(&self, n: usize) -> &'a [$unit] {
+                let group_offsets = &self.partial_ovector[(n * 2)..];
+                let start = group_offsets[0];
+                let end = group_offsets[1];
+                &self.subject[(start as usize)..(end as usize)]
+            }
+
+            /// Returns the substring captured by group `n`, or `None` if there is no such
+            /// group or it did not participate in the match.
+            pub fn get(&self, n: usize) -> Option<&'a [$unit]> {
+                if self.is_set(n) {
+                    Some(self.group(n))
+                } else {
+                    None
+                }
+            }
+
+            /// Returns the substring captured by the capture group named `name`, or `None`
+            /// if there is no such group or it did not participate in the match.
+            pub fn named_group(&self, name: &str) -> Option<&'a [$unit]> {
+                self.name_table_.as_ref()
+                    .and_then(|name_table| name_table.get(name))
+                    .and_then(|numbers| numbers.iter().filter_map(|&n| self.get(n)).next())
+            }
+
+            /// Returns `true` if capture group `n` exists and participated in the match.
+            pub fn is_set(&self, n: usize) -> bool {
+                n < self.string_count() && self.partial_ovector[n * 2] >= 0
+            }
+
+            /// Returns the code unit range within the subject of capture group `n`.
+            ///
+            /// # Panics
+            /// If there is no such group or it did not participate in the match.
+            pub fn range(&self, n: usize) -> Range<usize> {
+                match self.get(n) {
+                    None => panic!("capture group {} did not participate in the match", n),
+                    Some(_) => self.group_start(n)..self.group_end(n)
+                }
+            }
+
+            /// Returns the number of the highest capture group that is set, plus one.
+            pub fn string_count(&self) -> usize {
+                self.string_count_ as usize
+            }
+
+            /// Returns the number of capture groups in the regular expression, plus one for
+            /// the whole match.
+            #[allow(clippy::len_without_is_empty)]
+            pub fn len(&self) -> usize {
+                self.partial_ovector.len() / 2
+            }
+
+            /// Returns the part of the subject before the match.
+            pub fn pre_match(&self) -> &'a [$unit] {
+                &self.subject[..self.group_start(0)]
+            }
+
+            /// Returns the part of the subject after the match.
+            pub fn post_match(&self) -> &'a [$unit] {
+                &self.subject[self.group_end(0)..]
+            }
+        }
+
+        impl<'a> Index<usize> for $Match<'a> {
+            type Output = [$unit];
+
+            /// Returns the substring for capture group `n`.
+            ///
+            /// # Panics
+            /// If there is no such group or it did not participate in the match.
+            fn index(&self, n: usize) -> &[$unit] {
+                match self.get(n) {
+                    None => panic!("capture group {} did not participate in the match", n),
+                    Some(group) => group
+                }
+            }
+        }
+
+        impl<'a, 'n> Index<&'n str> for $Match<'a> {
+            type Output = [$unit];
+
+            /// Returns the substring for the capture group named `name`.
+            ///
+            /// # Panics
+            /// If there is no such group or it did not participate in the match.
+            fn index(&self, name: &'n str) -> &[$unit] {
+                match self.named_group(name) {
+                    None => panic!("capture group `{}` did not participate in the match", name),
+                    Some(group) => group
+                }
+            }
+        }
+
+        impl<'a, 'p> Iterator for $MatchIterator<'a, 'p> {
+            type Item = $Match<'a>;
+
+            /// Gets the next match.
+            #[inline]
+            fn next(&mut self) -> Option<$Match<'a>> {
+                if self.subject.len() < self.offset as usize {
+                    return None;
+                }
+                // Only the first match checks the subject.
+                let mut options = self.options.bits();
+                if self.offset > 0 {
+                    options |= libpcre_sys::$no_utf_check;
+                }
+                let rc = self.re.exec_ovector(self.subject, self.offset, options, &mut self.ovector);
+                if rc >= 0 {
+                    // Advance by at least one character, so as not to split a character
+                    // after an empty match.
+                    let offset = self.offset as usize;
+                    let next = if offset < self.subject.len() { offset + $char_len(self.subject[offset]) } else { offset + 1 };
+                    self.offset = ::std::cmp::max(next as c_int, self.ovector[1]);
+
+                    Some($Match::new(self.subject, &self.ovector, rc, self.re.name_table_.clone()))
+                } else if let Some(err) = ExecError::from_rc(rc) {
+                    panic!("pcre_exec: {}", err)
+                } else {
+                    self.offset = self.subject.len() as c_int + 1;
+                    None
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "pcre16")]
+wide_pcre! {
+    /// A regular expression compiled by the 16-bit libpcre library, which matches UTF-16
+    /// subjects given as `&[u16]`. Enabled by the `pcre16` feature.
+    ///
+    /// Offsets are counted in 16-bit code units. Apart from the subject type, the API
+    /// follows that of [Pcre](struct.Pcre.html), except that there is no iterator over the
+    /// groups of a match and no support for marks.
+    ///
+    /// ```
+    /// let re = pcre::Pcre16::compile("(?<word>\\w+)!").unwrap();
+    /// let subject: Vec<u16> = "\u{1f600} hi!".encode_utf16().collect();
+    /// let m = re.exec(&subject).unwrap();
+    /// assert_eq!(m.range(0), 3..6);
+    /// assert_eq!(String::from_utf16(m.named_group("word").unwrap()).unwrap(), "hi");
+    /// ```
+    pub struct Pcre16;
+    /// A match of a UTF-16 subject against a [Pcre16](struct.Pcre16.html).
+    pub struct Match16;
+    /// Iterator type for iterating matches within a UTF-16 subject.
+    pub struct MatchIterator16;
+
+    unit: u16,
+    raw: pcre16,
+    extra: pcre16_extra,
+    utf: PCRE_UTF16,
+    no_utf_check: PCRE_NO_UTF16_CHECK,
+    bad_utf: PCRE_ERROR_BADUTF16 | PCRE_ERROR_BADUTF16_OFFSET,
+    compile: pcre16_compile2,
+    exec: pcre16_exec,
+    free: pcre16_free,
+    free_study: pcre16_free_study,
+    fullinfo: pcre16_fullinfo,
+    study: pcre16_study,
+    encode: |s: &str| s.encode_utf16().collect(),
+    unit_len: char::len_utf16,
+    decode_name: |name: &[u16]| String::from_utf16_lossy(name),
+    char_len: |unit: u16| if (0xd800..0xdc00).contains(&unit) { 2 } else { 1 }
+}
+
+#[cfg(feature = "pcre32")]
+wide_pcre! {
+    /// A regular expression compiled by the 32-bit libpcre library, which matches UTF-32
+    /// subjects given as `&[u32]`. Enabled by the `pcre32` feature.
+    ///
+    /// Offsets are counted in characters, which are 32-bit code units. The API is that of
+    /// [Pcre16](struct.Pcre16.html).
+    ///
+    /// ```
+    /// let re = pcre::Pcre32::compile("(?<word>\\w+)!").unwrap();
+    /// let subject: Vec<u32> = "\u{1f600} hi!".chars().map(|c| c as u32).collect();
+    /// let m = re.exec(&subject).unwrap();
+    /// assert_eq!(m.range(0), 2..5);
+    /// assert_eq!(m.named_group("word").unwrap(), &['h' as u32, 'i' as u32]);
+    /// ```
+    pub struct Pcre32;
+    /// A match of a UTF-32 subject against a [Pcre32](struct.Pcre32.html).
+    pub struct Match32;
+    /// Iterator type for iterating matches within a UTF-32 subject.
+    pub struct MatchIterator32;
+
+    unit: u32,
+    raw: pcre32,
+    extra: pcre32_extra,
+    utf: PCRE_UTF32,
+    no_utf_check: PCRE_NO_UTF32_CHECK,
+    bad_utf: PCRE_ERROR_BADUTF32,
+    compile: pcre32_compile2,
+    exec: pcre32_exec,
+    free: pcre32_free,
+    free_study: pcre32_free_study,
+    fullinfo: pcre32_fullinfo,
+    study: pcre32_study,
+    encode: |s: &str| s.chars().map(|c| c as u32).collect(),
+    unit_len: |_| 1,
+    decode_name: |name: &[u32]| name.iter().filter_map(|&unit| ::std::char::from_u32(unit)).collect(),
+    char_len: |_| 1
+}
@@ -0,0 +1,48 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate pcre;
+
+use pcre::{MemoryBudget, Pcre};
+
+#[test]
+fn test_memory_budget() {
+    // The allocation functions must be replaced before libpcre is used, which is why this
+    // is the only test in this file.
+    unsafe { pcre::install_allocator() };
+
+    let budget = MemoryBudget::new(1 << 20);
+    let mut compiled = budget.scope(|| Pcre::compile("(?<word>\\w+)@(?:a|bc)+")).unwrap().unwrap();
+    let compiled_size = budget.used();
+    assert!(compiled_size > 0);
+    assert!(budget.scope(|| compiled.study()).is_ok());
+    let used = budget.used();
+    assert!(budget.peak() >= used);
+    assert_eq!(budget.scope(|| compiled.exec("x me@abc").map(|m| m.group(1))).unwrap(), Some("me"));
+
+    // Freeing memory charged to a budget releases it, even on another thread.
+    std::thread::spawn(move || drop(compiled)).join().unwrap();
+    assert_eq!(budget.used(), 0);
+    assert!(budget.peak() >= used);
+
+    let small = MemoryBudget::new(compiled_size / 2);
+    let err = small.scope(|| Pcre::compile("(?<word>\\w+)@(?:a|bc)+")).unwrap_err();
+    assert_eq!(err.limit, compiled_size / 2);
+    assert!(err.requested > 0);
+    assert_eq!(small.used(), 0);
+
+    // Nested scopes charge the innermost budget, and outside of scopes nothing is charged.
+    let outer = MemoryBudget::new(1 << 20);
+    let inner = MemoryBudget::new(1 << 20);
+    let (a, b) = outer.scope(|| (inner.scope(|| Pcre::compile("a+").unwrap()).unwrap(), Pcre::compile("b+").unwrap())).unwrap();
+    let c = Pcre::compile("c+").unwrap();
+    assert!(inner.used() > 0 && outer.used() > 0);
+    assert_eq!(inner.used(), outer.used());
+    drop((a, b, c));
+    assert_eq!(inner.used() + outer.used(), 0);
+}
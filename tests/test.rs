@@ -1,14 +1,31 @@
-extern crate enum_set;
+#[macro_use]
 extern crate pcre;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 
-use enum_set::{EnumSet};
-use pcre::{CompileOption, Pcre, StudyOption};
+use pcre::{CompilationErrorKind, CompileOptions, ExecError, ExecOptions, LineIndex, Pcre, PcreCache, PcreSet, PerlLiteralError, Position, ReDosKind, SafeCompileError, SafeCompileOptions, StackGuard, StudyOptions, Template, TemplateError, Version};
+use std::sync::{Arc};
+use std::thread;
 
 #[test]
-#[should_panic]
 fn test_compile_nul() {
-    // Nul bytes are not allowed in the pattern string.
-    drop(Pcre::compile("\0abc"));
+    // Nul bytes in the pattern string match a literal nul byte.
+    let re = Pcre::compile("\0abc").unwrap();
+    assert_eq!(re.pattern(), "\0abc");
+    assert_eq!(re.exec("x\0abc").unwrap().group_start(0), 1);
+
+    let re = Pcre::compile("^[a\0]+\\\0\\Q.\0\\E$").unwrap();
+    assert!(re.exec("a\0a\0.\0").is_some());
+    assert!(re.exec("a\0a\0x\0").is_none());
+}
+
+#[test]
+fn test_compile_nul_error_offset() {
+    // The error offset refers to the pattern as given, not to the escaped pattern.
+    let err = Pcre::compile("\0\0(").unwrap_err();
+    assert_eq!(err.offset(), 3);
+    let err = Pcre::compile("a\\Q\0\\E\0)").unwrap_err();
+    assert_eq!(err.offset(), 7);
 }
 
 #[test]
@@ -30,6 +47,7 @@ fn test_compile_capture_count() {
 }
 
 #[test]
+#[allow(unused_mut)]
 fn test_exec_basic() {
     let mut re = Pcre::compile("^...$").unwrap();
     assert_eq!(re.capture_count(), 0);
@@ -38,12 +56,14 @@ fn test_exec_basic() {
 }
 
 #[test]
+#[allow(unused_mut)]
 fn test_exec_no_match() {
     let mut re = Pcre::compile("abc").unwrap();
     assert!(re.exec("def").is_none());
 }
 
 #[test]
+#[allow(unused_mut)]
 fn test_exec_nul_byte() {
     // Nul bytes *are* allowed in subject strings, however.
     let mut re = Pcre::compile("abc\\0def").unwrap();
@@ -52,6 +72,7 @@ fn test_exec_nul_byte() {
 }
 
 #[test]
+#[allow(unused_mut)]
 fn test_exec_from_basic() {
     let mut re = Pcre::compile("abc").unwrap();
     let subject = "abcabc";
@@ -77,6 +98,7 @@ fn test_study_basic() {
 }
 
 #[test]
+#[allow(unused_mut)]
 fn test_matches_basic() {
     let subject = "\0abc1111abcabc___ababc+a";
     let mut re = Pcre::compile("abc").unwrap();
@@ -114,6 +136,7 @@ fn test_matches_basic() {
 }
 
 #[test]
+#[allow(unused_mut)]
 fn test_matches_zero_width() {
     let subject = "12";
     let mut re = Pcre::compile("").unwrap();
@@ -142,6 +165,7 @@ fn test_matches_zero_width() {
 }
 
 #[test]
+#[allow(unused_mut)]
 fn test_matches_zero_width_empty_target() {
     let subject = "";
     let mut re = Pcre::compile("").unwrap();
@@ -163,17 +187,16 @@ fn test_extra_mark() {
     let subject1 = "XY";
     let subject2 = "XZ";
 
-    let mut compile_options: EnumSet<CompileOption> = EnumSet::new();
-    compile_options.insert(CompileOption::Extra);
+    // libpcre2 does not support PCRE_EXTRA.
+    let compile_options = if cfg!(feature = "pcre2") { CompileOptions::empty() } else { CompileOptions::EXTRA };
 
     let mut re = Pcre::compile_with_options(pattern, &compile_options).unwrap();
 
     // first try to get the mark from the compile to make sure it fails
     assert_eq!(re.mark(), None);
 
-    let mut study_options: EnumSet<StudyOption> = EnumSet::new();
-    //study_options.add(StudyOption::StudyExtraNeeded);
-    study_options.insert(StudyOption::StudyJitCompile);
+    //let study_options = StudyOptions::EXTRA_NEEDED;
+    let study_options = StudyOptions::JIT_COMPILE;
     let study = re.study_with_options(&study_options);
     // Double check to make sure the study worked
     assert!(study);
@@ -215,6 +238,7 @@ fn test_extra_mark() {
 }
 
 #[test]
+#[allow(unused_mut, clippy::legacy_numeric_constants)]
 fn test_optional_capture() {
     let mut re = Pcre::compile("(foo)?bar").unwrap();
     let subject = "bar";
@@ -227,45 +251,730 @@ fn test_optional_capture() {
 }
 
 #[test]
-fn test_matches_zero_width() {
-    let subject = "12";
-    let mut re = Pcre::compile("").unwrap();
-    let mut it = re.matches(subject);
+fn test_compile_options_from_str() {
+    let options: CompileOptions = "imsx".parse().unwrap();
+    assert_eq!(options, CompileOptions::CASELESS | CompileOptions::MULTILINE | CompileOptions::DOTALL | CompileOptions::EXTENDED);
+    assert!(options.contains(CompileOptions::CASELESS));
+    assert!(!options.contains(CompileOptions::UNGREEDY));
+
+    let err = "iq".parse::<CompileOptions>().unwrap_err();
+    assert_eq!(err.modifier(), 'q');
+    assert_eq!(err.offset(), 1);
+}
 
-    let mut opt_m = it.next();
-    assert!(opt_m.is_some());
-    let mut m = opt_m.unwrap();
-    assert_eq!(m.group_start(0), 0);
-    assert_eq!(m.group_end(0), 0);
+#[test]
+fn test_options_debug() {
+    assert_eq!(format!("{:?}", CompileOptions::empty()), "(empty)");
+    assert_eq!(format!("{:?}", CompileOptions::CASELESS | CompileOptions::NEWLINE_ANYCRLF), "CASELESS | NEWLINE_ANYCRLF");
+    assert_eq!(format!("{:?}", ExecOptions::PARTIAL | ExecOptions::from_bits(0x40000000)), "PARTIAL_SOFT | 0x40000000");
+}
 
-    let mut opt_m = it.next();
-    assert!(opt_m.is_some());
-    let mut m = opt_m.unwrap();
-    assert_eq!(m.group_start(0), 1);
-    assert_eq!(m.group_end(0), 1);
+#[test]
+fn test_compile_caseless() {
+    let re = Pcre::compile_with_options("abc", &CompileOptions::CASELESS).unwrap();
+    assert_eq!(re.exec("xABCx").unwrap().group(0), "ABC");
+}
 
-    let mut opt_m = it.next();
-    assert!(opt_m.is_some());
-    let mut m = opt_m.unwrap();
-    assert_eq!(m.group_start(0), 2);
+#[test]
+fn test_compile_never_utf() {
+    // Patterns are always compiled in UTF-8 mode, which NEVER_UTF forbids.
+    assert!(Pcre::compile_with_options("abc", &CompileOptions::NEVER_UTF).is_err());
+}
+
+#[test]
+fn test_exec_notempty() {
+    let re = Pcre::compile("a*").unwrap();
+    let m = re.exec_from_with_options("bab", 0, &ExecOptions::NOTEMPTY).unwrap();
+    assert_eq!(m.group_start(0), 1);
     assert_eq!(m.group_end(0), 2);
+}
 
-    let mut opt_m = it.next();
-    assert!(opt_m.is_none());
+#[test]
+fn test_compile_perl_literal() {
+    let re = Pcre::compile_perl_literal("/foo.*bar/i").unwrap();
+    assert_eq!(re.pattern(), "foo.*bar");
+    assert_eq!(re.compile_options(), CompileOptions::CASELESS);
+    assert!(re.exec("FOO-BAR").is_some());
+
+    let re = Pcre::compile_perl_literal("m{a/(b{2})}x").unwrap();
+    assert_eq!(re.pattern(), "a/(b{2})");
+    assert_eq!(re.compile_options(), CompileOptions::EXTENDED);
+    assert_eq!(re.exec("a/bb").unwrap().group(1), "bb");
+
+    let re = Pcre::compile_perl_literal("m!a\\!b!").unwrap();
+    assert_eq!(re.pattern(), "a\\!b");
 }
 
 #[test]
-fn test_matches_zero_width_empty_target() {
-    let subject = "";
-    let mut re = Pcre::compile("").unwrap();
-    let mut it = re.matches(subject);
+fn test_compile_perl_literal_errors() {
+    match Pcre::compile_perl_literal("foo") {
+        Err(PerlLiteralError::MissingDelimiter) => (),
+        _ => panic!("expected MissingDelimiter")
+    }
+    match Pcre::compile_perl_literal("m{foo") {
+        Err(PerlLiteralError::Unterminated) => (),
+        _ => panic!("expected Unterminated")
+    }
+    match Pcre::compile_perl_literal("/foo/iz") {
+        Err(PerlLiteralError::UnknownModifier(err)) => assert_eq!(err.modifier(), 'z'),
+        _ => panic!("expected UnknownModifier")
+    }
+    match Pcre::compile_perl_literal("/fo(o/") {
+        Err(PerlLiteralError::Compilation(err)) => assert_eq!(err.offset(), 4),
+        _ => panic!("expected Compilation")
+    }
+}
 
-    let mut opt_m = it.next();
-    assert!(opt_m.is_some());
-    let mut m = opt_m.unwrap();
-    assert_eq!(m.group_start(0), 0);
-    assert_eq!(m.group_end(0), 0);
+#[test]
+fn test_to_perl_literal() {
+    for literal in ["/foo/imsx", "m{a/b}U", "/a\\/b|c{1}\\/d!#|~/", "/x/", "/\\Q{!#|~\\\\E\\/a/"].iter() {
+        let re = Pcre::compile_perl_literal(literal).unwrap();
+        let round_tripped = Pcre::compile_perl_literal(&re.to_perl_literal().unwrap()).unwrap();
+        assert_eq!(round_tripped.pattern(), re.pattern());
+        assert_eq!(round_tripped.compile_options(), re.compile_options());
+    }
+    assert_eq!(Pcre::compile_perl_literal("m{a/b}i").unwrap().to_perl_literal().unwrap(), "m{a/b}i");
+
+    let re = Pcre::compile_with_options("abc", &CompileOptions::ANCHORED).unwrap();
+    assert!(re.to_perl_literal().is_none());
+    // A quoted slash cannot be escaped.
+    assert!(Pcre::compile("\\Qa/b\\E{}!#|~").unwrap().to_perl_literal().is_none());
+}
 
-    let mut opt_m = it.next();
-    assert!(opt_m.is_none());
+#[test]
+fn test_escape() {
+    let literals = ["a.b*c", "^[x]{2}(y|z)?$\\", "# comment\n\tx", "caf\u{e9} + 1", "nul\0byte", "-/=<>!:"];
+    for literal in literals.iter() {
+        let escaped = pcre::escape(literal);
+        assert!(!escaped.contains('\0'));
+        let re = Pcre::compile_with_options(&format!("^{}$", escaped), &CompileOptions::EXTENDED).unwrap();
+        assert_eq!(re.exec(literal).unwrap().group(0), *literal);
+        assert!(re.exec(&format!("{}x", literal)).is_none());
+    }
+    assert_eq!(pcre::escape("abc_123"), "abc_123");
+}
+
+#[test]
+fn test_quote() {
+    let literals = ["a.b*c", "a\\Eb", "\\E", "\\", "x\\Q\\E\\E", "nul\0byte", ""];
+    for literal in literals.iter() {
+        let quoted = pcre::quote(literal);
+        assert!(!quoted.contains('\0'));
+        let re = Pcre::compile(&format!("^{}$", quoted)).unwrap();
+        assert_eq!(re.exec(literal).unwrap().group(0), *literal);
+    }
+}
+
+#[test]
+fn test_set_matches() {
+    let set = PcreSet::new(["ERROR .* timeout", "^WARN", "disk (\\d+)%", "(?i)panic"]).unwrap();
+    assert_eq!(set.len(), 4);
+    assert!(set.is_combined());
+
+    assert_eq!(set.matches("ERROR read timeout, disk 91%"), vec![0, 2]);
+    assert_eq!(set.matches("WARN kernel PANIC"), vec![1, 3]);
+    assert!(set.matches("INFO all good").is_empty());
+    assert!(set.is_match("disk 5%"));
+    assert!(!set.is_match("disk full"));
+
+    let all = set.exec_all("ERROR read timeout, disk 91%");
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[1].0, 2);
+    assert_eq!(all[1].1.group(1), "91");
+}
+
+#[test]
+fn test_set_exec() {
+    let patterns = ["b(c)", "a(b)", "(?:(x)|y)\\1", "c"];
+    let subjects = ["xxabc", "zzbc", "yxx", "none", "ccab"];
+
+    // Patterns with back references are matched one by one rather than combined.
+    let separate = PcreSet::new(patterns).unwrap();
+    assert!(!separate.is_combined());
+    let combined = PcreSet::new(&patterns[..2]).unwrap();
+    assert!(combined.is_combined());
+
+    for subject in subjects.iter() {
+        for set in [&separate, &combined].iter() {
+            let expected = (0..set.len())
+                .filter_map(|i| set.get(i).unwrap().exec(subject).map(|m| (m.group_start(0), i)))
+                .min();
+            let actual = set.exec(subject);
+            assert_eq!(actual.as_ref().map(|&(i, ref m)| (m.group_start(0), i)), expected);
+            if let Some((i, m)) = actual {
+                assert_eq!(m.string_count(), set.get(i).unwrap().exec(subject).unwrap().string_count());
+            }
+        }
+    }
+
+    let (index, m) = combined.exec("xxabc").unwrap();
+    assert_eq!(index, 1);
+    assert_eq!(m.group(1), "b");
+}
+
+#[test]
+fn test_set_exec_verbs() {
+    // Backtracking control verbs in one pattern must not hide the matches of the others.
+    let cases: [(&[&str], &str); 4] = [
+        (&["a(*COMMIT)b", "ac"], "ac"),
+        (&["a(*PRUNE)b", "a"], "ax"),
+        (&["a(*SKIP)b|c", "ad"], "ad"),
+        (&["a(*ACCEPT)b", "x"], "ax")
+    ];
+    for &(patterns, subject) in cases.iter() {
+        let set = PcreSet::new(patterns).unwrap();
+        assert!(!set.is_combined());
+        assert_eq!(set.exec(subject).map(|(i, _)| i), set.matches(subject).first().cloned());
+    }
+
+    // The prefilters of patterns matched one by one must not require what follows (*ACCEPT).
+    let set = PcreSet::new(["xa(*ACCEPT)bcd", "q"]).unwrap();
+    assert!(set.is_match("xa"));
+    assert_eq!(set.matches("xa"), vec![0]);
+    assert_eq!(set.exec("xa").map(|(i, m)| (i, m.group(0))), Some((0, "xa")));
+}
+
+#[test]
+fn test_set_compilation_error() {
+    let err = PcreSet::new(vec!["a".to_string(), "(".to_string()]).unwrap_err();
+    assert_eq!(err.index(), 1);
+    assert_eq!(err.error().offset(), 1);
+}
+
+#[test]
+fn test_prefilter_consistency() {
+    // Enabling the mark field disables the prefilter, which gives the expected results.
+    let patterns = ["ERROR .* timeout", "ab+c", "ab?c", "a{2}b{0}c{1,}d", "x{,2}y", "[abc]def", "(?:foo|bar)baz",
+                    "(?i)abc", "a(?i)bc", "a(?-i)bc", "\\Qa.b\\E", "a\\.b", "a\\x41b", "caf\u{e9}+", "k", "(?#(c)de",
+                    "a|b", "(?=xyz)x", "a\\Kbc", "a\\bc", "(*CR)ab", "[a\\]b]c", "[[:alpha:]]z", "\\0ab", "\u{e9}\\0?"];
+    let subjects = ["ERROR read timeout", "ERROR", "abbbc", "ac", "abc", "ABC", "aBC", "aaccd", "aad", "xxy", "y", "bdef",
+                    "barbaz", "foobaz", "a.b", "aAb", "cafe", "caf\u{e9}\u{e9}", "K", "\u{212a}", "e", "(c)de", "b",
+                    "xyz", "a bc", "\0ab", "\u{e9}", "]c", "qz", ""];
+    for pattern in patterns.iter() {
+        for &options in [CompileOptions::empty(), CompileOptions::CASELESS, CompileOptions::EXTENDED].iter() {
+            let filtered = Pcre::compile_with_options(pattern, &options).unwrap();
+            let mut unfiltered = Pcre::compile_with_options(pattern, &options).unwrap();
+            assert!(unfiltered.study_with_options(&StudyOptions::EXTRA_NEEDED));
+            assert!(unfiltered.enable_mark());
+            for subject in subjects.iter() {
+                for offset in [0, subject.chars().next().map_or(0, char::len_utf8)].iter().cloned() {
+                    let expected = unfiltered.exec_from(subject, offset).map(|m| m.group_start(0));
+                    assert_eq!(filtered.exec_from(subject, offset).map(|m| m.group_start(0)), expected,
+                               "pattern {:?} options {:?} subject {:?} offset {}", pattern, options, subject, offset);
+                }
+                assert_eq!(filtered.matches(subject).count(), unfiltered.matches(subject).count());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_prefilter_accept() {
+    // Nothing after (*ACCEPT) is required, even outside of the group that contains it.
+    for &(pattern, subject) in [("xa(*ACCEPT)bcd", "xa"), ("a(?:(*ACCEPT))zzz", "a"), ("a(?:b|(*ACCEPT))zzz", "ac")].iter() {
+        let re = Pcre::compile(pattern).unwrap();
+        let mut unfiltered = Pcre::compile(pattern).unwrap();
+        assert!(unfiltered.study_with_options(&StudyOptions::EXTRA_NEEDED));
+        assert!(unfiltered.enable_mark());
+        assert!(re.exec(subject).is_some(), "pattern {:?} subject {:?}", pattern, subject);
+        assert_eq!(re.exec(subject).map(|m| m.group_end(0)), unfiltered.exec(subject).map(|m| m.group_end(0)));
+    }
+}
+
+#[test]
+fn test_cache_lru() {
+    let cache = PcreCache::new(2);
+    let a = cache.get("a").unwrap();
+    let b = cache.get("b").unwrap();
+    assert!(Arc::ptr_eq(&a, &cache.get("a").unwrap()));
+    // The same pattern with other options is another entry, which evicts "b".
+    let caseless_a = cache.get_with_options("a", &CompileOptions::CASELESS).unwrap();
+    assert!(!Arc::ptr_eq(&a, &caseless_a));
+    assert!(caseless_a.exec("A").is_some());
+    assert!(Arc::ptr_eq(&a, &cache.get("a").unwrap()));
+    assert!(!Arc::ptr_eq(&b, &cache.get("b").unwrap()));
+    assert_eq!(cache.len(), 2);
+
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.evictions), (2, 4, 2));
+
+    assert!(cache.get("(").is_err());
+    assert_eq!(cache.len(), 2);
+    cache.clear();
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn test_cache_threads() {
+    let cache = Arc::new(PcreCache::with_study_options(8, &StudyOptions::JIT_COMPILE));
+    let threads: Vec<_> = (0..4).map(|i| {
+        let cache = cache.clone();
+        thread::spawn(move || {
+            for _ in 0..100 {
+                let re = cache.get("(\\d+)-(\\d+)").unwrap();
+                let subject = format!("{}-{}", i, i * 2);
+                let m = re.exec(&subject).unwrap();
+                assert_eq!(m.group(2), (i * 2).to_string());
+            }
+        })
+    }).collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(cache.len(), 1);
+    let stats = cache.stats();
+    assert_eq!(stats.hits + stats.misses, 400);
+}
+
+#[test]
+fn test_pcre_macro() {
+    fn first_word(s: &str) -> Option<&str> {
+        pcre!("\\w+").exec(s).map(|m| m.group(0))
+    }
+    assert_eq!(first_word("  hello world"), Some("hello"));
+    assert_eq!(first_word("  "), None);
+    assert!(pcre!("^x$", CompileOptions::CASELESS).exec("X").is_some());
+    // Each use of the macro compiles its pattern only once.
+    let compiled: Vec<*const Pcre> = (0..2).map(|_| pcre!("y") as *const Pcre).collect();
+    assert_eq!(compiled[0], compiled[1]);
+}
+
+#[test]
+fn test_named_group() {
+    let re = Pcre::compile_with_options("(?<n>a)|(?<n>b)|(?<m>c)", &CompileOptions::DUPNAMES).unwrap();
+    let m = re.exec("b").unwrap();
+    assert_eq!(m.named_group("n"), Some("b"));
+    assert_eq!(m.named_group("m"), None);
+    assert_eq!(m.named_group("x"), None);
+    assert_eq!(Pcre::compile("a").unwrap().exec("a").unwrap().named_group("n"), None);
+}
+
+#[test]
+fn test_match_groups() {
+    let re = Pcre::compile("(?<key>\\w+)=(\\d+)?(x)?(y)?").unwrap();
+    let m = re.exec("<a=x>").unwrap();
+    assert_eq!(m.len(), 5);
+    assert_eq!(m.as_str(), "a=x");
+    assert_eq!(m.iter().collect::<Vec<_>>(), [Some("a=x"), Some("a"), None, Some("x"), None]);
+    assert_eq!((&m).into_iter().next_back(), Some(None));
+    assert_eq!(m.iter().len(), 5);
+    assert_eq!((&m[1], &m["key"], &m[3]), ("a", "a", "x"));
+    assert_eq!((m.range(0), m.range(3)), (1..4, 3..4));
+    assert_eq!((m.pre_match(), m.post_match()), ("<", ">"));
+    assert_eq!(format!("{:?}", m), r#"Match { range: 1..4, groups: [Some("a=x"), Some("a"), None, Some("x"), None] }"#);
+
+    let copy = m.clone();
+    assert_eq!(copy.get(1), Some("a"));
+}
+
+#[test]
+fn test_exec_with_groups() {
+    let re = Pcre::compile("(a)(b)?(c)").unwrap();
+    let m = re.exec_from_with_groups("xac", 0, &ExecOptions::empty(), 1).unwrap();
+    assert_eq!((m.len(), m.string_count()), (2, 2));
+    assert_eq!(m.iter().collect::<Vec<_>>(), [Some("ac"), Some("a")]);
+    assert!(m.is_set(1) && !m.is_set(2) && !m.is_set(3));
+    assert_eq!(m.get(3), None);
+
+    let m = re.exec_from_with_groups("xac", 0, &ExecOptions::empty(), 0).unwrap();
+    assert_eq!((m.len(), m.string_count(), m.as_str()), (1, 1, "ac"));
+
+    let m = re.exec_from_with_groups("ac", 0, &ExecOptions::empty(), 10).unwrap();
+    assert_eq!((m.len(), m.string_count()), (4, 4));
+    assert!(!m.is_set(2) && m.is_set(3));
+
+    let re = Pcre::compile("(a)?(b)").unwrap();
+    let m = re.exec_from_with_groups("b", 0, &ExecOptions::empty(), 1).unwrap();
+    assert_eq!((m.len(), m.string_count()), (2, 1));
+    assert!(m.is_set(0) && !m.is_set(1));
+
+    let ends: Vec<_> = re.matches_with_groups("ab b", &ExecOptions::empty(), 1).map(|m| (m.get(1), m.string_count())).collect();
+    assert_eq!(ends, [(Some("a"), 2), (None, 1)]);
+}
+
+#[test]
+fn test_match_offsets() {
+    let subject = "h\u{e9}llo\n\u{1f600} w\u{f6}rld\r\n\nend";
+    let re = Pcre::compile_with_options("(w\\Sr)ld", &CompileOptions::UTF8).unwrap();
+    let m = re.exec(subject).unwrap();
+    assert_eq!((m.range(0), m.char_range(0), m.utf16_range(0)), (12..18, 8..13, 9..14));
+    assert_eq!(m.char_range(1), 8..11);
+
+    let index = LineIndex::new(subject);
+    assert_eq!(index.line_count(), 4);
+    assert_eq!((index.line_range(1), index.line_range(3)), (7..20, 21..24));
+    assert_eq!((index.char_offset(18), index.utf16_offset(18)), (13, 14));
+    assert_eq!(m.positions(0, &index), Position { line: 1, column: 2 }..Position { line: 1, column: 7 });
+    assert_eq!(index.utf16_position(12), Position { line: 1, column: 3 });
+    assert_eq!(index.position(7), Position { line: 1, column: 0 });
+    assert_eq!(index.position(subject.len()), Position { line: 3, column: 3 });
+
+    let lines: Vec<_> = Pcre::compile("(?m)^e").unwrap().matches(subject)
+        .map(|m| index.position(m.group_start(0)).line).collect();
+    assert_eq!(lines, [3]);
+}
+
+#[test]
+#[should_panic(expected = "capture group 2 did not participate in the match")]
+fn test_match_index_unset() {
+    let re = Pcre::compile("(a)(b)?").unwrap();
+    let _ = &re.exec("a").unwrap()[2];
+}
+
+#[test]
+#[should_panic(expected = "capture group `b` did not participate in the match")]
+fn test_match_index_name_unset() {
+    let re = Pcre::compile("(?<a>a)").unwrap();
+    let _ = &re.exec("a").unwrap()["b"];
+}
+
+#[test]
+fn test_match_expand() {
+    let re = Pcre::compile("(?<key>\\w+)=(\\d+)?(x)?").unwrap();
+    let m = re.exec("<a=1>").unwrap();
+    let mut dst = String::from(">");
+    m.expand("$0|$&|$1|${2}0|$20|${key}|$3|$`|$'|$$|$|$.|a$", &mut dst).unwrap();
+    assert_eq!(dst, ">a=1|a=1|a|10||a||<|>|$|$|$.|a$");
+
+    assert_eq!(m.expand("${key", &mut dst), Err(TemplateError::Unterminated(0)));
+    assert_eq!(m.expand("x${}", &mut dst), Err(TemplateError::InvalidReference(1)));
+    assert_eq!(m.expand("$key", &mut dst), Err(TemplateError::InvalidReference(0)));
+    assert_eq!(m.expand("${a-b}", &mut dst), Err(TemplateError::InvalidReference(0)));
+}
+
+#[test]
+fn test_template_validate() {
+    let re = Pcre::compile("(?<key>\\w+)=(\\d+)").unwrap();
+    assert!("$2 ${key} ${0}".parse::<Template>().unwrap().validate(&re).is_ok());
+    assert_eq!("$3".parse::<Template>().unwrap().validate(&re), Err(TemplateError::NoSuchGroup(3)));
+    assert_eq!("${value}".parse::<Template>().unwrap().validate(&re), Err(TemplateError::NoSuchName("value".to_string())));
+
+    // Named groups need braces, while a `$` before anything else is literal.
+    assert_eq!(Template::parse("to $word"), Err(TemplateError::InvalidReference(3)));
+    assert_eq!(Template::parse("$_1"), Err(TemplateError::InvalidReference(0)));
+    assert_eq!(Template::parse("5 $ $-"), Ok(Template::parse("5 $$ $$-").unwrap()));
+
+    let template = Template::parse("${key}:$2;").unwrap();
+    let mut dst = String::new();
+    for m in re.matches("a=1 b=2") {
+        template.expand(&m, &mut dst);
+    }
+    assert_eq!(dst, "a:1;b:2;");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_pcre() {
+    let re = Pcre::compile_with_options("^a.c$", &(CompileOptions::CASELESS | CompileOptions::DOTALL)).unwrap();
+    let json = serde_json::to_string(&re).unwrap();
+    assert_eq!(json, r#"{"pattern":"^a.c$","options":["CASELESS","DOTALL"]}"#);
+
+    let re: Pcre = serde_json::from_str(&json).unwrap();
+    assert!(re.exec("A\nC").is_some());
+    let re: Pcre = serde_json::from_str(r#"{"pattern":"a+","options":"MULTILINE | UNGREEDY"}"#).unwrap();
+    assert_eq!(re.compile_options(), CompileOptions::MULTILINE | CompileOptions::UNGREEDY);
+    let re: Pcre = serde_json::from_str(r#"{"pattern":"a+"}"#).unwrap();
+    assert!(re.compile_options().is_empty());
+
+    let err = serde_json::from_str::<Pcre>(r#"{"pattern":"a(b"}"#).unwrap_err();
+    assert!(err.to_string().contains("compilation failed at offset 3"), "{}", err);
+    assert!(serde_json::from_str::<Pcre>(r#"{"pattern":"a","options":["CASELES"]}"#).is_err());
+
+    // Raw bits must fit in a C int instead of being truncated.
+    let re: Pcre = serde_json::from_str(r#"{"pattern":"a","options":["CASELESS",2]}"#).unwrap();
+    assert_eq!(re.compile_options(), CompileOptions::CASELESS | CompileOptions::MULTILINE);
+    let err = serde_json::from_str::<Pcre>(r#"{"pattern":"a","options":[4294967297]}"#).unwrap_err();
+    assert!(err.to_string().contains("invalid value: integer `4294967297`"), "{}", err);
+    assert!(serde_json::from_str::<Pcre>(r#"{"pattern":"a","options":[-4294967295]}"#).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_match() {
+    let re = Pcre::compile("(?<key>\\w+)=(\\d+)?(?<unit>x)?").unwrap();
+    let m = re.exec(" ab=1").unwrap();
+    let value: serde_json::Value = serde_json::to_value(&m).unwrap();
+    assert_eq!(value, serde_json::json!({
+        "start": 1,
+        "end": 5,
+        "groups": [
+            {"start": 1, "end": 5, "text": "ab=1"},
+            {"start": 1, "end": 3, "text": "ab"},
+            {"start": 4, "end": 5, "text": "1"},
+            null
+        ],
+        "named": {"key": "ab", "unit": null}
+    }));
+}
+
+#[test]
+#[cfg(feature = "pcre16")]
+fn test_pcre16() {
+    use pcre::Pcre16;
+
+    let re = Pcre16::compile("(?<word>\\w+)(\\d)?").unwrap();
+    assert_eq!((re.capture_count(), re.name_count()), (2, 1));
+    let subject: Vec<u16> = "\u{1f600}ab \u{e9}\u{1f600}cd".encode_utf16().collect();
+    let m = re.exec(&subject).unwrap();
+    assert_eq!((m.range(0), m.get(2)), (2..4, None));
+    assert_eq!(String::from_utf16(m.named_group("word").unwrap()).unwrap(), "ab");
+    assert!(re.exec_from(&subject, 1).is_none());
+
+    let words: Vec<String> = re.matches(&subject).map(|m| String::from_utf16(m.group(0)).unwrap()).collect();
+    assert_eq!(words, ["ab", "cd"]);
+    assert_eq!((&m[0], &m["word"]), (&subject[2..4], &subject[2..4]));
+    assert_eq!((m.pre_match(), m.post_match()), (&subject[..2], &subject[4..]));
+
+    let limited = re.exec_from_with_groups(&subject, 5, &ExecOptions::empty(), 0).unwrap();
+    assert_eq!((limited.len(), limited.range(0)), (1, 8..10));
+    assert_eq!(re.matches_with_groups(&subject, &ExecOptions::empty(), 1).map(|m| m.len()).collect::<Vec<_>>(), [2, 2]);
+    let empty = Pcre16::compile("").unwrap();
+    assert_eq!(empty.matches(&subject).map(|m| m.group_start(0)).collect::<Vec<_>>(), [0, 2, 3, 4, 5, 6, 8, 9, 10]);
+
+    let invalid = [0x61, 0xd800, 0x61];
+    assert!(Pcre16::compile("a").unwrap().exec(&invalid).is_none());
+    assert_eq!(Pcre16::compile("\u{e9}(").unwrap_err().offset(), 3);
+
+    let mut nested = Pcre16::compile("^(a+)+$").unwrap();
+    nested.study_with_options(&StudyOptions::EXTRA_NEEDED);
+    nested.extra().unwrap().set_match_limit(1000);
+    let long: Vec<u16> = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaab".encode_utf16().collect();
+    assert_eq!(nested.try_exec(&long).unwrap_err(), ExecError::MatchLimit);
+    assert!(nested.try_exec(&long[..4]).unwrap().is_some());
+}
+
+#[test]
+#[cfg(feature = "pcre32")]
+fn test_pcre32() {
+    use pcre::Pcre32;
+
+    let mut re = Pcre32::compile("(?<word>\\w+)").unwrap();
+    re.study();
+    let subject: Vec<u32> = "\u{1f600}ab cd".chars().map(|c| c as u32).collect();
+    let m = re.exec(&subject).unwrap();
+    assert_eq!(m.range(0), 1..3);
+    assert_eq!(m.named_group("word"), Some(&['a' as u32, 'b' as u32][..]));
+    assert_eq!((&m["word"], m.pre_match(), m.post_match()), (&subject[1..3], &subject[..1], &subject[3..]));
+    assert_eq!(re.matches(&subject).map(|m| m.group_start(0)).collect::<Vec<_>>(), [1, 4]);
+    assert!(re.exec(&[0x61, 0x110000]).is_none());
+}
+
+#[test]
+#[cfg(feature = "pcre2")]
+fn test_pcre2_backend() {
+    assert!(pcre::pcre_version().starts_with("10."));
+
+    let mut re = Pcre::compile_with_options("^b$", &(CompileOptions::MULTILINE | CompileOptions::NEWLINE_CR)).unwrap();
+    assert!(re.exec("a\rb").is_some());
+    assert!(re.exec("a\nb").is_none());
+
+    assert!(re.study_with_options(&StudyOptions::JIT_COMPILE));
+    re.extra().unwrap().set_match_limit(10);
+    assert_eq!(re.extra().unwrap().match_limit(), Some(10));
+    assert!(re.exec("a\rb").is_some());
+
+    let err = Pcre::compile_with_options("a", &(CompileOptions::CASELESS | CompileOptions::EXTRA)).unwrap_err();
+    assert_eq!(err.message().unwrap(), "compile options EXTRA are not supported by libpcre2");
+
+    // Matches reuse their match data and match context, whose limits must not carry over.
+    let mut limited = Pcre::compile("^(a+)+$").unwrap();
+    limited.study_with_options(&StudyOptions::EXTRA_NEEDED);
+    limited.extra().unwrap().set_match_limit(1000);
+    let mut deep = Pcre::compile("^(a|b)(c)?(d)?(e)?(?:a+)+$").unwrap();
+    deep.study_with_options(&StudyOptions::EXTRA_NEEDED);
+    deep.extra().unwrap().set_match_limit_recursion(1000);
+    let subject = format!("{}b", "a".repeat(12));
+    assert_eq!(limited.try_exec(&subject).unwrap_err(), ExecError::MatchLimit);
+    assert!(deep.try_exec(&subject).unwrap().is_none());
+    let m = deep.exec_from_with_groups("aaa", 0, &ExecOptions::empty(), 1).unwrap();
+    assert_eq!((m.len(), m.get(1), m.get(2)), (2, Some("a"), None));
+
+    // libpcre2 only accepts the newline and \R conventions and NO_START_OPTIMIZE when compiling.
+    let mut re = Pcre::compile("a").unwrap();
+    for &options in [ExecOptions::NEWLINE_CR, ExecOptions::BSR_UNICODE, ExecOptions::NO_START_OPTIMIZE].iter() {
+        assert_eq!(re.try_exec_from_with_options("a", 0, &options).unwrap_err(), ExecError::UnsupportedOptions);
+        assert!(re.exec_from_with_options("a", 0, &options).is_none());
+        assert_eq!(re.matches_with_options("a", &options).count(), 0);
+    }
+    assert!(!re.study_with_options(&StudyOptions::from_bits(0x0100)));
+    assert!(re.exec("a").is_some());
+}
+
+#[test]
+fn test_exec_unsupported_options() {
+    let re = Pcre::compile("a").unwrap();
+    let options = ExecOptions::from_bits(0x40000000);
+    assert_eq!(re.try_exec_from_with_options("a", 0, &options).unwrap_err(), ExecError::UnsupportedOptions);
+    assert!(re.exec_from_with_options("a", 0, &options).is_none());
+}
+
+#[test]
+fn test_config() {
+    let config = pcre::config();
+    assert!(config.utf);
+    assert!(config.link_size >= 2);
+    assert!(config.match_limit > 0);
+    assert!(config.match_limit_recursion > 0);
+    assert_eq!(config.jit, config.jit_target.is_some());
+    assert!(config.jit_target.iter().all(|target| !target.as_str().is_empty()));
+    assert!(config.newline.bits().count_ones() >= 1);
+    assert!(config.bsr == CompileOptions::BSR_UNICODE || config.bsr == CompileOptions::BSR_ANYCRLF);
+    if config.unicode_properties {
+        assert!(Pcre::compile(r"\p{Lu}").is_ok());
+    }
+}
+
+#[test]
+fn test_version() {
+    let version = Version::parse("8.39-RC1 2016-05-20").unwrap();
+    assert_eq!(version, Version { major: 8, minor: 39, date: "2016-05-20".to_string() });
+    assert_eq!(version.to_string(), "8.39 2016-05-20");
+    assert!(version.supports_stack_guard());
+    assert!(!Version::parse("8.34 2013-12-15").unwrap().supports_stack_guard());
+    assert!(Version::parse("8.02 2010-03-19").unwrap() < Version::parse("8.10 2010-06-25").unwrap());
+    assert!(!Version::parse("8.02 2010-03-19").unwrap().supports_mark());
+    assert!(Version::parse("10.42 2022-12-11").unwrap().supports_mark());
+    assert_eq!(Version::parse("8"), None);
+    assert_eq!(Version::parse("unknown"), None);
+
+    let linked = pcre::version();
+    assert_eq!(linked, Version::parse(&pcre::pcre_version()).unwrap());
+    assert!(linked.at_least(8, 20));
+}
+
+#[test]
+fn test_stack_guard() {
+    let nested = |depth: usize| format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+    assert_eq!(Pcre::compile("a)").unwrap_err().kind(), CompilationErrorKind::Invalid);
+    assert!(Pcre::compile(&nested(200)).is_ok());
+
+    let guard = StackGuard::new(32 * 1024);
+    assert_eq!(guard.limit(), 32 * 1024);
+    match guard.scope(|| Pcre::compile(&nested(200))) {
+        Err(err) => assert_eq!(err.kind(), CompilationErrorKind::StackGuard),
+        Ok(_) => assert!(!pcre::version().supports_stack_guard())
+    }
+    // The limit only applies within the scope, and shallow patterns compile within it.
+    assert!(guard.scope(|| Pcre::compile("(a)(b)(c)")).is_ok());
+    assert!(Pcre::compile(&nested(200)).is_ok());
+
+    // Nesting beyond what libpcre was built with is reported as such.
+    if let Some(limit) = pcre::config().parens_limit {
+        let err = Pcre::compile(&nested(limit as usize + 1)).unwrap_err();
+        assert_eq!(err.kind(), CompilationErrorKind::NestingLimit);
+    }
+
+    // Nesting within that limit fails to compile instead of overflowing the stack of a small
+    // thread.
+    if pcre::version().supports_stack_guard() {
+        let depth = pcre::config().parens_limit.map_or(1000, |limit| std::cmp::min(limit as usize - 1, 1000));
+        let deep = nested(depth);
+        let result = thread::Builder::new().stack_size(256 * 1024).spawn(move || {
+            StackGuard::new(32 * 1024).scope(|| Pcre::compile(&deep).map(|_| ()).map_err(|err| err.kind()))
+        }).unwrap().join().unwrap();
+        assert_eq!(result, Err(CompilationErrorKind::StackGuard));
+    }
+}
+
+#[test]
+fn test_safe_compile() {
+    let safe = SafeCompileOptions::default();
+    let re = safe.compile("^(\\w+)@[a-z.]+$").unwrap();
+    assert!(re.size() > 0 && re.size() <= safe.max_compiled_size);
+    assert!(re.exec("me@example.com").is_some());
+
+    // Catastrophic backtracking hits the match limit, which abandons the match.
+    let mut re = safe.compile("^(a+)+$").unwrap();
+    assert_eq!(re.extra().unwrap().match_limit(), Some(safe.match_limit as usize));
+    assert_eq!(re.extra().unwrap().match_limit_recursion(), Some(safe.match_limit_recursion as usize));
+    assert_eq!(re.try_exec(&format!("{}b", "a".repeat(40))).unwrap_err(), ExecError::MatchLimit);
+    assert!(re.try_exec("aaaa").unwrap().is_some());
+
+    let rejected = |pattern: &str| safe.compile(pattern).unwrap_err();
+    match rejected(&"a".repeat(safe.max_pattern_len + 1)) {
+        SafeCompileError::PatternTooLong(len) => assert_eq!(len, safe.max_pattern_len + 1),
+        err => panic!("{:?}", err)
+    }
+    match rejected("ab(?C1)c") {
+        SafeCompileError::Callout(offset) => assert_eq!(offset, 2),
+        err => panic!("{:?}", err)
+    }
+    // Callout syntax that is escaped, quoted, in a class or in a comment is not a callout.
+    assert!(safe.compile("a\\(?C1\\)|\\Q(?C1)\\E|[(?C1)]|[](?C1)]|(?#(?C1)").is_ok());
+    match rejected("(*CRLF)(*UCP)\\w") {
+        SafeCompileError::StartSetting(offset, name) => assert_eq!((offset, name.as_str()), (7, "UCP")),
+        err => panic!("{:?}", err)
+    }
+    match rejected("(*LIMIT_RECURSION=100000)a") {
+        SafeCompileError::StartSetting(0, name) => assert_eq!(name, "LIMIT_RECURSION"),
+        err => panic!("{:?}", err)
+    }
+    assert!(safe.compile("(*CRLF)a$").is_ok());
+
+    let auto_callout = SafeCompileOptions { options: CompileOptions::AUTO_CALLOUT, ..safe };
+    match auto_callout.compile("a") {
+        Err(SafeCompileError::ForbiddenOptions(options)) => assert_eq!(options, CompileOptions::AUTO_CALLOUT),
+        result => panic!("{:?}", result.map(|re| re.size()))
+    }
+    let small = SafeCompileOptions { max_compiled_size: 16, ..safe };
+    match small.compile("abc|def|ghi") {
+        Err(SafeCompileError::CompiledTooLarge(size)) => assert!(size > 16),
+        result => panic!("{:?}", result.map(|re| re.size()))
+    }
+    if pcre::version().supports_stack_guard() {
+        match rejected(&format!("{}a{}", "(".repeat(200), ")".repeat(200))) {
+            SafeCompileError::Compilation(err) => assert_eq!(err.kind(), CompilationErrorKind::StackGuard),
+            err => panic!("{:?}", err)
+        }
+    }
+}
+
+#[test]
+fn test_redos_warnings() {
+    let warnings = |pattern: &str| pcre::redos_warnings(pattern, &CompileOptions::empty());
+    let kinds = |pattern: &str| warnings(pattern).iter().map(|warning| warning.kind).collect::<Vec<_>>();
+
+    let nested = [ReDosKind::NestedQuantifier];
+    for pattern in &["(a+)+", "^(a*)*$", "(?:\\w+\\s?)*x", "((ab)+)+c", "(?<n>[a-z]+\\d*)+:", "(x+x+)+y", "(.*a){1,}"] {
+        assert_eq!(kinds(pattern), nested, "{}", pattern);
+    }
+    let overlapping = [ReDosKind::OverlappingAlternatives];
+    for pattern in &["(a|a)*", "(a|aa)+b", "(\\w|\\d)+$", "(?:[a-c]|b|x)*", "(?i)(A|a)+$"] {
+        assert_eq!(kinds(pattern), overlapping, "{}", pattern);
+    }
+    // Repeats that cannot split the same text in several ways, or that do not backtrack.
+    for pattern in &["a+b+", "(ab+c)+", "(a|b)*", "(ab|ac)*", "(ab|ba)*", "(a+)++", "(?>a+)+", "(a+){2}", "\\(a+\\)+",
+                     "[(a+)+]", "\\Q(a+)+\\E", "(?#(a+)+)", "(a|ab)*", "(?x) ( a | a ) # (a+)+", "(a\\1?)+"] {
+        assert_eq!(kinds(pattern), [], "{}", pattern);
+    }
+    // Option settings change what the alternatives match.
+    assert_eq!(kinds("(A|a)+"), []);
+    assert_eq!(pcre::redos_warnings("(A|a)+", &CompileOptions::CASELESS).len(), 1);
+    // Backtracking within an atomic group or a lookahead is still reported.
+    assert_eq!(kinds("(?>(a+)+b)"), nested);
+    assert_eq!(kinds("(?=(a|a)*b)"), overlapping);
+
+    let warning = &warnings("x(?:é+)+?y")[0];
+    assert_eq!((warning.range.clone(), warning.inner.clone()), (1..10, 4..7));
+    assert_eq!(warning.suggestion, "(?>(?:é+)+?)");
+    assert_eq!(warning.to_string(), "nested quantifier at offset 1 may backtrack catastrophically; consider `(?>(?:é+)+?)`");
+
+    let warning = &warnings("^(\\d|[0-9a-f])*$")[0];
+    assert_eq!((warning.range.clone(), warning.inner.clone()), (1..15, 5..13));
+
+    // The suggestions compile and do not backtrack catastrophically.
+    let re = Pcre::compile("^(a|aa)+$").unwrap();
+    let suggestion = re.redos_warnings()[0].suggestion.clone();
+    let rewritten = Pcre::compile(&format!("^{}$", suggestion)).unwrap();
+    assert!(rewritten.exec(&format!("{}b", "a".repeat(64))).is_none());
+    assert!(rewritten.exec("aaaa").is_some());
+
+    // Invalid patterns are analyzed as far as possible.
+    for pattern in &["(a+)+)", "((a+)+", "[a", "\\", "(?", "a{2", "\\Q(a+", "\\x{41"] {
+        warnings(pattern);
+    }
+    assert_eq!(kinds("(a+)+)(b|b)*"), [ReDosKind::NestedQuantifier, ReDosKind::OverlappingAlternatives]);
+    assert!(warnings(&"(".repeat(100_000)).is_empty());
+    assert!(warnings(&"(?".repeat(100_000)).is_empty());
+    assert!(warnings(&"a)".repeat(100_000)).is_empty());
 }